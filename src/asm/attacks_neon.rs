@@ -7,186 +7,119 @@ use std::arch::aarch64::*;
 
 use super::neon_ops::*;
 
+/// Line masks through each square, the square itself excluded, indexed
+/// `[square][line]` with lines rank, file, diagonal and anti-diagonal
+static LINE_MASKS: [[u64; 4]; 64] = generate_line_masks();
+
+const RANK: usize = 0;
+const FILE: usize = 1;
+const DIAGONAL: usize = 2;
+const ANTI_DIAGONAL: usize = 3;
+
+/// Build the line masks by walking both ways along each line
+const fn generate_line_masks() -> [[u64; 4]; 64] {
+    // (rank delta, file delta) of one direction of each line
+    const LINES: [(i32, i32); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+    let mut masks = [[0u64; 4]; 64];
+    let mut sq = 0;
+
+    while sq < 64 {
+        let mut line = 0;
+        while line < 4 {
+            let (dr, df) = LINES[line];
+            let mut sign = -1;
+            while sign <= 1 {
+                let mut rank = (sq / 8) as i32 + sign * dr;
+                let mut file = (sq % 8) as i32 + sign * df;
+                while rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+                    masks[sq][line] |= 1u64 << (rank * 8 + file);
+                    rank += sign * dr;
+                    file += sign * df;
+                }
+                sign += 2;
+            }
+            line += 1;
+        }
+        sq += 1;
+    }
+
+    masks
+}
+
 /// Generate rook attacks using Neon-accelerated classical approach
 ///
 /// This uses the hyperbola quintessence algorithm with Neon optimization.
 #[inline(always)]
 pub fn rook_attacks_neon(square: u32, occupied: u64) -> u64 {
-    let rank_attacks = rank_attacks_inner(square, occupied);
-    let file_attacks = file_attacks_inner(square, occupied);
-    rank_attacks | file_attacks
+    let masks = &LINE_MASKS[square as usize];
+    line_attacks(square, occupied, masks[RANK]) | line_attacks(square, occupied, masks[FILE])
 }
 
 /// Generate bishop attacks using Neon-accelerated classical approach
 #[inline(always)]
 pub fn bishop_attacks_neon(square: u32, occupied: u64) -> u64 {
-    let diag_attacks = diagonal_attacks_inner(square, occupied);
-    let anti_diag_attacks = anti_diagonal_attacks_inner(square, occupied);
-    diag_attacks | anti_diag_attacks
+    let masks = &LINE_MASKS[square as usize];
+    line_attacks(square, occupied, masks[DIAGONAL])
+        | line_attacks(square, occupied, masks[ANTI_DIAGONAL])
 }
 
-/// Internal: Generate rank attacks using hyperbola quintessence
+/// Union of the attacks of every knight on the `knights` bitboard
+///
+/// On aarch64 the table entries are gathered four knights at a time and
+/// ORed in Neon registers; elsewhere the lookups are ORed one by one.
 #[inline(always)]
-fn rank_attacks_inner(square: u32, occupied: u64) -> u64 {
-    let rank = square / 8;
-    let file = square % 8;
-    let rank_mask = 0xFFu64 << (rank * 8);
-
-    let rank_occupied = (occupied & rank_mask) >> (rank * 8);
-    let file_bit = 1u64 << file;
-
-    // Special case for empty rank
-    if rank_occupied == 0 {
-        return rank_mask & !(1u64 << square);
+pub fn knight_attacks_multi(knights: u64) -> u64 {
+    use crate::bitboard::attacks::knight_attacks;
+    use crate::bitboard::Square;
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        let mut remaining = knights;
+        let mut attacks = 0u64;
+        while remaining != 0 {
+            let mut batch = [0u64; 4];
+            for slot in batch.iter_mut() {
+                if remaining == 0 {
+                    break;
+                }
+                *slot = knight_attacks(Square(bitscan_forward(remaining) as u8)).0;
+                remaining = reset_lsb(remaining);
+            }
+            attacks |= unsafe { neon_or_array(&batch) };
+        }
+        attacks
     }
-
-    // Forward fill
-    let mut forward = rank_occupied;
-    forward ^= file_bit;
-    forward = (forward << 1) | (forward >> 1);
-    forward &= !file_bit;
-
-    // Reverse fill
-    let mut reverse = rank_occupied;
-    reverse = reverse.reverse_bits() >> (64 - 8);
-    reverse ^= (file_bit.reverse_bits() >> (64 - 8));
-    reverse = (reverse << 1) | (reverse >> 1);
-    reverse &= !(file_bit.reverse_bits() >> (64 - 8));
-    reverse = reverse.reverse_bits() >> (64 - 8);
-
-    ((forward | reverse) << (rank * 8)) & rank_mask
-}
-
-/// Internal: Generate file attacks
-#[inline(always)]
-fn file_attacks_inner(square: u32, occupied: u64) -> u64 {
-    let file = square % 8;
-    let file_mask = 0x0101_0101_0101_0101u64 << file;
-
-    let square_bb = 1u64 << square;
-
-    // Special case for empty file
-    if occupied & file_mask == 0 {
-        return file_mask & !square_bb;
+    #[cfg(not(target_arch = "aarch64"))]
+    {
+        let mut remaining = knights;
+        let mut attacks = 0u64;
+        while remaining != 0 {
+            attacks |= knight_attacks(Square(bitscan_forward(remaining) as u8)).0;
+            remaining = reset_lsb(remaining);
+        }
+        attacks
     }
-
-    // Use similar hyperbola quintessence on the file
-    let forward = (occupied & file_mask) ^ square_bb;
-    let reverse = forward.reverse_bits();
-
-    // Simplified attack generation
-    (forward.wrapping_sub(square_bb)
-        ^ reverse
-            .wrapping_sub(square_bb.reverse_bits())
-            .reverse_bits())
-        & file_mask
 }
 
-/// Internal: Generate diagonal attacks
-#[inline(always)]
-fn diagonal_attacks_inner(square: u32, occupied: u64) -> u64 {
-    // Diagonal mask for the square
-    let diag = ((square as i32) / 8) - ((square as i32) % 8);
-    let mask = diagonal_mask(diag);
-
-    let square_bb = 1u64 << square;
-
-    // Special case for empty diagonal
-    if occupied & mask == 0 {
-        return mask & !square_bb;
-    }
-
-    let forward = (occupied & mask) ^ square_bb;
-    let reverse = forward.reverse_bits();
-
-    (forward.wrapping_sub(square_bb)
-        ^ reverse
-            .wrapping_sub(square_bb.reverse_bits())
-            .reverse_bits())
-        & mask
-}
-
-/// Internal: Generate anti-diagonal attacks
+/// Internal: Attacks along one line using hyperbola quintessence
+///
+/// Subtracting twice the slider from the line occupancy flips every bit up
+/// to and including the first blocker above the slider; doing the same on
+/// the bit-reversed board covers the squares below it. Full 64-bit reversal
+/// keeps every line (ranks included) a line, so one routine serves all four.
 #[inline(always)]
-fn anti_diagonal_attacks_inner(square: u32, occupied: u64) -> u64 {
-    let anti_diag = ((square as i32) / 8) + ((square as i32) % 8);
-    let mask = anti_diagonal_mask(anti_diag);
-
+fn line_attacks(square: u32, occupied: u64, mask: u64) -> u64 {
     let square_bb = 1u64 << square;
-
-    // Special case for empty anti-diagonal
-    if occupied & mask == 0 {
-        return mask & !square_bb;
-    }
-
-    let forward = (occupied & mask) ^ square_bb;
+    let forward = occupied & mask;
     let reverse = forward.reverse_bits();
 
-    (forward.wrapping_sub(square_bb)
+    (forward.wrapping_sub(square_bb.wrapping_mul(2))
         ^ reverse
-            .wrapping_sub(square_bb.reverse_bits())
+            .wrapping_sub(square_bb.reverse_bits().wrapping_mul(2))
             .reverse_bits())
         & mask
 }
 
-/// Get diagonal mask for a given diagonal index
-#[inline(always)]
-fn diagonal_mask(diag: i32) -> u64 {
-    const DIAGONALS: [u64; 15] = [
-        0x0000_0000_0000_0080,
-        0x0000_0000_0000_8040,
-        0x0000_0000_0080_4020,
-        0x0000_0000_8040_2010,
-        0x0000_0080_4020_1008,
-        0x0000_8040_2010_0804,
-        0x0080_4020_1008_0402,
-        0x8040_2010_0804_0201,
-        0x4020_1008_0804_0200,
-        0x2010_0804_0200_0000,
-        0x1008_0400_0000_0000,
-        0x0804_0000_0000_0000,
-        0x0400_0000_0000_0000,
-        0x0200_0000_0000_0000,
-        0x0100_0000_0000_0000,
-    ];
-
-    let index = (diag + 7) as usize;
-    if index < 15 {
-        DIAGONALS[index]
-    } else {
-        0
-    }
-}
-
-/// Get anti-diagonal mask for a given anti-diagonal index
-#[inline(always)]
-fn anti_diagonal_mask(anti_diag: i32) -> u64 {
-    const ANTI_DIAGONALS: [u64; 15] = [
-        0x0100_0000_0000_0000,
-        0x0201_0000_0000_0000,
-        0x0402_0100_0000_0000,
-        0x0804_0201_0000_0000,
-        0x1008_0402_0100_0000,
-        0x2010_0804_0201_0000,
-        0x4020_1008_0402_0100,
-        0x8040_2010_0804_0201,
-        0x0080_4020_1008_0402,
-        0x0000_8040_2010_0804,
-        0x0000_0080_4020_1008,
-        0x0000_0000_8040_2010,
-        0x0000_0000_0080_4020,
-        0x0000_0000_0000_8040,
-        0x0000_0000_0000_0080,
-    ];
-
-    let index = anti_diag as usize;
-    if index < 15 {
-        ANTI_DIAGONALS[index]
-    } else {
-        0
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -208,4 +141,48 @@ mod tests {
         // Should have diagonal attacks
         assert!(attacks != 0);
     }
+
+    #[test]
+    fn test_knight_attacks_multi() {
+        use crate::bitboard::attacks::knight_attacks;
+        use crate::bitboard::Square;
+
+        assert_eq!(knight_attacks_multi(0), 0);
+        // One, four (a full batch) and six knights (a partial second batch)
+        for knights in [1u64 << 28, 0x8100_0000_0000_0081, 0x0000_2400_0042_0081] {
+            let expected = (0..64)
+                .filter(|sq| knights & (1u64 << sq) != 0)
+                .fold(0u64, |acc, sq| acc | knight_attacks(Square(sq as u8)).0);
+            assert_eq!(knight_attacks_multi(knights), expected);
+        }
+    }
+
+    #[test]
+    fn test_matches_magic_attacks() {
+        use crate::bitboard::magic::{bishop_attacks_magic, init_magics, rook_attacks_magic};
+        use crate::bitboard::{Bitboard, Square};
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        init_magics();
+        let mut rng = StdRng::seed_from_u64(0x5EED);
+        for _ in 0..2000 {
+            // Sparse and dense boards
+            let occupied = match rng.gen_range(0..3) {
+                0 => rng.gen::<u64>() & rng.gen::<u64>() & rng.gen::<u64>(),
+                1 => rng.gen::<u64>() & rng.gen::<u64>(),
+                _ => rng.gen::<u64>(),
+            };
+            for sq in 0..64 {
+                let square = Square(sq as u8);
+                let (bishop, rook) = unsafe {
+                    (
+                        bishop_attacks_magic(square, Bitboard(occupied)),
+                        rook_attacks_magic(square, Bitboard(occupied)),
+                    )
+                };
+                assert_eq!(bishop_attacks_neon(sq, occupied), bishop.0, "bishop {} {:#x}", sq, occupied);
+                assert_eq!(rook_attacks_neon(sq, occupied), rook.0, "rook {} {:#x}", sq, occupied);
+            }
+        }
+    }
 }
@@ -15,6 +15,21 @@ static KING_ATTACKS: [Bitboard; 64] = generate_king_attacks();
 /// Precomputed pawn attack tables (one for each color)
 static PAWN_ATTACKS: [[Bitboard; 64]; 2] = generate_pawn_attacks();
 
+/// Precomputed squares strictly between two aligned squares
+static BETWEEN: [[Bitboard; 64]; 64] = generate_between();
+
+/// The eight ray directions as (rank delta, file delta, is diagonal)
+const RAY_DIRECTIONS: [(i32, i32, bool); 8] = [
+    (1, 0, false),
+    (-1, 0, false),
+    (0, 1, false),
+    (0, -1, false),
+    (1, 1, true),
+    (1, -1, true),
+    (-1, 1, true),
+    (-1, -1, true),
+];
+
 /// Generate knight attacks for all squares
 const fn generate_knight_attacks() -> [Bitboard; 64] {
     let mut attacks = [Bitboard::EMPTY; 64];
@@ -148,6 +163,99 @@ const fn generate_pawn_attacks() -> [[Bitboard; 64]; 2] {
     attacks
 }
 
+/// Generate the between-squares table by walking every ray from every square
+const fn generate_between() -> [[Bitboard; 64]; 64] {
+    let mut between = [[Bitboard::EMPTY; 64]; 64];
+    let mut sq = 0;
+
+    while sq < 64 {
+        let mut d = 0;
+        while d < RAY_DIRECTIONS.len() {
+            let (dr, df, _) = RAY_DIRECTIONS[d];
+            let mut rank = (sq / 8) as i32 + dr;
+            let mut file = (sq % 8) as i32 + df;
+            let mut ray = 0u64;
+
+            while rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+                let target = (rank * 8 + file) as usize;
+                between[sq][target].0 = ray;
+                ray |= 1u64 << target;
+                rank += dr;
+                file += df;
+            }
+
+            d += 1;
+        }
+
+        sq += 1;
+    }
+
+    between
+}
+
+/// A piece pinned against a king by an enemy slider
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Pin {
+    /// The pinned piece
+    pub pinned: Square,
+    /// The slider pinning it
+    pub pinner: Square,
+    /// Squares the pinned piece may move to: between the king and the
+    /// pinner, plus the pinner itself
+    pub ray: Bitboard,
+}
+
+/// Squares strictly between `a` and `b`, or empty if they don't share a
+/// rank, file or diagonal
+#[inline(always)]
+pub fn ray_between(a: Square, b: Square) -> Bitboard {
+    BETWEEN[a.0 as usize][b.0 as usize]
+}
+
+/// Pins against the king on `king`, one slot per ray direction
+///
+/// A pin is the first piece on a ray from the king followed by a slider
+/// that moves along that ray: `diagonal` holds the bishops and queens,
+/// `orthogonal` the rooks and queens. The first piece may be of either
+/// color, so callers filter `pinned` by side (their own pieces for legality,
+/// the other side's for discovered checks).
+pub fn pin_rays(
+    king: Square,
+    occupied: Bitboard,
+    diagonal: Bitboard,
+    orthogonal: Bitboard,
+) -> [Option<Pin>; 8] {
+    let mut pins = [None; 8];
+
+    for (slot, &(dr, df, is_diagonal)) in pins.iter_mut().zip(RAY_DIRECTIONS.iter()) {
+        let sliders = if is_diagonal { diagonal } else { orthogonal };
+        let mut rank = king.rank() as i32 + dr;
+        let mut file = king.file() as i32 + df;
+        let mut pinned = None;
+
+        while (0..8).contains(&rank) && (0..8).contains(&file) {
+            let sq = Square::new(file as u8, rank as u8);
+            if occupied.is_occupied(sq) {
+                match pinned {
+                    None => pinned = Some(sq),
+                    Some(pinned) => {
+                        if sliders.is_occupied(sq) {
+                            let mut ray = ray_between(king, sq);
+                            ray.set(sq);
+                            *slot = Some(Pin { pinned, pinner: sq, ray });
+                        }
+                        break;
+                    }
+                }
+            }
+            rank += dr;
+            file += df;
+        }
+    }
+
+    pins
+}
+
 /// Get knight attacks for a square
 #[inline(always)]
 pub fn knight_attacks(square: Square) -> Bitboard {
@@ -196,6 +304,22 @@ pub fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
     unsafe { crate::bitboard::magic::queen_attacks_magic(square, occupied) }
 }
 
+/// Squares attacked by a piece of `color` standing on `square`
+///
+/// Lets callers treat all piece types alike (`for piece in 0..6`); `color`
+/// only matters for pawns and `occupied` only for sliders.
+#[inline(always)]
+pub fn piece_attacks(piece: Piece, square: Square, color: Color, occupied: Bitboard) -> Bitboard {
+    match piece {
+        Piece::Pawn => pawn_attacks(square, color),
+        Piece::Knight => knight_attacks(square),
+        Piece::Bishop => bishop_attacks(square, occupied),
+        Piece::Rook => rook_attacks(square, occupied),
+        Piece::Queen => queen_attacks(square, occupied),
+        Piece::King => king_attacks(square),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -237,6 +361,36 @@ mod tests {
         assert_eq!(black_attacks.count(), 2);
     }
 
+    #[test]
+    fn test_ray_between() {
+        let between = ray_between(Square::A1, Square::D4);
+        assert_eq!(between.count(), 2);
+        assert!(between.is_occupied(Square::B2) && between.is_occupied(Square::C3));
+        assert_eq!(ray_between(Square::D4, Square::A1), between);
+        assert!(ray_between(Square::E1, Square::E2).is_empty());
+        assert!(ray_between(Square::A1, Square::B3).is_empty());
+    }
+
+    #[test]
+    fn test_rook_pins_knight_to_king() {
+        let mut occupied = Bitboard::EMPTY;
+        for sq in [Square::E1, Square::E4, Square::E8, Square::B4] {
+            occupied.set(sq);
+        }
+        let pins = pin_rays(Square::E1, occupied, Bitboard::EMPTY, Square::E8.to_bitboard());
+        let pins: Vec<Pin> = pins.iter().flatten().copied().collect();
+
+        assert_eq!(pins.len(), 1);
+        assert_eq!(pins[0].pinned, Square::E4);
+        assert_eq!(pins[0].pinner, Square::E8);
+        assert_eq!(pins[0].ray.count(), 7);
+        assert!(pins[0].ray.is_occupied(Square::E2) && pins[0].ray.is_occupied(Square::E8));
+
+        // A rook doesn't pin along a diagonal
+        let pins = pin_rays(Square::E1, occupied, Square::E8.to_bitboard(), Bitboard::EMPTY);
+        assert!(pins.iter().all(|pin| pin.is_none()));
+    }
+
     #[test]
     fn test_bishop_attacks_empty_board() {
         crate::bitboard::magic::init_magics();
@@ -258,4 +412,25 @@ mod tests {
         assert!(attacks.is_occupied(Square::A4));
         assert!(attacks.is_occupied(Square::H4));
     }
+
+    #[test]
+    fn test_piece_attacks_dispatch() {
+        crate::bitboard::magic::init_magics();
+        let mut occupied = Bitboard::EMPTY;
+        for sq in [Square::B2, Square::D6, Square::G4, Square::E7] {
+            occupied.set(sq);
+        }
+
+        for sq in (0..64).map(Square) {
+            for color in [Color::White, Color::Black] {
+                let attacks = |piece| piece_attacks(piece, sq, color, occupied);
+                assert_eq!(attacks(Piece::Pawn), pawn_attacks(sq, color));
+                assert_eq!(attacks(Piece::Knight), knight_attacks(sq));
+                assert_eq!(attacks(Piece::Bishop), bishop_attacks(sq, occupied));
+                assert_eq!(attacks(Piece::Rook), rook_attacks(sq, occupied));
+                assert_eq!(attacks(Piece::Queen), queen_attacks(sq, occupied));
+                assert_eq!(attacks(Piece::King), king_attacks(sq));
+            }
+        }
+    }
 }
@@ -40,13 +40,22 @@ static mut ROOK_MAGICS: [MagicEntry; 64] = [MagicEntry {
 static mut BISHOP_ATTACKS: [Bitboard; 5248] = [Bitboard::EMPTY; 5248];
 static mut ROOK_ATTACKS: [Bitboard; 102400] = [Bitboard::EMPTY; 102400];
 
+/// Guards the one-time table initialization
+static MAGICS_INIT: std::sync::Once = std::sync::Once::new();
+
 /// Initialize magic bitboard tables
-/// This must be called before using magic bitboard functions
+/// This must be called before using magic bitboard functions.
+/// Repeated or concurrent calls are safe; the tables are only built once.
 pub fn init_magics() {
-    unsafe {
+    MAGICS_INIT.call_once(|| unsafe {
         init_bishop_attacks();
         init_rook_attacks();
-    }
+    });
+}
+
+/// Whether `init_magics` has finished building the tables
+pub fn magics_initialized() -> bool {
+    MAGICS_INIT.is_completed()
 }
 
 /// Get bishop-relevant occupancy mask for a square
@@ -153,23 +162,31 @@ fn find_magic(mask: Bitboard, attack_fn: fn(Square, Bitboard) -> Bitboard, squar
         attacks[i as usize] = attack_fn(square, Bitboard(occupied));
     }
 
-    // Try random magic numbers until we find one that works
-    use std::collections::HashSet;
+    // Try random magic numbers until we find one that works.
+    // Two occupancies may share an index as long as they produce the same
+    // attack set (a constructive collision).
+    let mut used = vec![Bitboard::EMPTY; num_subsets as usize];
+    let mut epoch = vec![0u32; num_subsets as usize];
+    let mut attempt = 0u32;
+    let mut rng = MagicRng(0x9E37_79B9_7F4A_7C15 ^ ((square.0 as u64 + 1) << 32));
     loop {
-        let magic = rand::random::<u64>() & rand::random::<u64>() & rand::random::<u64>();
-        if (magic.wrapping_mul(mask.0)).count_ones() < 6 {
+        let magic = rng.sparse();
+        if (magic.wrapping_mul(mask.0) & 0xFF00_0000_0000_0000).count_ones() < 6 {
             continue; // Bad magic
         }
 
-        let mut used = HashSet::new();
+        // Slots written during an earlier attempt count as empty
+        attempt += 1;
         let mut ok = true;
-        for i in 0..num_subsets {
-            let index = (occupancies[i as usize].wrapping_mul(magic) >> (64 - bits)) as usize;
-            if used.contains(&index) {
+        for i in 0..num_subsets as usize {
+            let index = (occupancies[i].wrapping_mul(magic) >> (64 - bits)) as usize;
+            if epoch[index] != attempt {
+                epoch[index] = attempt;
+                used[index] = attacks[i];
+            } else if used[index] != attacks[i] {
                 ok = false;
                 break;
             }
-            used.insert(index);
         }
         if ok {
             return magic;
@@ -177,6 +194,26 @@ fn find_magic(mask: Bitboard, attack_fn: fn(Square, Bitboard) -> Bitboard, squar
     }
 }
 
+/// Small xorshift generator used for the magic search
+///
+/// `rand`'s thread RNG is far slower (especially in debug builds) and a fixed
+/// seed makes the table layout reproducible between runs.
+struct MagicRng(u64);
+
+impl MagicRng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Candidate magics work best with few set bits
+    fn sparse(&mut self) -> u64 {
+        self.next() & self.next() & self.next()
+    }
+}
+
 /// Initialize bishop attack tables
 unsafe fn init_bishop_attacks() {
     let mut offset = 0;
@@ -10,6 +10,9 @@ use crate::utils::zobrist::ZobristHash;
 use std::fmt;
 use std::str::FromStr;
 
+/// Game phase with all pieces on the board, see `Position::phase`
+pub const MAX_PHASE: u8 = 24;
+
 /// Position struct - encapsulates the full chess board state
 #[derive(Clone, PartialEq, Eq)]
 pub struct Position {
@@ -25,6 +28,18 @@ pub struct Position {
     pub halfmove_clock: u32,
     /// Fullmove number (starts at 1, incremented after Black's move)
     pub fullmove_number: u32,
+    /// Zobrist key of the pawn placement only (for the pawn hash table)
+    pub pawn_hash: u64,
+    /// Zobrist keys of the positions before each move made, oldest first
+    /// (for repetition detection)
+    pub hash_history: Vec<u64>,
+    /// Squares occupied by each color, kept in step with `pieces`
+    occupancy: [Bitboard; 2],
+    /// Occupant of each square, kept in step with `pieces` for `piece_at`
+    mailbox: [Option<(Piece, Color)>; 64],
+    /// Number of pieces of each type and color: [piece][color], kept in
+    /// step with `pieces` for `material`
+    piece_counts: [[u8; 2]; 6],
 }
 
 impl Position {
@@ -37,6 +52,11 @@ impl Position {
             en_passant: None,
             halfmove_clock: 0,
             fullmove_number: 1,
+            pawn_hash: 0,
+            hash_history: Vec::new(),
+            occupancy: [Bitboard::EMPTY; 2],
+            mailbox: [None; 64],
+            piece_counts: [[0; 2]; 6],
         }
     }
 
@@ -50,43 +70,49 @@ impl Position {
         let mut prev_castling = self.castling_rights;
         let mut prev_en_passant = self.en_passant;
         let mut prev_halfmove = self.halfmove_clock;
+        let prev_pawn_hash = self.pawn_hash;
+        self.hash_history.push(self.zobrist_hash().value());
 
         // Find the moving piece
-        let mut moving_piece = None;
-        for piece in 0..6 {
-            if self.pieces[piece][color as usize].is_occupied(from) {
-                moving_piece = Some(Piece::from_u8(piece as u8).unwrap());
-                break;
-            }
-        }
-        let moving_piece = moving_piece.expect("No moving piece found on from square");
+        let moving_piece = match self.piece_at(from) {
+            Some((piece, owner)) if owner == color => piece,
+            _ => panic!("No moving piece found on from square"),
+        };
 
         // Handle captures
-        for piece in 0..6 {
-            let opp = color.opposite() as usize;
-            if self.pieces[piece][opp].is_occupied(to) {
-                self.pieces[piece][opp].clear(to);
-                captured = Some(Piece::from_u8(piece as u8).unwrap());
-                break;
+        if let Some((piece, owner)) = self.piece_at(to) {
+            if owner == color.opposite() {
+                self.remove_from_board(piece as usize, owner as usize, to);
+                captured = Some(piece);
+                if piece == Piece::Pawn {
+                    self.toggle_pawn_key(owner, to);
+                }
             }
         }
 
         // Remove moving piece from source
-        self.pieces[moving_piece as usize][color as usize].clear(from);
+        self.remove_from_board(moving_piece as usize, color as usize, from);
+        if moving_piece == Piece::Pawn {
+            self.toggle_pawn_key(color, from);
+        }
 
         // Handle move types
         match mv.move_type() {
             crate::movegen::MoveType::Normal => {
-                self.pieces[moving_piece as usize][color as usize].set(to);
+                self.add_to_board(moving_piece as usize, color as usize, to);
+                if moving_piece == Piece::Pawn {
+                    self.toggle_pawn_key(color, to);
+                }
             }
             crate::movegen::MoveType::Promotion => {
                 // Remove pawn, add promoted piece
                 let promo = mv.promotion_piece();
-                self.pieces[Piece::Pawn as usize][color as usize].clear(from);
-                self.pieces[promo as usize][color as usize].set(to);
+                self.remove_from_board(Piece::Pawn as usize, color as usize, from);
+                self.add_to_board(promo as usize, color as usize, to);
             }
             crate::movegen::MoveType::EnPassant => {
-                self.pieces[moving_piece as usize][color as usize].set(to);
+                self.add_to_board(moving_piece as usize, color as usize, to);
+                self.toggle_pawn_key(color, to);
                 // Remove captured pawn
                 let ep_rank = if color == Color::White {
                     to.rank() - 1
@@ -94,32 +120,33 @@ impl Position {
                     to.rank() + 1
                 };
                 let ep_sq = Square::new(to.file(), ep_rank);
-                self.pieces[Piece::Pawn as usize][color.opposite() as usize].clear(ep_sq);
+                self.remove_from_board(Piece::Pawn as usize, color.opposite() as usize, ep_sq);
+                self.toggle_pawn_key(color.opposite(), ep_sq);
                 captured = Some(Piece::Pawn);
             }
             crate::movegen::MoveType::Castling => {
-                self.pieces[moving_piece as usize][color as usize].set(to);
+                self.add_to_board(moving_piece as usize, color as usize, to);
                 // Move rook as well
                 match (from, to) {
                     (Square::E1, Square::G1) => {
                         // White kingside
-                        self.pieces[Piece::Rook as usize][Color::White as usize].clear(Square::H1);
-                        self.pieces[Piece::Rook as usize][Color::White as usize].set(Square::F1);
+                        self.remove_from_board(Piece::Rook as usize, Color::White as usize, Square::H1);
+                        self.add_to_board(Piece::Rook as usize, Color::White as usize, Square::F1);
                     }
                     (Square::E1, Square::C1) => {
                         // White queenside
-                        self.pieces[Piece::Rook as usize][Color::White as usize].clear(Square::A1);
-                        self.pieces[Piece::Rook as usize][Color::White as usize].set(Square::D1);
+                        self.remove_from_board(Piece::Rook as usize, Color::White as usize, Square::A1);
+                        self.add_to_board(Piece::Rook as usize, Color::White as usize, Square::D1);
                     }
                     (Square::E8, Square::G8) => {
                         // Black kingside
-                        self.pieces[Piece::Rook as usize][Color::Black as usize].clear(Square::H8);
-                        self.pieces[Piece::Rook as usize][Color::Black as usize].set(Square::F8);
+                        self.remove_from_board(Piece::Rook as usize, Color::Black as usize, Square::H8);
+                        self.add_to_board(Piece::Rook as usize, Color::Black as usize, Square::F8);
                     }
                     (Square::E8, Square::C8) => {
                         // Black queenside
-                        self.pieces[Piece::Rook as usize][Color::Black as usize].clear(Square::A8);
-                        self.pieces[Piece::Rook as usize][Color::Black as usize].set(Square::D8);
+                        self.remove_from_board(Piece::Rook as usize, Color::Black as usize, Square::A8);
+                        self.add_to_board(Piece::Rook as usize, Color::Black as usize, Square::D8);
                     }
                     _ => {}
                 }
@@ -178,6 +205,7 @@ impl Position {
             prev_castling,
             prev_en_passant,
             prev_halfmove,
+            prev_pawn_hash,
         }
     }
 
@@ -200,37 +228,37 @@ impl Position {
         self.halfmove_clock = undo.prev_halfmove;
         self.castling_rights = undo.prev_castling;
         self.en_passant = undo.prev_en_passant;
+        self.pawn_hash = undo.prev_pawn_hash;
+        self.hash_history.pop();
 
         // Remove piece from destination
-        let mut moving_piece = None;
-        for piece in 0..6 {
-            if self.pieces[piece][color as usize].is_occupied(to) {
-                moving_piece = Some(Piece::from_u8(piece as u8).unwrap());
-                break;
-            }
-        }
-        let moving_piece = moving_piece.expect("No moving piece found on to square");
+        let moving_piece = match self.piece_at(to) {
+            Some((piece, owner)) if owner == color => piece,
+            _ => panic!("No moving piece found on to square"),
+        };
 
         // Remove from destination
-        self.pieces[moving_piece as usize][color as usize].clear(to);
+        self.remove_from_board(moving_piece as usize, color as usize, to);
 
-        // Restore captured piece if any
+        // Restore captured piece if any (an en passant victim is restored below)
         if let Some(captured) = undo.captured {
-            self.pieces[captured as usize][color.opposite() as usize].set(to);
+            if undo.mv.move_type() != crate::movegen::MoveType::EnPassant {
+                self.add_to_board(captured as usize, color.opposite() as usize, to);
+            }
         }
 
         // Restore moving piece to source
         match undo.mv.move_type() {
             crate::movegen::MoveType::Normal => {
-                self.pieces[moving_piece as usize][color as usize].set(from);
+                self.add_to_board(moving_piece as usize, color as usize, from);
             }
             crate::movegen::MoveType::Promotion => {
                 // Remove promoted piece, restore pawn
-                self.pieces[moving_piece as usize][color as usize].clear(to);
-                self.pieces[Piece::Pawn as usize][color as usize].set(from);
+                self.remove_from_board(moving_piece as usize, color as usize, to);
+                self.add_to_board(Piece::Pawn as usize, color as usize, from);
             }
             crate::movegen::MoveType::EnPassant => {
-                self.pieces[moving_piece as usize][color as usize].set(from);
+                self.add_to_board(moving_piece as usize, color as usize, from);
                 // Restore captured pawn
                 let ep_rank = if color == Color::White {
                     to.rank() - 1
@@ -238,31 +266,31 @@ impl Position {
                     to.rank() + 1
                 };
                 let ep_sq = Square::new(to.file(), ep_rank);
-                self.pieces[Piece::Pawn as usize][color.opposite() as usize].set(ep_sq);
+                self.add_to_board(Piece::Pawn as usize, color.opposite() as usize, ep_sq);
             }
             crate::movegen::MoveType::Castling => {
-                self.pieces[moving_piece as usize][color as usize].set(from);
+                self.add_to_board(moving_piece as usize, color as usize, from);
                 // Move rook back
                 match (from, to) {
                     (Square::E1, Square::G1) => {
                         // White kingside
-                        self.pieces[Piece::Rook as usize][Color::White as usize].clear(Square::F1);
-                        self.pieces[Piece::Rook as usize][Color::White as usize].set(Square::H1);
+                        self.remove_from_board(Piece::Rook as usize, Color::White as usize, Square::F1);
+                        self.add_to_board(Piece::Rook as usize, Color::White as usize, Square::H1);
                     }
                     (Square::E1, Square::C1) => {
                         // White queenside
-                        self.pieces[Piece::Rook as usize][Color::White as usize].clear(Square::D1);
-                        self.pieces[Piece::Rook as usize][Color::White as usize].set(Square::A1);
+                        self.remove_from_board(Piece::Rook as usize, Color::White as usize, Square::D1);
+                        self.add_to_board(Piece::Rook as usize, Color::White as usize, Square::A1);
                     }
                     (Square::E8, Square::G8) => {
                         // Black kingside
-                        self.pieces[Piece::Rook as usize][Color::Black as usize].clear(Square::F8);
-                        self.pieces[Piece::Rook as usize][Color::Black as usize].set(Square::H8);
+                        self.remove_from_board(Piece::Rook as usize, Color::Black as usize, Square::F8);
+                        self.add_to_board(Piece::Rook as usize, Color::Black as usize, Square::H8);
                     }
                     (Square::E8, Square::C8) => {
                         // Black queenside
-                        self.pieces[Piece::Rook as usize][Color::Black as usize].clear(Square::D8);
-                        self.pieces[Piece::Rook as usize][Color::Black as usize].set(Square::A8);
+                        self.remove_from_board(Piece::Rook as usize, Color::Black as usize, Square::D8);
+                        self.add_to_board(Piece::Rook as usize, Color::Black as usize, Square::A8);
                     }
                     _ => {}
                 }
@@ -270,11 +298,106 @@ impl Position {
         }
     }
 
+    /// Pass the turn without moving a piece, returning what `unmake_null_move`
+    /// needs to restore.
+    ///
+    /// Counts as a ply for the move counters and the hash history exactly like
+    /// `make_move`, so repetition checks and the Zobrist key stay consistent.
+    pub fn make_null_move(&mut self) -> NullUndo {
+        let color = self.side_to_move;
+        let undo = NullUndo {
+            prev_en_passant: self.en_passant,
+            prev_halfmove: self.halfmove_clock,
+        };
+        self.hash_history.push(self.zobrist_hash().value());
+
+        self.en_passant = None;
+        self.halfmove_clock += 1;
+        if color == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.side_to_move = color.opposite();
+
+        undo
+    }
+
+    /// Undo a null move made with `make_null_move`.
+    pub fn unmake_null_move(&mut self, undo: NullUndo) {
+        let color = self.side_to_move.opposite();
+        self.side_to_move = color;
+        if color == Color::Black {
+            self.fullmove_number -= 1;
+        }
+        self.halfmove_clock = undo.prev_halfmove;
+        self.en_passant = undo.prev_en_passant;
+        self.hash_history.pop();
+    }
+
+    /// Apply a move after checking it is legal, instead of trusting it like
+    /// `make_move`.
+    ///
+    /// The move is matched to a legal move by its squares and promotion
+    /// piece, so a move parsed without the board (as UCI sends them) is
+    /// played as the castling or en passant move it stands for.
+    pub fn make_move_checked(&mut self, mv: crate::movegen::Move) -> Result<Undo, MoveError> {
+        use crate::movegen::generator::generate_moves;
+        use crate::movegen::legal::is_legal_move;
+        use crate::movegen::MoveList;
+
+        let color = self.side_to_move;
+        if !self.occupancy(color).is_occupied(mv.from()) {
+            return Err(MoveError::NoPiece(mv));
+        }
+        // Only reachable from a position with the side not to move in check,
+        // and taking the king would leave a board without one
+        if self.piece_bb(Piece::King, color.opposite()).is_occupied(mv.to()) {
+            return Err(MoveError::KingCapture(mv));
+        }
+
+        let mut moves = MoveList::new();
+        generate_moves(&mut moves, self, color);
+        let same_move = |candidate: &crate::movegen::Move| {
+            candidate.from() == mv.from()
+                && candidate.to() == mv.to()
+                && candidate.is_promotion() == mv.is_promotion()
+                && (!mv.is_promotion() || candidate.promotion_piece() == mv.promotion_piece())
+        };
+        match moves.iter().copied().find(|candidate| same_move(candidate)) {
+            Some(legal) if is_legal_move(legal, self, color) => Ok(self.make_move(legal)),
+            _ => Err(MoveError::Illegal(mv)),
+        }
+    }
+
+    /// Apply a sequence of moves with `make_move_checked`, returning their
+    /// undo records in the order played.
+    ///
+    /// The moves are applied all or nothing: at the first one refused, the
+    /// ones before it are unmade and its error returned.
+    pub fn apply_moves(&mut self, moves: &[crate::movegen::Move]) -> Result<Vec<Undo>, MoveError> {
+        let mut undos = Vec::with_capacity(moves.len());
+        for &mv in moves {
+            match self.make_move_checked(mv) {
+                Ok(undo) => undos.push(undo),
+                Err(err) => {
+                    while let Some(undo) = undos.pop() {
+                        self.unmake_move(undo);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(undos)
+    }
+
     /// Parse a FEN string and set the position accordingly.
+    ///
+    /// The halfmove clock and fullmove number may be left out (they default
+    /// to 0 and 1), and anything after the sixth field is ignored. An en
+    /// passant square no pawn can just have double-pushed past is dropped.
     pub fn set_fen(&mut self, fen: &str) -> Result<(), String> {
-        let parts: Vec<&str> = fen.trim().split_whitespace().collect();
+        let parts: Vec<&str> = fen.split_whitespace().take(6).collect();
         if parts.len() < 4 {
-            return Err("FEN string must have at least 4 fields".to_string());
+            return Err(format!("FEN string must have at least 4 fields, got {}", parts.len()));
         }
 
         // Clear the board
@@ -295,28 +418,15 @@ impl Position {
                 '1'..='8' => {
                     file += c.to_digit(10).unwrap() as u8;
                 }
-                'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => {
-                    let color = if c.is_uppercase() {
-                        Color::White
-                    } else {
-                        Color::Black
-                    };
-                    let piece = match c.to_ascii_lowercase() {
-                        'p' => Piece::Pawn,
-                        'n' => Piece::Knight,
-                        'b' => Piece::Bishop,
-                        'r' => Piece::Rook,
-                        'q' => Piece::Queen,
-                        'k' => Piece::King,
-                        _ => return Err(format!("Invalid piece char: {}", c)),
-                    };
+                _ => {
+                    let (piece, color) =
+                        Piece::from_char(c).ok_or_else(|| format!("Invalid FEN char: {}", c))?;
                     if file > 7 || rank > 7 {
                         return Err("Invalid FEN: file or rank out of bounds".to_string());
                     }
                     self.set_piece(piece, color, Square::new(file as u8, rank as u8));
                     file += 1;
                 }
-                _ => return Err(format!("Invalid FEN char: {}", c)),
             }
         }
         if rank != 0 || file != 8 {
@@ -324,25 +434,15 @@ impl Position {
         }
 
         // Side to move
-        self.side_to_move = match parts[1] {
-            "w" => Color::White,
-            "b" => Color::Black,
-            _ => return Err("Invalid FEN: side to move".to_string()),
+        let mut side_chars = parts[1].chars();
+        self.side_to_move = match (side_chars.next().and_then(Color::from_char), side_chars.next()) {
+            (Some(color), None) => color,
+            _ => return Err(format!("Invalid FEN side to move '{}': expected 'w' or 'b'", parts[1])),
         };
 
         // Castling rights
-        self.castling_rights = CastleRights(0);
-        if parts[2] != "-" {
-            for c in parts[2].chars() {
-                match c {
-                    'K' => self.castling_rights.add(CastleRights::WHITE_KING),
-                    'Q' => self.castling_rights.add(CastleRights::WHITE_QUEEN),
-                    'k' => self.castling_rights.add(CastleRights::BLACK_KING),
-                    'q' => self.castling_rights.add(CastleRights::BLACK_QUEEN),
-                    _ => return Err(format!("Invalid castling char: {}", c)),
-                }
-            }
-        }
+        self.castling_rights = CastleRights::from_str(parts[2])
+            .map_err(|err| format!("Invalid FEN castling field '{}': {}", parts[2], err))?;
 
         // En passant
         self.en_passant = if parts[3] == "-" {
@@ -363,7 +463,8 @@ impl Position {
                 _ => return Err("Invalid FEN: en passant rank".to_string()),
             };
             Some(Square::new(file_idx, rank_idx))
-        };
+        }
+        .filter(|&ep| self.is_double_push_target(ep));
 
         // Halfmove clock
         self.halfmove_clock = if parts.len() > 4 {
@@ -382,6 +483,24 @@ impl Position {
         Ok(())
     }
 
+    /// Whether `ep` is the square an enemy pawn just skipped over: on the
+    /// sixth rank (third for Black to move), empty along with the pawn's
+    /// start square, and with the pawn in front of it.
+    ///
+    /// Like `make_move`, this does not ask for a pawn able to capture.
+    fn is_double_push_target(&self, ep: Square) -> bool {
+        let enemy = self.side_to_move.opposite();
+        let (ep_rank, pawn, start) = match self.side_to_move {
+            Color::White => (5, ep.0.wrapping_sub(8), ep.0 + 8),
+            Color::Black => (2, ep.0 + 8, ep.0.wrapping_sub(8)),
+        };
+        let occupied = self.all_occupancy();
+        ep.rank() == ep_rank
+            && self.piece_bb(Piece::Pawn, enemy).is_occupied(Square(pawn))
+            && !occupied.is_occupied(ep)
+            && !occupied.is_occupied(Square(start))
+    }
+
     /// Generate a FEN string from the current position.
     pub fn to_fen(&self) -> String {
         let mut fen = String::new();
@@ -399,22 +518,9 @@ impl Position {
                                 fen.push_str(&empty.to_string());
                                 empty = 0;
                             }
-                            let symbol =
-                                match (Piece::from_u8(piece as u8), Color::from_u8(color as u8)) {
-                                    (Some(Piece::Pawn), Color::White) => 'P',
-                                    (Some(Piece::Pawn), Color::Black) => 'p',
-                                    (Some(Piece::Knight), Color::White) => 'N',
-                                    (Some(Piece::Knight), Color::Black) => 'n',
-                                    (Some(Piece::Bishop), Color::White) => 'B',
-                                    (Some(Piece::Bishop), Color::Black) => 'b',
-                                    (Some(Piece::Rook), Color::White) => 'R',
-                                    (Some(Piece::Rook), Color::Black) => 'r',
-                                    (Some(Piece::Queen), Color::White) => 'Q',
-                                    (Some(Piece::Queen), Color::Black) => 'q',
-                                    (Some(Piece::King), Color::White) => 'K',
-                                    (Some(Piece::King), Color::Black) => 'k',
-                                    _ => '?',
-                                };
+                            let symbol = Piece::from_u8(piece as u8)
+                                .unwrap()
+                                .to_char(Color::from_u8(color as u8));
                             fen.push(symbol);
                             found = true;
                         }
@@ -434,31 +540,11 @@ impl Position {
 
         // Side to move
         fen.push(' ');
-        fen.push(match self.side_to_move {
-            Color::White => 'w',
-            Color::Black => 'b',
-        });
+        fen.push(self.side_to_move.to_char());
 
         // Castling rights
         fen.push(' ');
-        let mut rights = String::new();
-        if self.castling_rights.has(CastleRights::WHITE_KING) {
-            rights.push('K');
-        }
-        if self.castling_rights.has(CastleRights::WHITE_QUEEN) {
-            rights.push('Q');
-        }
-        if self.castling_rights.has(CastleRights::BLACK_KING) {
-            rights.push('k');
-        }
-        if self.castling_rights.has(CastleRights::BLACK_QUEEN) {
-            rights.push('q');
-        }
-        if rights.is_empty() {
-            fen.push('-');
-        } else {
-            fen.push_str(&rights);
-        }
+        fen.push_str(&self.castling_rights.to_string());
 
         // En passant
         fen.push(' ');
@@ -516,14 +602,362 @@ impl Position {
         ZobristHash(hash)
     }
 
+    /// Whether `other` is the same position for play: same pieces, side to
+    /// move, castling rights and en passant square
+    ///
+    /// Unlike `==` this ignores the move counters and the game history, so
+    /// it holds between transpositions reached in different move counts.
+    pub fn same_position(&self, other: &Position) -> bool {
+        self.pieces == other.pieces
+            && self.side_to_move == other.side_to_move
+            && self.castling_rights == other.castling_rights
+            && self.en_passant == other.en_passant
+    }
+
+    /// Check whether the current position occurred before in the game.
+    ///
+    /// Only the last `halfmove_clock` positions can match (a capture or pawn
+    /// move makes earlier ones unreachable), and only every other one has the
+    /// same side to move, so the scan is bounded by the halfmove clock.
+    pub fn is_repetition(&self) -> bool {
+        let key = self.zobrist_hash().value();
+        let window = (self.halfmove_clock as usize).min(self.hash_history.len());
+
+        (2..=window)
+            .step_by(2)
+            .any(|back| self.hash_history[self.hash_history.len() - back] == key)
+    }
+
+    /// Check whether the current position occurred at least twice before,
+    /// the threefold repetition draw.
+    pub fn is_threefold_repetition(&self) -> bool {
+        let key = self.zobrist_hash().value();
+        let window = (self.halfmove_clock as usize).min(self.hash_history.len());
+
+        (2..=window)
+            .step_by(2)
+            .filter(|&back| self.hash_history[self.hash_history.len() - back] == key)
+            .count()
+            >= 2
+    }
+
+    /// Result of the game if the position ends it, for adjudicating games.
+    ///
+    /// Checkmate (lost by the side to move) and stalemate come first, then
+    /// the fifty-move rule, threefold repetition and insufficient material.
+    pub fn game_result(&self) -> Option<GameResult> {
+        use crate::eval::material::has_mating_material;
+        use crate::movegen::generator::generate_moves;
+        use crate::movegen::legal::is_legal_move;
+        use crate::movegen::MoveList;
+
+        let color = self.side_to_move;
+        let mut moves = MoveList::new();
+        generate_moves(&mut moves, self, color);
+        if !moves.iter().any(|&mv| is_legal_move(mv, self, color)) {
+            let in_check = self
+                .piece_bb(Piece::King, color)
+                .lsb()
+                .is_some_and(|king_sq| self.attacked_by(color.opposite()).is_occupied(king_sq));
+            return Some(match (in_check, color) {
+                (false, _) => GameResult::Draw,
+                (true, Color::White) => GameResult::BlackWins,
+                (true, Color::Black) => GameResult::WhiteWins,
+            });
+        }
+
+        let mating_material = has_mating_material(
+            self.piece_bb(Piece::Pawn, Color::White),
+            self.piece_bb(Piece::Knight, Color::White),
+            self.piece_bb(Piece::Bishop, Color::White),
+            self.piece_bb(Piece::Rook, Color::White),
+            self.piece_bb(Piece::Queen, Color::White),
+            self.piece_bb(Piece::Pawn, Color::Black),
+            self.piece_bb(Piece::Knight, Color::Black),
+            self.piece_bb(Piece::Bishop, Color::Black),
+            self.piece_bb(Piece::Rook, Color::Black),
+            self.piece_bb(Piece::Queen, Color::Black),
+        );
+        if self.halfmove_clock >= 100 || self.is_threefold_repetition() || !mating_material {
+            return Some(GameResult::Draw);
+        }
+
+        None
+    }
+
+    /// Game phase from the non-pawn material left on the board, from 24 (all
+    /// pieces, the opening) down to 0 (bare kings and pawns).
+    ///
+    /// Knights and bishops count 1, rooks 2 and queens 4; promoted pieces can
+    /// push the sum past 24, so it is capped.
+    pub fn phase(&self) -> u8 {
+        const WEIGHTS: [(Piece, u32); 4] = [
+            (Piece::Knight, 1),
+            (Piece::Bishop, 1),
+            (Piece::Rook, 2),
+            (Piece::Queen, 4),
+        ];
+
+        let phase: u32 = WEIGHTS
+            .iter()
+            .map(|&(piece, weight)| {
+                let count = self.piece_bb(piece, Color::White).count()
+                    + self.piece_bb(piece, Color::Black).count();
+                count * weight
+            })
+            .sum();
+        phase.min(MAX_PHASE as u32) as u8
+    }
+
+    /// Compute the pawn-only Zobrist key from scratch.
+    pub fn compute_pawn_hash(&self) -> u64 {
+        use crate::utils::zobrist::ZOBRIST_PIECE_SQUARE;
+
+        let mut hash = 0u64;
+        for color in 0..2 {
+            for sq in self.pieces[Piece::Pawn as usize][color].iter() {
+                hash ^= ZOBRIST_PIECE_SQUARE[Piece::Pawn as usize][color][sq.0 as usize];
+            }
+        }
+        hash
+    }
+
+    /// Toggle a pawn of the given color on `sq` in the pawn key.
+    #[inline(always)]
+    fn toggle_pawn_key(&mut self, color: Color, sq: Square) {
+        use crate::utils::zobrist::ZOBRIST_PIECE_SQUARE;
+        self.pawn_hash ^= ZOBRIST_PIECE_SQUARE[Piece::Pawn as usize][color as usize][sq.0 as usize];
+    }
+
+    /// Set a piece bit, its color's occupancy bit, its mailbox square and
+    /// its count if the piece wasn't there.
+    #[inline(always)]
+    fn add_to_board(&mut self, piece: usize, color: usize, sq: Square) {
+        if !self.pieces[piece][color].is_occupied(sq) {
+            self.pieces[piece][color].set(sq);
+            self.occupancy[color].set(sq);
+            self.mailbox[sq.0 as usize] = Piece::from_u8(piece as u8).map(|piece| (piece, Color::from_u8(color as u8)));
+            self.piece_counts[piece][color] += 1;
+        }
+    }
+
+    /// Clear a piece bit, its color's occupancy bit, its mailbox square and
+    /// its count if the piece was there.
+    #[inline(always)]
+    fn remove_from_board(&mut self, piece: usize, color: usize, sq: Square) {
+        if self.pieces[piece][color].is_occupied(sq) {
+            self.pieces[piece][color].clear(sq);
+            self.occupancy[color].clear(sq);
+            self.mailbox[sq.0 as usize] = None;
+            self.piece_counts[piece][color] -= 1;
+        }
+    }
+
+    /// Rebuild the occupancy bitboards, the mailbox and the piece counts
+    /// from the piece bitboards.
+    fn refresh_occupancy(&mut self) {
+        for color in 0..2 {
+            self.occupancy[color] = (0..6).fold(Bitboard::EMPTY, |acc, piece| acc | self.pieces[piece][color]);
+        }
+        self.mailbox = [None; 64];
+        for piece in 0..6 {
+            for color in 0..2 {
+                self.piece_counts[piece][color] = self.pieces[piece][color].count() as u8;
+                for sq in self.pieces[piece][color].iter() {
+                    self.mailbox[sq.0 as usize] =
+                        Piece::from_u8(piece as u8).map(|piece| (piece, Color::from_u8(color as u8)));
+                }
+            }
+        }
+    }
+
     /// Place a piece on the board.
     pub fn set_piece(&mut self, piece: Piece, color: Color, sq: Square) {
-        self.pieces[piece as usize][color as usize].set(sq);
+        if piece == Piece::Pawn && !self.pieces[piece as usize][color as usize].is_occupied(sq) {
+            self.toggle_pawn_key(color, sq);
+        }
+        self.add_to_board(piece as usize, color as usize, sq);
     }
 
     /// Remove a piece from the board.
     pub fn remove_piece(&mut self, piece: Piece, color: Color, sq: Square) {
-        self.pieces[piece as usize][color as usize].clear(sq);
+        if piece == Piece::Pawn && self.pieces[piece as usize][color as usize].is_occupied(sq) {
+            self.toggle_pawn_key(color, sq);
+        }
+        self.remove_from_board(piece as usize, color as usize, sq);
+    }
+
+    /// Squares occupied by the pieces of one color.
+    #[inline(always)]
+    pub fn occupancy(&self, color: Color) -> Bitboard {
+        self.occupancy[color as usize]
+    }
+
+    /// Squares occupied by any piece.
+    #[inline(always)]
+    pub fn all_occupancy(&self) -> Bitboard {
+        self.occupancy[0] | self.occupancy[1]
+    }
+
+    /// Squares attacked by the pieces of `color`.
+    ///
+    /// Computed from scratch on each call, so callers that need the map
+    /// more than once in a node should keep the result.
+    pub fn attacked_by(&self, color: Color) -> Bitboard {
+        use crate::bitboard::attacks::piece_attacks;
+
+        let occupied = self.all_occupancy();
+        let mut attacked = Bitboard::EMPTY;
+        for piece in 0..6 {
+            let piece = Piece::from_u8(piece).unwrap();
+            for sq in self.piece_bb(piece, color).iter() {
+                attacked |= piece_attacks(piece, sq, color, occupied);
+            }
+        }
+        attacked
+    }
+
+    /// Enemy pieces giving check to the king of the side to move.
+    ///
+    /// Two checkers (double check) leave only king moves; a single checker
+    /// can also be captured, or blocked on `ray_between` if it is a slider.
+    pub fn checkers(&self) -> Bitboard {
+        use crate::bitboard::attacks::{bishop_attacks, knight_attacks, pawn_attacks, rook_attacks};
+
+        let color = self.side_to_move;
+        let enemy = color.opposite();
+        let Some(king) = self.piece_bb(Piece::King, color).lsb() else {
+            return Bitboard::EMPTY;
+        };
+        let occupied = self.all_occupancy();
+        let queens = self.piece_bb(Piece::Queen, enemy);
+
+        (pawn_attacks(king, color) & self.piece_bb(Piece::Pawn, enemy))
+            | (knight_attacks(king) & self.piece_bb(Piece::Knight, enemy))
+            | (bishop_attacks(king, occupied) & (self.piece_bb(Piece::Bishop, enemy) | queens))
+            | (rook_attacks(king, occupied) & (self.piece_bb(Piece::Rook, enemy) | queens))
+    }
+
+    /// Pieces of both colors attacking `sq`, with sliders seeing through
+    /// `occupied` rather than the board, so removed pieces uncover x-rays.
+    pub fn attackers_to(&self, sq: Square, occupied: Bitboard) -> Bitboard {
+        use crate::bitboard::attacks::{bishop_attacks, king_attacks, knight_attacks, pawn_attacks, rook_attacks};
+
+        let pieces = |piece: Piece| self.piece_bb(piece, Color::White) | self.piece_bb(piece, Color::Black);
+        let queens = pieces(Piece::Queen);
+
+        (pawn_attacks(sq, Color::Black) & self.piece_bb(Piece::Pawn, Color::White))
+            | (pawn_attacks(sq, Color::White) & self.piece_bb(Piece::Pawn, Color::Black))
+            | (knight_attacks(sq) & pieces(Piece::Knight))
+            | (king_attacks(sq) & pieces(Piece::King))
+            | (bishop_attacks(sq, occupied) & (pieces(Piece::Bishop) | queens))
+            | (rook_attacks(sq, occupied) & (pieces(Piece::Rook) | queens))
+    }
+
+    /// Static exchange value of `mv` in centipawns: the material the side
+    /// to move wins (or, if negative, loses) when both sides keep
+    /// recapturing on the destination square with their least valuable
+    /// piece, each stopping once recapturing would lose.
+    ///
+    /// A quiet move scores what it loses to the exchange that follows, so
+    /// 0 if its destination is safe.
+    pub fn see(&self, mv: crate::movegen::Move) -> i32 {
+        use crate::eval::material::piece_value;
+
+        let (from, to) = (mv.from(), mv.to());
+        let piece_on = |sq: Square| self.piece_at(sq).map(|(piece, _)| piece);
+        let Some(mut attacker) = piece_on(from) else {
+            return 0;
+        };
+
+        let mut occupied = self.all_occupancy();
+        occupied.clear(from);
+        let mut gains = [0i32; 32];
+        gains[0] = if mv.is_en_passant() {
+            let captured = if self.side_to_move == Color::White { to.0 - 8 } else { to.0 + 8 };
+            occupied.clear(Square(captured));
+            piece_value(Piece::Pawn)
+        } else {
+            piece_on(to).map_or(0, piece_value)
+        };
+        if mv.is_promotion() {
+            attacker = mv.promotion_piece();
+            gains[0] += piece_value(attacker) - piece_value(Piece::Pawn);
+        }
+
+        // Each entry is what the side recapturing at that depth gains if it
+        // does, taking the piece that captured last
+        let mut side = self.side_to_move.opposite();
+        let mut depth = 0;
+        while depth + 1 < gains.len() {
+            let attackers = self.attackers_to(to, occupied) & occupied & self.occupancy(side);
+            let Some((next, sq)) = (0..6)
+                .filter_map(Piece::from_u8)
+                .find_map(|piece| (attackers & self.piece_bb(piece, side)).lsb().map(|sq| (piece, sq)))
+            else {
+                break;
+            };
+            // The king may only recapture if nothing can take it back
+            if next == Piece::King {
+                let defenders = self.attackers_to(to, occupied) & occupied & self.occupancy(side.opposite());
+                if !defenders.is_empty() {
+                    break;
+                }
+            }
+            depth += 1;
+            gains[depth] = piece_value(attacker) - gains[depth - 1];
+            occupied.clear(sq);
+            attacker = next;
+            side = side.opposite();
+        }
+
+        // Either side may stop recapturing when carrying on loses material
+        while depth > 0 {
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+            depth -= 1;
+        }
+        gains[0]
+    }
+
+    /// Whether `color` has a knight, bishop, rook or queen. With only king
+    /// and pawns, zugzwang is common enough that passing the turn (as
+    /// null-move pruning does) is no safe guess of the worst case.
+    pub fn has_non_pawn_material(&self, color: Color) -> bool {
+        [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+            .iter()
+            .any(|&piece| !self.piece_bb(piece, color).is_empty())
+    }
+
+    /// Whether the side to move is out of check and has no legal capture
+    /// or promotion, so its static evaluation can be trusted as it stands.
+    pub fn is_quiet(&self) -> bool {
+        use crate::movegen::generator::generate_captures;
+        use crate::movegen::legal::is_legal_move;
+        use crate::movegen::MoveList;
+
+        if !self.checkers().is_empty() {
+            return false;
+        }
+        let color = self.side_to_move;
+        let mut moves = MoveList::new();
+        generate_captures(&mut moves, self, color);
+        !moves.iter().any(|&mv| is_legal_move(mv, self, color))
+    }
+
+    /// Number of pieces of a given type and color.
+    #[inline(always)]
+    pub fn piece_count(&self, piece: Piece, color: Color) -> u32 {
+        self.piece_counts[piece as usize][color as usize] as u32
+    }
+
+    /// Material balance from White's perspective with `values` indexed by
+    /// piece, read from the piece counts kept up to date by every move.
+    pub fn material(&self, values: &[i32; 6]) -> i32 {
+        values
+            .iter()
+            .zip(self.piece_counts.iter())
+            .map(|(&value, counts)| (counts[0] as i32 - counts[1] as i32) * value)
+            .sum()
     }
 
     /// Get the bitboard for a given piece and color.
@@ -531,6 +965,59 @@ impl Position {
         self.pieces[piece as usize][color as usize]
     }
 
+    /// The piece standing on `sq` and its color, if any
+    #[inline(always)]
+    pub fn piece_at(&self, sq: Square) -> Option<(Piece, Color)> {
+        let occupant = self.mailbox[sq.0 as usize];
+        debug_assert_eq!(occupant, self.piece_at_from_bitboards(sq), "mailbox out of step on {:?}", sq);
+        occupant
+    }
+
+    /// `piece_at` looked up in the piece bitboards, which stay authoritative
+    fn piece_at_from_bitboards(&self, sq: Square) -> Option<(Piece, Color)> {
+        let color = if self.occupancy(Color::White).is_occupied(sq) {
+            Color::White
+        } else if self.occupancy(Color::Black).is_occupied(sq) {
+            Color::Black
+        } else {
+            return None;
+        };
+        (0..6)
+            .filter_map(Piece::from_u8)
+            .find(|&piece| self.piece_bb(piece, color).is_occupied(sq))
+            .map(|piece| (piece, color))
+    }
+
+    /// Flip the board vertically and swap the colors of everything on it.
+    ///
+    /// The result is the same position seen from the other side: side to move,
+    /// castling rights and the en passant square are mirrored too, so a
+    /// color-blind evaluation scores both positions identically.
+    pub fn mirror(&self) -> Position {
+        let mut mirrored = self.clone();
+
+        for piece in 0..6 {
+            for color in 0..2 {
+                // Reversing the bytes of a bitboard reverses the order of its ranks
+                mirrored.pieces[piece][color] = Bitboard(self.pieces[piece][1 - color].0.swap_bytes());
+            }
+        }
+
+        mirrored.side_to_move = self.side_to_move.opposite();
+
+        // White rights live in the low two bits, black rights in the next two
+        let rights = self.castling_rights.0;
+        mirrored.castling_rights = CastleRights(((rights & 0b0011) << 2) | ((rights & 0b1100) >> 2));
+
+        mirrored.en_passant = self.en_passant.map(Square::flip_rank);
+        mirrored.refresh_occupancy();
+        mirrored.pawn_hash = mirrored.compute_pawn_hash();
+        // Keys of earlier positions don't carry over to the mirrored game
+        mirrored.hash_history.clear();
+
+        mirrored
+    }
+
     /// Set up the standard chess starting position.
     pub fn set_startpos(&mut self) {
         use super::types::*;
@@ -574,6 +1061,38 @@ impl Position {
     }
 }
 
+/// Final result of a game
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    /// White won
+    WhiteWins,
+    /// Black won
+    BlackWins,
+    /// Drawn
+    Draw,
+}
+
+/// Why `make_move_checked` refused a move
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MoveError {
+    /// No piece of the side to move stands on the from-square
+    NoPiece(crate::movegen::Move),
+    /// The move is not legal in the position
+    Illegal(crate::movegen::Move),
+    /// The move would capture the enemy king
+    KingCapture(crate::movegen::Move),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::NoPiece(mv) => write!(f, "no piece to move for {}", mv),
+            MoveError::Illegal(mv) => write!(f, "illegal move {}", mv),
+            MoveError::KingCapture(mv) => write!(f, "move {} captures the king", mv),
+        }
+    }
+}
+
 /// Undo information for unmaking a move.
 #[derive(Clone, Debug)]
 pub struct Undo {
@@ -582,6 +1101,17 @@ pub struct Undo {
     pub prev_castling: CastleRights,
     pub prev_en_passant: Option<Square>,
     pub prev_halfmove: u32,
+    /// Pawn key before the move
+    pub prev_pawn_hash: u64,
+}
+
+/// State needed to undo a null move
+#[derive(Clone, Copy, Debug)]
+pub struct NullUndo {
+    /// En passant square before the null move
+    pub prev_en_passant: Option<Square>,
+    /// Halfmove clock before the null move
+    pub prev_halfmove: u32,
 }
 
 impl fmt::Debug for Position {
@@ -590,21 +1120,9 @@ impl fmt::Debug for Position {
         for piece in 0..6 {
             for color in 0..2 {
                 let mut bb = self.pieces[piece][color];
-                let symbol = match (Piece::from_u8(piece as u8), Color::from_u8(color as u8)) {
-                    (Some(Piece::Pawn), Color::White) => 'P',
-                    (Some(Piece::Pawn), Color::Black) => 'p',
-                    (Some(Piece::Knight), Color::White) => 'N',
-                    (Some(Piece::Knight), Color::Black) => 'n',
-                    (Some(Piece::Bishop), Color::White) => 'B',
-                    (Some(Piece::Bishop), Color::Black) => 'b',
-                    (Some(Piece::Rook), Color::White) => 'R',
-                    (Some(Piece::Rook), Color::Black) => 'r',
-                    (Some(Piece::Queen), Color::White) => 'Q',
-                    (Some(Piece::Queen), Color::Black) => 'q',
-                    (Some(Piece::King), Color::White) => 'K',
-                    (Some(Piece::King), Color::Black) => 'k',
-                    _ => '?',
-                };
+                let symbol = Piece::from_u8(piece as u8)
+                    .unwrap()
+                    .to_char(Color::from_u8(color as u8));
                 while let Some(sq) = bb.pop_lsb() {
                     let file = sq.file() as usize;
                     let rank = sq.rank() as usize;
@@ -629,6 +1147,50 @@ impl fmt::Debug for Position {
     }
 }
 
+/// Unicode chess glyph for a piece
+fn piece_glyph(piece: Piece, color: Color) -> char {
+    match (piece, color) {
+        (Piece::King, Color::White) => '♔',
+        (Piece::Queen, Color::White) => '♕',
+        (Piece::Rook, Color::White) => '♖',
+        (Piece::Bishop, Color::White) => '♗',
+        (Piece::Knight, Color::White) => '♘',
+        (Piece::Pawn, Color::White) => '♙',
+        (Piece::King, Color::Black) => '♚',
+        (Piece::Queen, Color::Black) => '♛',
+        (Piece::Rook, Color::Black) => '♜',
+        (Piece::Bishop, Color::Black) => '♝',
+        (Piece::Knight, Color::Black) => '♞',
+        (Piece::Pawn, Color::Black) => '♟',
+    }
+}
+
+/// Board diagram with Unicode piece glyphs and file/rank labels
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut board = [['·'; 8]; 8];
+        for piece in 0..6 {
+            for color in 0..2 {
+                let glyph = piece_glyph(
+                    Piece::from_u8(piece as u8).unwrap(),
+                    Color::from_u8(color as u8),
+                );
+                for sq in self.pieces[piece][color].iter() {
+                    board[sq.rank() as usize][sq.file() as usize] = glyph;
+                }
+            }
+        }
+        for (rank, squares) in board.iter().enumerate().rev() {
+            write!(f, "{}", rank + 1)?;
+            for glyph in squares {
+                write!(f, " {}", glyph)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "  a b c d e f g h")
+    }
+}
+
 // Tests for Position
 #[cfg(test)]
 mod tests {
@@ -663,6 +1225,520 @@ mod tests {
             false
         );
     }
+
+    #[test]
+    fn test_piece_at() {
+        let mut pos = Position::empty();
+        pos.set_startpos();
+        assert_eq!(pos.piece_at(Square::E1), Some((Piece::King, Color::White)));
+        assert_eq!(pos.piece_at(Square::D8), Some((Piece::Queen, Color::Black)));
+        assert_eq!(pos.piece_at(Square::E4), None);
+    }
+
+    #[test]
+    fn test_set_fen_field_counts() {
+        let mut pos = Position::empty();
+
+        // Clocks left out
+        pos.set_fen("4k3/8/8/8/8/8/4P3/4K3 b - -").unwrap();
+        assert_eq!(pos.side_to_move, Color::Black);
+        assert_eq!((pos.halfmove_clock, pos.fullmove_number), (0, 1));
+
+        // Full six fields, then trailing junk that is ignored
+        pos.set_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 12 40").unwrap();
+        assert_eq!((pos.halfmove_clock, pos.fullmove_number), (12, 40));
+        pos.set_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 12 40 junk").unwrap();
+        assert_eq!((pos.halfmove_clock, pos.fullmove_number), (12, 40));
+
+        assert!(pos.set_fen("4k3/8/8/8/8/8/4P3/4K3 w -").is_err());
+    }
+
+    #[test]
+    fn test_set_fen_drops_impossible_en_passant() {
+        let ep = |fen: &str| {
+            let mut pos = Position::empty();
+            pos.set_fen(fen).unwrap();
+            pos.en_passant
+        };
+
+        assert_eq!(ep("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1"), Some(Square::D6));
+        assert_eq!(ep("4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 1"), Some(Square::D3));
+        // No black pawn on d5
+        assert_eq!(ep("4k3/8/8/4P3/8/8/8/4K3 w - d6 0 1"), None);
+        // Wrong rank for the side to move
+        assert_eq!(ep("4k3/8/8/3pP3/8/8/8/4K3 b - d6 0 1"), None);
+        assert_eq!(ep("4k3/8/8/8/3pP3/8/8/4K3 w - d3 0 1"), None);
+        // The pawn's start square is occupied
+        assert_eq!(ep("4k3/3b4/8/3pP3/8/8/8/4K3 w - d6 0 1"), None);
+    }
+
+    #[test]
+    fn test_set_fen_rejects_bad_side_and_castling() {
+        let mut pos = Position::empty();
+
+        let err = pos.set_fen("4k3/8/8/8/8/8/4P3/4K3 W - - 0 1").unwrap_err();
+        assert!(err.contains("side to move 'W'"), "{}", err);
+        assert!(pos.set_fen("4k3/8/8/8/8/8/4P3/4K3 white - - 0 1").is_err());
+
+        let err = pos.set_fen("4k3/8/8/8/8/8/4P3/4K3 w KX - 0 1").unwrap_err();
+        assert!(err.contains("castling field 'KX'"), "{}", err);
+    }
+
+    #[test]
+    fn test_make_move_checked() {
+        use crate::movegen::Move;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let before = pos.clone();
+
+        // Empty from-square, an enemy piece, and a move the piece can't make
+        let empty = Move::new(Square::C3, Square::C4);
+        assert_eq!(pos.make_move_checked(empty).unwrap_err(), MoveError::NoPiece(empty));
+        let enemy = Move::new(Square::D5, Square::D4);
+        assert_eq!(pos.make_move_checked(enemy).unwrap_err(), MoveError::NoPiece(enemy));
+        let illegal = Move::new(Square::A1, Square::B2);
+        assert_eq!(pos.make_move_checked(illegal).unwrap_err(), MoveError::Illegal(illegal));
+        assert!(pos == before);
+
+        // Capturing the king of a side left in check
+        let mut exposed = Position::empty();
+        exposed.set_fen("4k3/8/8/8/8/8/8/4RK2 w - - 0 1").unwrap();
+        let regicide = Move::new(Square::E1, Square::E8);
+        assert_eq!(exposed.make_move_checked(regicide).unwrap_err(), MoveError::KingCapture(regicide));
+        assert!(exposed.piece_bb(Piece::King, Color::Black).is_occupied(Square::E8));
+
+        // Untyped moves play as en passant and castling
+        pos.make_move_checked(Move::new(Square::E5, Square::D6)).unwrap();
+        assert!(pos.piece_bb(Piece::Pawn, Color::Black).is_empty());
+        pos.make_move_checked(Move::new(Square::E8, Square::G8)).unwrap();
+        assert!(pos.piece_bb(Piece::Rook, Color::Black).is_occupied(Square::F8));
+    }
+
+    #[test]
+    fn test_apply_moves() {
+        use crate::movegen::Move;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_startpos();
+        let start = pos.clone();
+
+        // A short game with a capture and castling
+        let game = [
+            Move::new(Square::E2, Square::E4),
+            Move::new(Square::D7, Square::D5),
+            Move::new(Square::E4, Square::D5),
+            Move::new(Square::G8, Square::F6),
+            Move::new(Square::G1, Square::F3),
+            Move::new(Square::F6, Square::D5),
+            Move::new(Square::F1, Square::E2),
+            Move::new(Square::E7, Square::E6),
+            Move::new(Square::E1, Square::G1),
+        ];
+        let undos = pos.apply_moves(&game).unwrap();
+        assert_eq!(undos.len(), game.len());
+        assert_eq!(pos.to_fen(), "rnbqkb1r/ppp2ppp/4p3/3n4/8/5N2/PPPPBPPP/RNBQ1RK1 b kq - 1 5");
+        for undo in undos.into_iter().rev() {
+            pos.unmake_move(undo);
+        }
+        assert_eq!(pos, start);
+
+        // Nothing is kept when a later move is illegal
+        let illegal = [game[0], game[1], Move::new(Square::E4, Square::E6)];
+        assert_eq!(pos.apply_moves(&illegal).unwrap_err(), MoveError::Illegal(illegal[2]));
+        assert_eq!(pos, start);
+    }
+
+    #[test]
+    fn test_is_quiet() {
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        // The knight on d5 hangs to the pawn on e4
+        pos.set_fen("4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        assert!(!pos.is_quiet());
+        // Locked pawn chains with nothing to take
+        pos.set_fen("4k3/8/3p4/2pPp3/2P1P3/8/8/4K3 w - - 0 1").unwrap();
+        assert!(pos.is_quiet());
+        // In check
+        pos.set_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").unwrap();
+        assert!(!pos.is_quiet());
+    }
+
+    #[test]
+    fn test_has_non_pawn_material() {
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/pppp4/8/8/8/8/4PPPP/4K1N1 w - - 0 1").unwrap();
+        assert!(pos.has_non_pawn_material(Color::White));
+        assert!(!pos.has_non_pawn_material(Color::Black));
+
+        pos.set_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(!pos.has_non_pawn_material(Color::White));
+        for piece in ["n", "b", "r", "q"] {
+            pos.set_fen(&format!("4k3/8/8/8/8/8/8/{}3K3 b - - 0 1", piece)).unwrap();
+            assert!(pos.has_non_pawn_material(Color::Black), "{}", piece);
+        }
+    }
+
+    #[test]
+    fn test_phase() {
+        let mut pos = Position::empty();
+        pos.set_startpos();
+        assert_eq!(pos.phase(), 24);
+
+        pos.set_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(pos.phase(), 0);
+        pos.set_fen("4k3/pppp4/8/8/8/8/4PPPP/4K3 w - - 0 1").unwrap();
+        assert_eq!(pos.phase(), 0);
+
+        pos.set_fen("3rk3/8/8/8/8/8/8/2N1K3 w - - 0 1").unwrap();
+        assert_eq!(pos.phase(), 3);
+
+        // Extra promoted queens don't push it past the opening value
+        pos.set_fen("qqqqkqqq/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(pos.phase(), 24);
+    }
+
+    #[test]
+    fn test_mirror() {
+        let mut pos = Position::empty();
+        pos.set_fen("r3k2r/ppp1qppp/8/3pP3/8/8/PPP2PPP/R3K2R w Kq d6 0 12").unwrap();
+
+        let mirrored = pos.mirror();
+        assert_eq!(
+            mirrored.to_fen(),
+            "r3k2r/ppp2ppp/8/8/3Pp3/8/PPP1QPPP/R3K2R b Qk d3 0 12"
+        );
+        assert_eq!(mirrored.mirror(), pos);
+    }
+
+    #[test]
+    fn test_display_unicode_board() {
+        let mut pos = Position::empty();
+        pos.set_startpos();
+        let board = pos.to_string();
+
+        // Rank 1 is the second-to-last line; E1 is its fifth square
+        let rank1 = board.lines().rev().nth(1).unwrap();
+        assert!(rank1.starts_with('1'));
+        assert_eq!(rank1.split_whitespace().nth(5), Some("♔"));
+        assert!(board.contains('♚'));
+    }
+
+    #[test]
+    fn test_pawn_hash() {
+        // Same pawns, different pieces
+        let mut a = Position::empty();
+        a.set_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let mut b = Position::empty();
+        b.set_fen("rnbqkb1r/pppp1ppp/5n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR w KQkq - 2 3").unwrap();
+        assert_eq!(a.pawn_hash, b.pawn_hash);
+        assert_ne!(a.zobrist_hash(), b.zobrist_hash());
+
+        // Incremental updates match a full recomputation through captures,
+        // en passant and promotion, and unmake restores the key
+        use crate::movegen::Move;
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let original = pos.pawn_hash;
+        for mv in [
+            Move::en_passant(Square::E5, Square::D6),
+            Move::promotion(Square::B7, Square::B8, Piece::Queen),
+        ] {
+            let undo = pos.make_move(mv);
+            assert_eq!(pos.pawn_hash, pos.compute_pawn_hash());
+            assert_ne!(pos.pawn_hash, original);
+            let mut restored = pos.clone();
+            restored.unmake_move(undo);
+            assert_eq!(restored.pawn_hash, restored.compute_pawn_hash());
+            // Let white move again for the next case
+            pos.side_to_move = Color::White;
+        }
+    }
+
+    #[test]
+    fn test_repetition_matches_full_scan() {
+        use crate::movegen::Move;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_startpos();
+
+        // Knight shuffles broken up by pawn moves and captures
+        let game = [
+            "g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "e2e4", "f6e4", "f3g1", "e4f6",
+            "g1f3", "f6e4", "f3g1", "e4f6", "d2d4", "d7d5", "b1c3", "b8c6", "c3b1", "c6b8",
+            "b1c3", "b8c6", "c3d5", "f6d5", "d1d3", "d8d6", "d3d1", "d6d8", "d1d3", "d8d6",
+        ];
+        let mut repetitions = 0;
+        for uci in game {
+            let square = |s: &str| {
+                let b = s.as_bytes();
+                Square::new(b[0] - b'a', b[1] - b'1')
+            };
+            pos.make_move(Move::new(square(&uci[..2]), square(&uci[2..])));
+
+            // Reference: every earlier position with the same side to move
+            let key = pos.zobrist_hash().value();
+            let full_scan = pos.hash_history.iter().rev().skip(1).step_by(2).any(|&k| k == key);
+            assert_eq!(pos.is_repetition(), full_scan, "after {}", uci);
+            repetitions += full_scan as u32;
+        }
+        assert!(repetitions >= 4);
+    }
+
+    #[test]
+    fn test_incremental_state_matches_pieces() {
+        use crate::eval::material::PIECE_VALUES;
+        use crate::movegen::generator::{generate_captures, generate_quiets};
+        use crate::movegen::legal::is_legal_move;
+        use crate::movegen::MoveList;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        fn folded(pos: &Position, color: usize) -> Bitboard {
+            (0..6).fold(Bitboard::EMPTY, |acc, piece| acc | pos.pieces[piece][color])
+        }
+        fn assert_in_step(pos: &Position) {
+            for color in [Color::White, Color::Black] {
+                assert_eq!(pos.occupancy(color), folded(pos, color as usize), "{}", pos.to_fen());
+            }
+            assert_eq!(pos.all_occupancy(), folded(pos, 0) | folded(pos, 1));
+            for sq in (0..64).map(Square) {
+                assert_eq!(pos.mailbox[sq.0 as usize], pos.piece_at_from_bitboards(sq), "{} on {:?}", pos.to_fen(), sq);
+            }
+            let recounted: i32 = (0..6)
+                .map(|piece| (pos.pieces[piece][0].count() as i32 - pos.pieces[piece][1].count() as i32) * PIECE_VALUES[piece])
+                .sum();
+            assert_eq!(pos.material(&PIECE_VALUES), recounted, "{}", pos.to_fen());
+        }
+
+        crate::bitboard::magic::init_magics();
+        let mut rng = StdRng::seed_from_u64(1863);
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ];
+        for fen in fens {
+            for _ in 0..10 {
+                let mut pos = Position::empty();
+                pos.set_fen(fen).unwrap();
+                assert_in_step(&pos);
+                assert_in_step(&pos.mirror());
+
+                let mut undos = Vec::new();
+                for _ in 0..40 {
+                    let color = pos.side_to_move;
+                    let mut moves = MoveList::new();
+                    generate_captures(&mut moves, &pos, color);
+                    generate_quiets(&mut moves, &pos, color);
+                    let legal: Vec<_> = moves.iter().filter(|&&mv| is_legal_move(mv, &pos, color)).collect();
+                    if legal.is_empty() {
+                        break;
+                    }
+                    undos.push(pos.make_move(*legal[rng.gen_range(0..legal.len())]));
+                    assert_in_step(&pos);
+                }
+                while let Some(undo) = undos.pop() {
+                    pos.unmake_move(undo);
+                    assert_in_step(&pos);
+                }
+            }
+        }
+    }
+    #[test]
+    fn test_same_position_ignores_move_counters() {
+        let mut a = Position::empty();
+        a.set_fen("r3k2r/8/8/8/4P3/8/8/R3K2R b KQkq e3 0 1").unwrap();
+        let mut b = Position::empty();
+        b.set_fen("r3k2r/8/8/8/4P3/8/8/R3K2R b KQkq e3 7 23").unwrap();
+        assert!(a.same_position(&b));
+        assert!(a != b);
+
+        // Castling rights and the en passant square still count
+        let mut c = Position::empty();
+        c.set_fen("r3k2r/8/8/8/4P3/8/8/R3K2R b KQk e3 0 1").unwrap();
+        assert!(!a.same_position(&c));
+        c.set_fen("r3k2r/8/8/8/4P3/8/8/R3K2R b KQkq - 0 1").unwrap();
+        assert!(!a.same_position(&c));
+    }
+
+    #[test]
+    fn test_checkers() {
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").unwrap();
+        assert_eq!(pos.checkers(), Square::A1.to_bitboard());
+
+        // The knight uncovers the rook: a double check
+        pos.set_fen("4k3/8/8/8/4N3/8/8/4RK2 w - - 0 1").unwrap();
+        assert!(pos.checkers().is_empty());
+        pos.make_move(crate::movegen::Move::new(Square::E4, Square::D6));
+        assert_eq!(pos.checkers(), Square::D6.to_bitboard() | Square::E1.to_bitboard());
+
+        // Pawns check diagonally forward only
+        pos.set_fen("8/8/8/3k4/4P3/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(pos.checkers(), Square::E4.to_bitboard());
+        pos.set_fen("8/8/8/3kP3/8/8/8/4K3 b - - 0 1").unwrap();
+        assert!(pos.checkers().is_empty());
+    }
+
+    #[test]
+    fn test_see() {
+        use crate::movegen::Move;
+
+        crate::bitboard::magic::init_magics();
+        let see = |fen: &str, from: Square, to: Square| {
+            let mut pos = Position::empty();
+            pos.set_fen(fen).unwrap();
+            pos.see(Move::new(from, to))
+        };
+
+        // Knight for knight
+        assert_eq!(see("4k3/8/3p4/4n3/8/5N2/8/4K3 w - - 0 1", Square::F3, Square::E5), 0);
+        // Pawn takes a defended knight
+        assert_eq!(see("4k3/8/3p4/4n3/3P4/8/8/4K3 w - - 0 1", Square::D4, Square::E5), 320 - 100);
+        // Queen takes a pawn defended by a pawn
+        assert_eq!(see("4k3/8/3p4/4p3/8/8/4Q3/4K3 w - - 0 1", Square::E2, Square::E5), 100 - 900);
+        // The rook behind the queen backs it up once the queen has captured
+        assert_eq!(see("4k3/4r3/8/4p3/8/8/4Q3/4R1K1 w - - 0 1", Square::E2, Square::E5), 100 - 900 + 500);
+    }
+
+    #[test]
+    fn test_attacked_by_matches_per_piece_attacks() {
+        use crate::bitboard::attacks::*;
+        use crate::movegen::generator::{generate_captures, generate_quiets};
+        use crate::movegen::legal::is_legal_move;
+        use crate::movegen::MoveList;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        // The map the search and legality checks used to build by hand
+        fn reference(pos: &Position, color: Color) -> Bitboard {
+            let occupied = pos.all_occupancy();
+            let mut attacked = Bitboard::EMPTY;
+            for piece in 0..6 {
+                let piece = Piece::from_u8(piece).unwrap();
+                for sq in pos.piece_bb(piece, color).iter() {
+                    attacked |= match piece {
+                        Piece::Pawn => pawn_attacks(sq, color),
+                        Piece::Knight => knight_attacks(sq),
+                        Piece::Bishop => bishop_attacks(sq, occupied),
+                        Piece::Rook => rook_attacks(sq, occupied),
+                        Piece::Queen => queen_attacks(sq, occupied),
+                        Piece::King => king_attacks(sq),
+                    };
+                }
+            }
+            attacked
+        }
+
+        crate::bitboard::magic::init_magics();
+        let mut rng = StdRng::seed_from_u64(1864);
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ];
+        for fen in fens {
+            let mut pos = Position::empty();
+            pos.set_fen(fen).unwrap();
+            for _ in 0..60 {
+                for color in [Color::White, Color::Black] {
+                    assert_eq!(pos.attacked_by(color), reference(&pos, color), "{}", pos.to_fen());
+                }
+
+                let color = pos.side_to_move;
+                let mut moves = MoveList::new();
+                generate_captures(&mut moves, &pos, color);
+                generate_quiets(&mut moves, &pos, color);
+                let legal: Vec<_> = moves.iter().filter(|&&mv| is_legal_move(mv, &pos, color)).collect();
+                if legal.is_empty() {
+                    break;
+                }
+                pos.make_move(*legal[rng.gen_range(0..legal.len())]);
+            }
+        }
+    }
+    #[test]
+    fn test_game_result() {
+        crate::bitboard::magic::init_magics();
+        let result = |fen: &str| {
+            let mut pos = Position::empty();
+            pos.set_fen(fen).unwrap();
+            pos.game_result()
+        };
+
+        assert_eq!(result("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), None);
+        // Checkmate: the side to move loses
+        assert_eq!(result("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1"), Some(GameResult::WhiteWins));
+        assert_eq!(
+            result("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"),
+            Some(GameResult::BlackWins)
+        );
+        // Stalemate
+        assert_eq!(result("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), Some(GameResult::Draw));
+        // Fifty-move rule, unless the last move mated
+        assert_eq!(result("4k3/8/8/8/8/8/4P3/4K3 w - - 100 80"), Some(GameResult::Draw));
+        assert_eq!(result("4k3/8/8/8/8/8/4P3/4K3 w - - 99 80"), None);
+        assert_eq!(result("R5k1/5ppp/8/8/8/8/8/6K1 b - - 100 80"), Some(GameResult::WhiteWins));
+        // Insufficient material
+        assert_eq!(result("4k3/8/8/8/8/8/8/3NK3 w - - 0 1"), Some(GameResult::Draw));
+        assert_eq!(result("4k3/8/8/8/8/8/8/3RK3 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn test_threefold_repetition_result() {
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_startpos();
+
+        let shuffle = [
+            crate::movegen::Move::new(Square::G1, Square::F3),
+            crate::movegen::Move::new(Square::G8, Square::F6),
+            crate::movegen::Move::new(Square::F3, Square::G1),
+            crate::movegen::Move::new(Square::F6, Square::G8),
+        ];
+        for mv in shuffle {
+            pos.make_move(mv);
+        }
+        // Second occurrence of the start position: not yet a draw
+        assert_eq!(pos.game_result(), None);
+
+        for mv in shuffle {
+            pos.make_move(mv);
+        }
+        assert!(pos.is_threefold_repetition());
+        assert_eq!(pos.game_result(), Some(GameResult::Draw));
+    }
+
+    #[test]
+    fn test_null_move_round_trip() {
+        for fen in [
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+            "4k3/8/8/8/8/8/8/4K2R b K - 17 42",
+        ] {
+            let mut pos = Position::empty();
+            pos.set_fen(fen).unwrap();
+            let before = pos.clone();
+
+            let undo = pos.make_null_move();
+            assert_eq!(pos.side_to_move, before.side_to_move.opposite());
+            assert_eq!(pos.en_passant, None);
+            assert_eq!(pos.halfmove_clock, before.halfmove_clock + 1);
+            let expected_fullmove = before.fullmove_number + (before.side_to_move == Color::Black) as u32;
+            assert_eq!(pos.fullmove_number, expected_fullmove);
+            assert_ne!(pos.zobrist_hash(), before.zobrist_hash());
+
+            pos.unmake_null_move(undo);
+            assert_eq!(
+                (pos.fullmove_number, pos.side_to_move, pos.halfmove_clock),
+                (before.fullmove_number, before.side_to_move, before.halfmove_clock)
+            );
+            assert_eq!(pos, before);
+            assert_eq!(pos.zobrist_hash(), before.zobrist_hash());
+        }
+    }
 }
 
 // Helper trait implementations for Piece and Color
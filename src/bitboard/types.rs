@@ -57,6 +57,11 @@ impl Bitboard {
         (self.0 & (1u64 << sq.0)) != 0
     }
 
+    /// Bitboard with the given squares set
+    pub fn from_squares(squares: &[Square]) -> Bitboard {
+        squares.iter().copied().collect()
+    }
+
     /// Set a square
     #[inline(always)]
     pub fn set(&mut self, sq: Square) {
@@ -109,6 +114,12 @@ impl Bitboard {
     pub fn iter(self) -> BitboardIter {
         BitboardIter(self)
     }
+
+    /// Iterator over the `(file, rank)` coordinates of the set bits, in the
+    /// same order as `iter`
+    pub fn iter_coords(self) -> impl Iterator<Item = (u8, u8)> {
+        self.iter().map(|sq| (sq.file(), sq.rank()))
+    }
 }
 
 /// Iterator over set bits in a bitboard
@@ -122,6 +133,25 @@ impl Iterator for BitboardIter {
     }
 }
 
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = BitboardIter;
+
+    fn into_iter(self) -> BitboardIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<Square> for Bitboard {
+    fn from_iter<I: IntoIterator<Item = Square>>(squares: I) -> Self {
+        let mut bb = Bitboard::EMPTY;
+        for sq in squares {
+            bb.set(sq);
+        }
+        bb
+    }
+}
+
 // Bitwise operations
 impl std::ops::BitOr for Bitboard {
     type Output = Self;
@@ -187,6 +217,31 @@ impl fmt::Debug for Bitboard {
     }
 }
 
+/// Labeled grid, rank 8 at the top with rank numbers on the left and file
+/// letters below; the alternate form (`{:#}`) leaves out the labels
+impl fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let labeled = !f.alternate();
+        for rank in (0..8).rev() {
+            if labeled {
+                write!(f, "{} ", rank + 1)?;
+            }
+            for file in 0..8 {
+                let mark = if self.is_occupied(Square::new(file, rank)) { 'X' } else { '.' };
+                if file < 7 {
+                    write!(f, "{} ", mark)?;
+                } else {
+                    writeln!(f, "{}", mark)?;
+                }
+            }
+        }
+        if labeled {
+            writeln!(f, "  a b c d e f g h")?;
+        }
+        Ok(())
+    }
+}
+
 /// Represents a square on the chess board (0-63)
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
@@ -289,6 +344,42 @@ impl Square {
     pub const fn to_bitboard(self) -> Bitboard {
         Bitboard(1u64 << self.0)
     }
+
+    /// Number of king moves between two squares (Chebyshev distance)
+    #[inline(always)]
+    pub const fn distance(self, other: Square) -> u8 {
+        let files = self.file().abs_diff(other.file());
+        let ranks = self.rank().abs_diff(other.rank());
+        if files > ranks {
+            files
+        } else {
+            ranks
+        }
+    }
+
+    /// The square `df` files and `dr` ranks away, or `None` if that is off the board
+    #[inline(always)]
+    pub const fn offset(self, df: i8, dr: i8) -> Option<Square> {
+        let file = self.file() as i8 + df;
+        let rank = self.rank() as i8 + dr;
+        if file < 0 || file > 7 || rank < 0 || rank > 7 {
+            None
+        } else {
+            Some(Square::new(file as u8, rank as u8))
+        }
+    }
+
+    /// The square on the same file with the rank mirrored (e2 <-> e7)
+    #[inline(always)]
+    pub const fn flip_rank(self) -> Square {
+        Square(self.0 ^ 56)
+    }
+
+    /// The square on the same rank with the file mirrored (b3 <-> g3)
+    #[inline(always)]
+    pub const fn flip_file(self) -> Square {
+        Square(self.0 ^ 7)
+    }
 }
 
 impl fmt::Debug for Square {
@@ -323,6 +414,41 @@ impl Piece {
             _ => None,
         }
     }
+
+    /// Parse a FEN piece letter (uppercase for white, lowercase for black)
+    pub fn from_char(c: char) -> Option<(Piece, Color)> {
+        let piece = match c.to_ascii_lowercase() {
+            'p' => Piece::Pawn,
+            'n' => Piece::Knight,
+            'b' => Piece::Bishop,
+            'r' => Piece::Rook,
+            'q' => Piece::Queen,
+            'k' => Piece::King,
+            _ => return None,
+        };
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        Some((piece, color))
+    }
+
+    /// FEN letter for this piece (uppercase for white, lowercase for black)
+    pub fn to_char(self, color: Color) -> char {
+        let c = match self {
+            Piece::Pawn => 'p',
+            Piece::Knight => 'n',
+            Piece::Bishop => 'b',
+            Piece::Rook => 'r',
+            Piece::Queen => 'q',
+            Piece::King => 'k',
+        };
+        match color {
+            Color::White => c.to_ascii_uppercase(),
+            Color::Black => c,
+        }
+    }
 }
 
 /// Color
@@ -350,6 +476,23 @@ impl Color {
             _ => Color::White,
         }
     }
+
+    /// Parse the FEN side-to-move letter (`w` or `b`)
+    pub fn from_char(c: char) -> Option<Color> {
+        match c {
+            'w' => Some(Color::White),
+            'b' => Some(Color::Black),
+            _ => None,
+        }
+    }
+
+    /// FEN side-to-move letter for this color
+    pub fn to_char(self) -> char {
+        match self {
+            Color::White => 'w',
+            Color::Black => 'b',
+        }
+    }
 }
 
 /// Castle rights
@@ -380,6 +523,52 @@ impl CastleRights {
     }
 }
 
+/// FEN castling field: `KQkq` order, or `-` when no rights remain
+impl fmt::Display for CastleRights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0 == 0 {
+            return write!(f, "-");
+        }
+        for (rights, c) in [
+            (CastleRights::WHITE_KING, 'K'),
+            (CastleRights::WHITE_QUEEN, 'Q'),
+            (CastleRights::BLACK_KING, 'k'),
+            (CastleRights::BLACK_QUEEN, 'q'),
+        ] {
+            if self.has(rights) {
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for CastleRights {
+    type Err = String;
+
+    /// Parse a FEN castling field (`-` or any combination of `KQkq`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "-" {
+            return Ok(CastleRights::NONE);
+        }
+        if s.is_empty() {
+            return Err("Empty castling field".to_string());
+        }
+
+        let mut rights = CastleRights::NONE;
+        for c in s.chars() {
+            match c {
+                'K' => rights.add(CastleRights::WHITE_KING),
+                'Q' => rights.add(CastleRights::WHITE_QUEEN),
+                'k' => rights.add(CastleRights::BLACK_KING),
+                'q' => rights.add(CastleRights::BLACK_QUEEN),
+                _ => return Err(format!("Invalid castling char: {}", c)),
+            }
+        }
+        Ok(rights)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -401,6 +590,49 @@ mod tests {
         assert_eq!(bb.count(), 1);
     }
 
+    #[test]
+    fn test_from_squares_and_for_loop() {
+        let squares = [Square::H8, Square::A1, Square::E4, Square::A1];
+        let bb = Bitboard::from_squares(&squares);
+        assert_eq!(bb, Square::A1.to_bitboard() | Square::E4.to_bitboard() | Square::H8.to_bitboard());
+        assert_eq!(squares.into_iter().collect::<Bitboard>(), bb);
+        assert_eq!(Bitboard::from_squares(&[]), Bitboard::EMPTY);
+
+        let mut visited = Vec::new();
+        for sq in bb {
+            visited.push(sq);
+        }
+        assert_eq!(visited, vec![Square::A1, Square::E4, Square::H8]);
+        assert_eq!(bb.into_iter().collect::<Bitboard>(), bb);
+    }
+
+    #[test]
+    fn test_iter_coords() {
+        let bb = Square::A1.to_bitboard() | Square::E4.to_bitboard() | Square::H8.to_bitboard();
+        let coords: Vec<(u8, u8)> = bb.iter_coords().collect();
+        assert_eq!(coords, vec![(0, 0), (4, 3), (7, 7)]);
+        assert!(coords.iter().all(|&(file, rank)| bb.is_occupied(Square::new(file, rank))));
+        assert_eq!(Bitboard::EMPTY.iter_coords().count(), 0);
+    }
+
+    #[test]
+    fn test_bitboard_display() {
+        let bb = Square::A1.to_bitboard() | Square::E4.to_bitboard() | Square::H8.to_bitboard();
+        let text = bb.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[0], "8 . . . . . . . X");
+        assert_eq!(lines[4], "4 . . . . X . . .");
+        assert_eq!(lines[7], "1 X . . . . . . .");
+        assert_eq!(lines[8], "  a b c d e f g h");
+
+        // The bare grid, without labels
+        let bare = format!("{:#}", bb);
+        assert_eq!(bare.lines().count(), 8);
+        assert_eq!(bare.lines().nth(4), Some(". . . . X . . ."));
+    }
+
     #[test]
     fn test_square_conversion() {
         let sq = Square::E4;
@@ -410,4 +642,68 @@ mod tests {
         let sq2 = Square::new(4, 3);
         assert_eq!(sq, sq2);
     }
+
+    #[test]
+    fn test_square_arithmetic() {
+        assert_eq!(Square::E4.offset(1, 2), Some(Square::F6));
+        assert_eq!(Square::E4.offset(-4, -3), Some(Square::A1));
+        assert_eq!(Square::H8.offset(1, 0), None);
+        assert_eq!(Square::A1.offset(0, -1), None);
+        assert_eq!(Square::A8.offset(-1, 0), None);
+        assert_eq!(Square::H1.offset(0, 7), Some(Square::H8));
+
+        assert_eq!(Square::A1.distance(Square::H8), 7);
+        assert_eq!(Square::H1.distance(Square::A8), 7);
+        assert_eq!(Square::E4.distance(Square::F6), 2);
+
+        assert_eq!(Square::E2.flip_rank(), Square::E7);
+        assert_eq!(Square::B3.flip_file(), Square::G3);
+        for sq in (0..64).map(Square) {
+            assert_eq!(sq.flip_rank().flip_rank(), sq);
+            assert_eq!(sq.flip_file().flip_file(), sq);
+            assert_eq!(sq.flip_rank().file(), sq.file());
+            assert_eq!(sq.flip_file().rank(), sq.rank());
+        }
+    }
+
+    #[test]
+    fn test_piece_char_round_trip() {
+        let cases = [
+            ('P', Piece::Pawn, Color::White),
+            ('N', Piece::Knight, Color::White),
+            ('B', Piece::Bishop, Color::White),
+            ('R', Piece::Rook, Color::White),
+            ('Q', Piece::Queen, Color::White),
+            ('K', Piece::King, Color::White),
+            ('p', Piece::Pawn, Color::Black),
+            ('n', Piece::Knight, Color::Black),
+            ('b', Piece::Bishop, Color::Black),
+            ('r', Piece::Rook, Color::Black),
+            ('q', Piece::Queen, Color::Black),
+            ('k', Piece::King, Color::Black),
+        ];
+        for (c, piece, color) in cases {
+            assert_eq!(Piece::from_char(c), Some((piece, color)));
+            assert_eq!(piece.to_char(color), c);
+        }
+        assert_eq!(Piece::from_char('x'), None);
+
+        assert_eq!(Color::from_char('w'), Some(Color::White));
+        assert_eq!(Color::from_char('b'), Some(Color::Black));
+        assert_eq!(Color::from_char('x'), None);
+        assert_eq!(Color::Black.to_char(), 'b');
+    }
+
+    #[test]
+    fn test_castle_rights_round_trip() {
+        for bits in 0..16u8 {
+            let rights = CastleRights(bits);
+            let text = rights.to_string();
+            assert_eq!(text.parse::<CastleRights>(), Ok(rights), "{}", text);
+        }
+        assert_eq!(CastleRights::NONE.to_string(), "-");
+        assert_eq!(CastleRights::ALL.to_string(), "KQkq");
+        assert!("".parse::<CastleRights>().is_err());
+        assert!("KX".parse::<CastleRights>().is_err());
+    }
 }
@@ -0,0 +1,80 @@
+//! Bishop evaluation
+//!
+//! This module evaluates bishop quality: bad bishops hemmed in by their own
+//! pawns on their color complex, and bishops trapped on a7/h7 (a2/h2 for
+//! Black) by an enemy pawn.
+
+use super::params::EvalParams;
+use crate::bitboard::{Bitboard, Color, Square};
+
+/// Dark squares, a1 among them
+const DARK_SQUARES: Bitboard = Bitboard(0xAA55_AA55_AA55_AA55);
+
+/// Trap patterns for a White bishop: the bishop square and the enemy pawn
+/// square shutting it in. Black uses the same squares flipped.
+const TRAPS: [(Square, Square); 2] = [(Square::A7, Square::B6), (Square::H7, Square::G6)];
+
+/// Score of the bishops of `color` (a penalty, so never positive)
+///
+/// Each bishop loses the bad-bishop penalty for every friendly pawn on its
+/// color complex, and the trapped penalty when caught in a corner trap.
+pub fn evaluate_bishops(
+    bishops: Bitboard,
+    pawns: Bitboard,
+    enemy_pawns: Bitboard,
+    color: Color,
+    params: &EvalParams,
+) -> i32 {
+    let mut score = 0;
+
+    for bishop in bishops.iter() {
+        let complex = if DARK_SQUARES.is_occupied(bishop) { DARK_SQUARES } else { !DARK_SQUARES };
+        score -= (pawns & complex).count() as i32 * params.bad_bishop_penalty;
+
+        if is_trapped(bishop, enemy_pawns, color) {
+            score -= params.trapped_bishop_penalty;
+        }
+    }
+
+    score
+}
+
+/// Whether a bishop of `color` on `bishop` is caught in a corner trap
+fn is_trapped(bishop: Square, enemy_pawns: Bitboard, color: Color) -> bool {
+    TRAPS.iter().any(|&(trap, pawn)| {
+        let (trap, pawn) = if color == Color::White { (trap, pawn) } else { (trap.flip_rank(), pawn.flip_rank()) };
+        bishop == trap && enemy_pawns.is_occupied(pawn)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(bishops: &[Square], pawns: &[Square], enemy_pawns: &[Square], color: Color) -> i32 {
+        let board = |squares: &[Square]| squares.iter().fold(Bitboard::EMPTY, |bb, sq| bb | sq.to_bitboard());
+        evaluate_bishops(board(bishops), board(pawns), board(enemy_pawns), color, &EvalParams::default())
+    }
+
+    #[test]
+    fn test_bad_bishop_behind_pawn_wall() {
+        // Light-squared bishop behind pawns on light squares, then dark ones
+        let wall = [Square::B3, Square::D3, Square::E4, Square::F3];
+        let open = [Square::C3, Square::D4, Square::E3, Square::F4];
+        let bad = score(&[Square::F1], &wall, &[], Color::White);
+        let good = score(&[Square::F1], &open, &[], Color::White);
+        assert!(bad < good);
+        assert_eq!(good, 0);
+        assert_eq!(bad, -4 * EvalParams::default().bad_bishop_penalty);
+    }
+
+    #[test]
+    fn test_trapped_bishop() {
+        let params = EvalParams::default();
+        assert_eq!(score(&[Square::A7], &[], &[Square::B6], Color::White), -params.trapped_bishop_penalty);
+        assert_eq!(score(&[Square::A7], &[], &[Square::C6], Color::White), 0);
+        assert_eq!(score(&[Square::H2], &[], &[Square::G3], Color::Black), -params.trapped_bishop_penalty);
+        // The corner only traps the bishop of the side attacking into it
+        assert_eq!(score(&[Square::A7], &[], &[Square::B6], Color::Black), 0);
+    }
+}
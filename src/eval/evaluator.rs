@@ -4,17 +4,28 @@
 //! evaluation components into a complete position evaluation.
 
 use super::material::*;
+use super::params::EvalParams;
 use crate::bitboard::{Bitboard, Color};
 
 /// Main position evaluator
 pub struct Evaluator {
-    // Evaluation parameters could be stored here
+    params: EvalParams,
 }
 
 impl Evaluator {
-    /// Create a new evaluator
+    /// Create a new evaluator with the default weights
     pub fn new() -> Self {
-        Evaluator {}
+        Self::with_params(EvalParams::default())
+    }
+
+    /// Create an evaluator using custom weights (for tuning and A/B tests)
+    pub fn with_params(params: EvalParams) -> Self {
+        Evaluator { params }
+    }
+
+    /// The weights this evaluator uses
+    pub fn params(&self) -> &EvalParams {
+        &self.params
     }
 
     /// Evaluate a position from the perspective of the side to move
@@ -23,8 +34,11 @@ impl Evaluator {
     pub fn evaluate(&self, position: &crate::bitboard::position::Position) -> i32 {
         use crate::bitboard::{Color, Piece};
         use crate::eval::{
-            king_safety::evaluate_king_safety, material::evaluate_material,
-            pawn::evaluate_pawn_structure, pst::evaluate_pst,
+            bishop::evaluate_bishops,
+            king_safety::evaluate_king_safety_with_params,
+            pawn::{evaluate_passer_race, evaluate_pawn_structure},
+            rook::evaluate_rooks,
+            space::evaluate_space,
         };
 
         // Extract bitboards for each piece and color
@@ -42,46 +56,83 @@ impl Evaluator {
         let bq = position.piece_bb(Piece::Queen, Color::Black);
         let bk = position.piece_bb(Piece::King, Color::Black);
 
-        // Material
-        let material = evaluate_material(wp, wn, wb, wr, wq, wk, bp, bn, bb, br, bq, bk);
+        // Neither side can force mate (e.g. K+NN vs K): a draw
+        if !has_mating_material(wp, wn, wb, wr, wq, bp, bn, bb, br, bq) {
+            return 0;
+        }
+
+        // Material and piece-square tables
+        let (material, pst) = self.material_and_pst(position);
+        let phase = position.phase() as i32;
+
+        // Pawn structure
+        let pawn_structure = evaluate_pawn_structure(wp, bp);
 
-        // Piece-square tables
-        let pst = evaluate_pst(
+        // Passed pawns against the defending king, at full weight since an
+        // unstoppable passer is worth close to a queen
+        let white_king = wk.lsb().unwrap_or(crate::bitboard::Square::E1);
+        let black_king = bk.lsb().unwrap_or(crate::bitboard::Square::E8);
+        let black_to_move = position.side_to_move == Color::Black;
+        let passer_race = evaluate_passer_race(
             wp,
-            wn,
-            wb,
-            wr,
-            wq,
-            wk.lsb().unwrap_or(crate::bitboard::Square::E1),
             bp,
-            bn,
-            bb,
-            br,
-            bq,
-            bk.lsb().unwrap_or(crate::bitboard::Square::E8),
+            Color::White,
+            black_king,
+            black_to_move,
+            !(bn | bb | br | bq).is_empty(),
+            &self.params,
+        ) - evaluate_passer_race(
+            bp,
+            wp,
+            Color::Black,
+            white_king,
+            !black_to_move,
+            !(wn | wb | wr | wq).is_empty(),
+            &self.params,
         );
 
-        // Pawn structure
-        let pawn_structure = evaluate_pawn_structure(wp, bp);
+        // Bad and trapped bishops
+        let bishops = evaluate_bishops(wb, wp, bp, Color::White, &self.params)
+            - evaluate_bishops(bb, bp, wp, Color::Black, &self.params);
+
+        // Rook placement
+        let occupied = position.all_occupancy();
+        let rooks = evaluate_rooks(wr, wp, bp, occupied, Color::White, &self.params)
+            - evaluate_rooks(br, bp, wp, occupied, Color::Black, &self.params);
+
+        // Space, a middlegame term fading out as pieces come off
+        let space = (evaluate_space(wp, bp, Color::White) - evaluate_space(bp, wp, Color::Black))
+            * self.params.space_bonus
+            * phase
+            / crate::bitboard::position::MAX_PHASE as i32;
 
         // King safety
-        let king_safety = evaluate_king_safety(
+        let king_safety = evaluate_king_safety_with_params(
             wk.lsb().unwrap_or(crate::bitboard::Square::E1),
             bk.lsb().unwrap_or(crate::bitboard::Square::E8),
             wp,
             bp,
+            &self.params,
         );
 
         // Mobility
         let mobility = Self::evaluate_mobility(position);
 
-        // Weighted sum (best-practice weights, can be tuned)
-        // Material: 1.0, PST: 0.2, Pawn structure: 0.15, King safety: 0.15, Mobility: 0.1
+        // Weighted sum, material, the passer race, bishops, rook placement and
+        // space at full weight and the rest scaled by the params
         let eval = (material as f32)
-            + 0.2 * (pst as f32)
-            + 0.15 * (pawn_structure as f32)
-            + 0.15 * (king_safety as f32)
-            + 0.1 * (mobility as f32);
+            + (passer_race as f32)
+            + (bishops as f32)
+            + (rooks as f32)
+            + (space as f32)
+            + self.params.pst_weight * (pst as f32)
+            + self.params.pawn_structure_weight * (pawn_structure as f32)
+            + self.params.king_safety_weight * (king_safety as f32)
+            + self.params.mobility_weight * (mobility as f32);
+
+        // The nearer the fifty-move rule, the less an advantage is worth
+        // unless a pawn move or capture resets the clock
+        let eval = eval * self.fifty_move_scale(position.halfmove_clock);
 
         // Return from the perspective of the side to move
         if position.side_to_move == Color::White {
@@ -91,46 +142,92 @@ impl Evaluator {
         }
     }
 
+    /// Factor applied to the evaluation at `halfmove_clock`: 1 up to
+    /// `fifty_move_scale_start`, then falling linearly to 0 at 100
+    fn fifty_move_scale(&self, halfmove_clock: u32) -> f32 {
+        let start = self.params.fifty_move_scale_start.min(99);
+        if halfmove_clock <= start {
+            1.0
+        } else {
+            100u32.saturating_sub(halfmove_clock) as f32 / (100 - start) as f32
+        }
+    }
+
+    /// Whether the evaluation at `halfmove_clock` is scaled down for the
+    /// fifty-move rule, so it depends on more than the position's hash
+    pub fn is_fifty_move_scaled(&self, halfmove_clock: u32) -> bool {
+        self.fifty_move_scale(halfmove_clock) < 1.0
+    }
+
+    /// Cheap evaluation from the side to move's perspective: material plus
+    /// the weighted piece-square tables, without the positional terms that
+    /// need attack sets or pawn scans
+    ///
+    /// Meant as a rough estimate for pruning and ordering decisions.
+    pub fn fast_eval(&self, position: &crate::bitboard::position::Position) -> i32 {
+        let (material, pst) = self.material_and_pst(position);
+        let eval = (material as f32) + self.params.pst_weight * (pst as f32);
+
+        if position.side_to_move == Color::White {
+            eval.round() as i32
+        } else {
+            (-eval).round() as i32
+        }
+    }
+
+    /// Material and (unweighted) piece-square table scores, from White's
+    /// perspective
+    fn material_and_pst(&self, position: &crate::bitboard::position::Position) -> (i32, i32) {
+        use crate::eval::pst::evaluate_pst;
+
+        // Material, from the piece counts the position keeps up to date
+        let material = position.material(&self.params.piece_values);
+
+        // Piece-square tables, the king's depending on the game phase
+        let pst = evaluate_pst(position, position.phase() as i32);
+
+        (material, pst)
+    }
+
     /// Evaluate mobility for both sides (difference in number of pseudo-legal moves)
+    ///
+    /// Counted from attack sets, without generating moves: knights, sliders
+    /// and the king count the squares they attack that their own side
+    /// doesn't hold, which is exactly their move count; pawns count their
+    /// free push squares and the enemy pieces they attack, so a promotion
+    /// counts once rather than once per piece.
     pub fn evaluate_mobility(position: &crate::bitboard::position::Position) -> i32 {
-        use crate::bitboard::{Bitboard, Color, Piece, Square};
-        use crate::movegen::generator::*;
-        use crate::movegen::MoveList;
+        use crate::bitboard::attacks::{pawn_attacks, piece_attacks};
+        use crate::bitboard::{Color, Piece};
 
         // Helper to count moves for a color
         fn count_moves(position: &crate::bitboard::position::Position, color: Color) -> i32 {
-            let mut moves = MoveList::new();
-            let occupied = (0..6).fold(Bitboard::EMPTY, |acc, p| {
-                acc | position.piece_bb(Piece::from_u8(p).unwrap(), Color::White)
-                    | position.piece_bb(Piece::from_u8(p).unwrap(), Color::Black)
-            });
-            let enemies = (0..6).fold(Bitboard::EMPTY, |acc, p| {
-                acc | position.piece_bb(Piece::from_u8(p).unwrap(), color.opposite())
-            });
+            let occupied = position.all_occupancy();
+            let own = position.occupancy(color);
+            let enemies = position.occupancy(color.opposite());
+            let empty = !occupied;
+            let mut count = 0;
+
+            for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King] {
+                for sq in position.piece_bb(piece, color).iter() {
+                    count += (piece_attacks(piece, sq, color, occupied) & !own).count();
+                }
+            }
 
             let pawns = position.piece_bb(Piece::Pawn, color);
-            let knights = position.piece_bb(Piece::Knight, color);
-            let bishops = position.piece_bb(Piece::Bishop, color);
-            let rooks = position.piece_bb(Piece::Rook, color);
-            let queens = position.piece_bb(Piece::Queen, color);
-            let king = position.piece_bb(Piece::King, color);
-
-            generate_pawn_moves(
-                &mut moves,
-                pawns,
-                occupied,
-                enemies,
-                color,
-                position.en_passant,
-            );
-            generate_knight_moves(&mut moves, knights, occupied, enemies);
-            generate_bishop_moves(&mut moves, bishops, occupied, enemies);
-            generate_rook_moves(&mut moves, rooks, occupied, enemies);
-            generate_queen_moves(&mut moves, queens, occupied, enemies);
-            if let Some(king_sq) = king.lsb() {
-                generate_king_moves(&mut moves, king_sq, occupied, enemies);
+            let (single, double) = if color == Color::White {
+                let single = Bitboard(pawns.0 << 8) & empty;
+                (single, Bitboard((single & Bitboard::RANK_3).0 << 8) & empty)
+            } else {
+                let single = Bitboard(pawns.0 >> 8) & empty;
+                (single, Bitboard((single & Bitboard::RANK_6).0 >> 8) & empty)
+            };
+            count += single.count() + double.count();
+            for sq in pawns.iter() {
+                count += (pawn_attacks(sq, color) & enemies).count();
             }
-            moves.len() as i32
+
+            count as i32
         }
 
         let white_moves = count_moves(position, Color::White);
@@ -139,36 +236,9 @@ impl Evaluator {
         white_moves - black_moves
     }
 
-    /// Evaluate material balance only
-    pub fn evaluate_material_only(
-        &self,
-        white_pawns: Bitboard,
-        white_knights: Bitboard,
-        white_bishops: Bitboard,
-        white_rooks: Bitboard,
-        white_queens: Bitboard,
-        white_king: Bitboard,
-        black_pawns: Bitboard,
-        black_knights: Bitboard,
-        black_bishops: Bitboard,
-        black_rooks: Bitboard,
-        black_queens: Bitboard,
-        black_king: Bitboard,
-    ) -> i32 {
-        evaluate_material(
-            white_pawns,
-            white_knights,
-            white_bishops,
-            white_rooks,
-            white_queens,
-            white_king,
-            black_pawns,
-            black_knights,
-            black_bishops,
-            black_rooks,
-            black_queens,
-            black_king,
-        )
+    /// Evaluate material balance only, from White's perspective
+    pub fn evaluate_material_only(&self, position: &crate::bitboard::position::Position) -> i32 {
+        position.material(&self.params.piece_values)
     }
 }
 
@@ -176,6 +246,74 @@ impl Evaluator {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_fast_eval() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let evaluator = Evaluator::new();
+        let mut pos = Position::empty();
+
+        // Both balanced in the symmetric start position
+        pos.set_startpos();
+        assert_eq!(evaluator.fast_eval(&pos), 0);
+        assert_eq!(evaluator.evaluate(&pos), 0);
+
+        // A queen up: both see it, from the side to move's perspective, and
+        // differ only by the terms the fast one leaves out
+        pos.set_fen("4k3/pppp4/8/8/8/8/PPPP4/3QK3 b - - 0 1").unwrap();
+        let (fast, full) = (evaluator.fast_eval(&pos), evaluator.evaluate(&pos));
+        assert!(fast < -800 && full < -800, "fast {} full {}", fast, full);
+        assert!((fast - full).abs() < 100, "fast {} full {}", fast, full);
+    }
+
+    #[test]
+    fn test_mobility_matches_move_counts() {
+        use crate::bitboard::position::Position;
+        use crate::bitboard::Piece;
+        use crate::movegen::generator::*;
+        use crate::movegen::MoveList;
+
+        /// The former mobility: pseudo-legal moves generated per side
+        fn generated(position: &Position, color: Color) -> i32 {
+            let mut moves = MoveList::new();
+            let occupied = position.all_occupancy();
+            let enemies = position.occupancy(color.opposite());
+            let bb = |piece| position.piece_bb(piece, color);
+            generate_pawn_moves(&mut moves, bb(Piece::Pawn), occupied, enemies, color, position.en_passant);
+            generate_knight_moves(&mut moves, bb(Piece::Knight), occupied, enemies);
+            generate_bishop_moves(&mut moves, bb(Piece::Bishop), occupied, enemies);
+            generate_rook_moves(&mut moves, bb(Piece::Rook), occupied, enemies);
+            generate_queen_moves(&mut moves, bb(Piece::Queen), occupied, enemies);
+            generate_king_moves(&mut moves, bb(Piece::King).lsb().unwrap(), occupied, enemies);
+            moves.len() as i32
+        }
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+
+        // Without promotions or en passant the counts agree exactly
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP2BPPP/R1BQ1RK1 b - - 0 8",
+        ] {
+            pos.set_fen(fen).unwrap();
+            let expected = generated(&pos, Color::White) - generated(&pos, Color::Black);
+            assert_eq!(Evaluator::evaluate_mobility(&pos), expected, "{}", fen);
+        }
+
+        // A promotion counts once instead of four times, so the sign still agrees
+        pos.set_fen("4k3/1P6/8/8/8/8/6p1/4K3 w - - 0 1").unwrap();
+        let expected = generated(&pos, Color::White) - generated(&pos, Color::Black);
+        assert_eq!(Evaluator::evaluate_mobility(&pos), 0);
+        assert_eq!(expected, 0);
+        pos.set_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let expected = generated(&pos, Color::White) - generated(&pos, Color::Black);
+        assert_eq!(Evaluator::evaluate_mobility(&pos).signum(), expected.signum());
+    }
+
     #[test]
     fn test_evaluator_creation() {
         let evaluator = Evaluator::new();
@@ -184,4 +322,112 @@ mod tests {
         let score = evaluator.evaluate(&dummy_position);
         assert!(score >= -20000 && score <= 20000); // Within reasonable bounds
     }
+
+    #[test]
+    fn test_evaluation_is_color_symmetric() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let evaluator = Evaluator::new();
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r1bq1rk1/pp2ppbp/2np1np1/8/3NP3/2N1BP2/PPPQ2PP/R3KB1R b KQ - 0 9",
+            "6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1",
+            "4k3/8/8/8/8/8/8/K7 b - - 0 1",
+            "7k/8/8/8/8/8/8/K7 w - - 0 1",
+            // Kings on their opponent's back rank
+            "K7/8/8/8/8/8/8/7k w - - 0 1",
+        ];
+
+        for fen in fens {
+            let mut pos = Position::empty();
+            pos.set_fen(fen).unwrap();
+            assert_eq!(
+                evaluator.evaluate(&pos),
+                evaluator.evaluate(&pos.mirror()),
+                "asymmetric evaluation for {}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn test_pawn_value_override_scales_material() {
+        use crate::bitboard::{Piece, Square};
+
+        // White is two pawns up
+        let mut pos = crate::bitboard::position::Position::empty();
+        pos.set_fen("4k3/p7/8/8/8/8/PPP5/4K3 w - - 0 1").unwrap();
+        let material = |evaluator: &Evaluator| evaluator.evaluate_material_only(&pos);
+
+        let mut params = EvalParams::default();
+        assert_eq!(material(&Evaluator::new()), 2 * params.piece_values[Piece::Pawn as usize]);
+
+        params.piece_values[Piece::Pawn as usize] = 150;
+        assert_eq!(material(&Evaluator::with_params(params)), 300);
+    }
+
+    #[test]
+    fn test_knn_vs_k_is_a_draw() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let evaluator = Evaluator::new();
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1").unwrap();
+        assert_eq!(evaluator.evaluate(&pos), 0);
+        assert_eq!(evaluator.evaluate(&pos.mirror()), 0);
+
+        // A pawn keeps winning chances alive
+        pos.set_fen("4k3/8/8/8/8/8/4P3/1N2KN2 w - - 0 1").unwrap();
+        assert!(evaluator.evaluate(&pos) > 0);
+    }
+
+    #[test]
+    fn test_advantage_shrinks_near_fifty_move_limit() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let evaluator = Evaluator::new();
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let fresh = evaluator.evaluate(&pos);
+
+        pos.set_fen("4k3/8/8/8/8/8/8/R3K3 w - - 80 60").unwrap();
+        assert_eq!(evaluator.evaluate(&pos), fresh);
+
+        pos.set_fen("4k3/8/8/8/8/8/8/R3K3 w - - 90 65").unwrap();
+        let late = evaluator.evaluate(&pos);
+        assert!(0 < late && late < fresh, "{} vs {}", late, fresh);
+
+        pos.set_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 70").unwrap();
+        assert_eq!(evaluator.evaluate(&pos), 0);
+    }
+
+    #[test]
+    fn test_unstoppable_passer_outscores_blockaded_one() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let evaluator = Evaluator::new();
+        let eval = |fen: &str| {
+            let mut pos = Position::empty();
+            pos.set_fen(fen).unwrap();
+            evaluator.evaluate(&pos)
+        };
+
+        // Same material; the black king is either out of the pawn's square
+        // or standing in front of it
+        let unstoppable = eval("8/8/P7/8/8/8/7k/K7 w - - 0 1");
+        let blockaded = eval("k7/8/P7/8/8/8/8/K7 w - - 0 1");
+        assert!(
+            unstoppable - blockaded > evaluator.params().unstoppable_passer_bonus / 2,
+            "{} vs {}",
+            unstoppable,
+            blockaded
+        );
+    }
 }
@@ -3,7 +3,10 @@
 //! This module evaluates king safety including pawn shields,
 //! open files near the king, and king attacks.
 
-use crate::bitboard::{Bitboard, Color, Square};
+use super::params::EvalParams;
+use crate::bitboard::attacks::{king_attacks, piece_attacks};
+use crate::bitboard::position::Position;
+use crate::bitboard::{Bitboard, Color, Piece, Square};
 
 /// Evaluate king safety
 pub fn evaluate_king_safety(
@@ -12,8 +15,27 @@ pub fn evaluate_king_safety(
     white_pawns: Bitboard,
     black_pawns: Bitboard,
 ) -> i32 {
-    let white_safety = evaluate_single_king_safety(white_king, white_pawns, black_pawns, Color::White);
-    let black_safety = evaluate_single_king_safety(black_king, black_pawns, white_pawns, Color::Black);
+    evaluate_king_safety_with_params(
+        white_king,
+        black_king,
+        white_pawns,
+        black_pawns,
+        &EvalParams::default(),
+    )
+}
+
+/// Evaluate king safety using the shield bonus and file penalties from `params`
+pub fn evaluate_king_safety_with_params(
+    white_king: Square,
+    black_king: Square,
+    white_pawns: Bitboard,
+    black_pawns: Bitboard,
+    params: &EvalParams,
+) -> i32 {
+    let white_safety =
+        evaluate_single_king_safety(white_king, white_pawns, black_pawns, Color::White, params);
+    let black_safety =
+        evaluate_single_king_safety(black_king, black_pawns, white_pawns, Color::Black, params);
 
     white_safety - black_safety
 }
@@ -24,20 +46,21 @@ fn evaluate_single_king_safety(
     friendly_pawns: Bitboard,
     enemy_pawns: Bitboard,
     color: Color,
+    params: &EvalParams,
 ) -> i32 {
     let mut score = 0;
 
-    // Pawn shield (3 points per pawn in shield)
-    score += evaluate_pawn_shield(king_sq, friendly_pawns, color) * 3;
+    // Pawn shield
+    score += evaluate_pawn_shield(king_sq, friendly_pawns, color) * params.king_shield_bonus;
 
     // Penalize open files near king
     if king_files_open(king_sq, enemy_pawns) {
-        score -= 20;
+        score -= params.king_open_file_penalty;
     }
 
     // Penalize semi-open files near king
     if king_files_semi_open(king_sq, friendly_pawns, enemy_pawns) {
-        score -= 10;
+        score -= params.king_semi_open_file_penalty;
     }
 
     score
@@ -49,11 +72,12 @@ pub fn evaluate_pawn_shield(king_sq: Square, pawns: Bitboard, color: Color) -> i
     let king_file = king_sq.file();
     let king_rank = king_sq.rank();
 
-    // Check pawns in front of king (ranks 2-3 for white, 6-7 for black)
+    // Check pawns in front of king (ranks 2-3 for white, 6-7 for black).
+    // Ranks off the board (including a wrapped rank below 0) are skipped below.
     let shield_ranks = if color == Color::White {
         [king_rank + 1, king_rank + 2]
     } else {
-        [king_rank - 1, king_rank - 2]
+        [king_rank.wrapping_sub(1), king_rank.wrapping_sub(2)]
     };
 
     // Check files: king file and adjacent files
@@ -83,6 +107,41 @@ pub fn evaluate_pawn_shield(king_sq: Square, pawns: Bitboard, color: Color) -> i
     shield_score
 }
 
+/// Squares around a king of `color` that an attack has to reach
+///
+/// The king's square and its ring of neighbours, plus the squares one rank
+/// beyond the ring towards the enemy, where attackers usually gather.
+pub fn king_attack_zone(king: Square, color: Color) -> Bitboard {
+    let ring = king_attacks(king) | king.to_bitboard();
+    let forward = if color == Color::White {
+        Bitboard(ring.0 << 8)
+    } else {
+        Bitboard(ring.0 >> 8)
+    };
+    ring | forward
+}
+
+/// Number of enemy pieces, the king aside, attacking the zone around the
+/// king of `color`
+pub fn king_zone_attackers(position: &Position, color: Color) -> u32 {
+    let Some(king) = position.piece_bb(Piece::King, color).lsb() else {
+        return 0;
+    };
+    let zone = king_attack_zone(king, color);
+    let enemy = color.opposite();
+    let occupied = position.all_occupancy();
+
+    let mut attackers = 0;
+    for piece in [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for sq in position.piece_bb(piece, enemy).iter() {
+            if !(piece_attacks(piece, sq, enemy, occupied) & zone).is_empty() {
+                attackers += 1;
+            }
+        }
+    }
+    attackers
+}
+
 /// Check if files near the king are open (no pawns on the file)
 pub fn king_files_open(king_sq: Square, enemy_pawns: Bitboard) -> bool {
     let king_file = king_sq.file();
@@ -137,4 +196,24 @@ mod tests {
         let score = evaluate_king_safety(Square::E1, Square::E8, Bitboard::EMPTY, Bitboard::EMPTY);
         assert_eq!(score, 0);
     }
+
+    #[test]
+    fn test_king_attack_zone_and_attackers() {
+        crate::bitboard::magic::init_magics();
+        let zone = king_attack_zone(Square::G1, Color::White);
+        for sq in [Square::F1, Square::F2, Square::G2, Square::H2, Square::H1, Square::G1, Square::G3] {
+            assert!(zone.is_occupied(sq), "{:?} missing", sq);
+        }
+        assert_eq!(zone.count(), 9);
+        // Mirrored for Black
+        let black_zone = king_attack_zone(Square::G8, Color::Black);
+        assert_eq!(black_zone.0, zone.0.swap_bytes());
+
+        // The knight, the rook along the third rank and the pawn reach the
+        // zone; the bishop is blocked by its own knight
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/1b6/8/8/4n2p/r7/5PPP/6K1 w - - 0 1").unwrap();
+        assert_eq!(king_zone_attackers(&pos, Color::White), 3);
+        assert_eq!(king_zone_attackers(&pos, Color::Black), 0);
+    }
 }
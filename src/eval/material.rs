@@ -23,48 +23,14 @@ pub const PIECE_VALUES: [i32; 6] = [
     KING_VALUE,   // King
 ];
 
-/// Evaluate material balance for a position
-///
-/// Returns the material score from the perspective of the side to move.
-/// Positive scores favor white, negative scores favor black.
-pub fn evaluate_material(
-    white_pawns: Bitboard,
-    white_knights: Bitboard,
-    white_bishops: Bitboard,
-    white_rooks: Bitboard,
-    white_queens: Bitboard,
-    white_king: Bitboard,
-    black_pawns: Bitboard,
-    black_knights: Bitboard,
-    black_bishops: Bitboard,
-    black_rooks: Bitboard,
-    black_queens: Bitboard,
-    black_king: Bitboard,
-) -> i32 {
-    let white_material = count_pieces(white_pawns) * PAWN_VALUE
-        + count_pieces(white_knights) * KNIGHT_VALUE
-        + count_pieces(white_bishops) * BISHOP_VALUE
-        + count_pieces(white_rooks) * ROOK_VALUE
-        + count_pieces(white_queens) * QUEEN_VALUE
-        + count_pieces(white_king) * KING_VALUE;
-
-    let black_material = count_pieces(black_pawns) * PAWN_VALUE
-        + count_pieces(black_knights) * KNIGHT_VALUE
-        + count_pieces(black_bishops) * BISHOP_VALUE
-        + count_pieces(black_rooks) * ROOK_VALUE
-        + count_pieces(black_queens) * QUEEN_VALUE
-        + count_pieces(black_king) * KING_VALUE;
-
-    white_material - black_material
-}
-
 /// Count the number of pieces on a bitboard
 #[inline(always)]
 fn count_pieces(bb: Bitboard) -> i32 {
     bb.count() as i32
 }
 
-/// Get the value of a piece
+/// Get the default value of a piece (see `EvalParams::piece_value` for the
+/// configured one)
 #[inline(always)]
 pub fn piece_value(piece: Piece) -> i32 {
     PIECE_VALUES[piece as usize]
@@ -107,8 +73,20 @@ pub fn has_mating_material(
         return false;
     }
 
-    // One minor piece is usually insufficient (except bishop vs bishop of opposite colors)
-    // But we'll be conservative and say it's sufficient unless both sides have no pieces
+    // Against a bare king, a single minor piece cannot mate at all and two
+    // knights only mate if the defender helps, so neither is a forced win
+    if white_minors == 0 || black_minors == 0 {
+        let (knights, minors) = if white_minors == 0 {
+            (count_pieces(black_knights), black_minors)
+        } else {
+            (count_pieces(white_knights), white_minors)
+        };
+        if minors == 1 || (minors == 2 && knights == 2) {
+            return false;
+        }
+    }
+
+    // Minor pieces on both sides can mate with help; be conservative there
     true
 }
 
@@ -124,41 +102,6 @@ mod tests {
         assert_eq!(piece_value(Piece::King), KING_VALUE);
     }
 
-    #[test]
-    fn test_material_evaluation() {
-        // Starting position material balance should be 0
-        let white_pawns = Bitboard(0x0000_0000_0000_FF00);
-        let white_knights = Bitboard(0x0000_0000_0000_0042);
-        let white_bishops = Bitboard(0x0000_0000_0000_0024);
-        let white_rooks = Bitboard(0x0000_0000_0000_0081);
-        let white_queens = Bitboard(0x0000_0000_0000_0008);
-        let white_king = Bitboard(0x0000_0000_0000_0010);
-
-        let black_pawns = Bitboard(0x00FF_0000_0000_0000);
-        let black_knights = Bitboard(0x4200_0000_0000_0000);
-        let black_bishops = Bitboard(0x2400_0000_0000_0000);
-        let black_rooks = Bitboard(0x8100_0000_0000_0000);
-        let black_queens = Bitboard(0x0800_0000_0000_0000);
-        let black_king = Bitboard(0x1000_0000_0000_0000);
-
-        let score = evaluate_material(
-            white_pawns,
-            white_knights,
-            white_bishops,
-            white_rooks,
-            white_queens,
-            white_king,
-            black_pawns,
-            black_knights,
-            black_bishops,
-            black_rooks,
-            black_queens,
-            black_king,
-        );
-
-        assert_eq!(score, 0);
-    }
-
     #[test]
     fn test_mating_material() {
         // King vs king
@@ -175,6 +118,14 @@ mod tests {
             Bitboard::EMPTY
         ));
 
+        // Two knights against a bare king, but not two bishops
+        let mut knights = Bitboard::EMPTY;
+        knights.set(Square::B1);
+        knights.set(Square::G1);
+        let e = Bitboard::EMPTY;
+        assert!(!has_mating_material(e, e, e, e, e, e, knights, e, e, e));
+        assert!(has_mating_material(e, e, knights, e, e, e, e, e, e, e));
+
         // King and pawn vs king
         let mut white_pawns = Bitboard::EMPTY;
         white_pawns.set(Square::E4);
@@ -1,22 +1,35 @@
 //! Evaluation module - Static position evaluation
 //!
 //! This module provides static evaluation functions for chess positions,
-//! including material balance, piece-square tables, pawn structure, and king safety.
+//! including material balance, piece-square tables, pawn structure, bishop
+//! quality, rook placement, space, and king safety.
+//! The weights of all terms are tunable through `EvalParams`, and the `tune`
+//! module fits them to labeled positions.
 
+pub mod bishop;
 pub mod evaluator;
 pub mod king_safety;
 pub mod material;
+pub mod params;
 pub mod pawn;
 pub mod pst;
+pub mod rook;
+pub mod space;
+pub mod tune;
 
 pub use self::prelude::*;
 
 pub mod prelude {
+    pub use super::bishop::*;
     pub use super::evaluator::*;
     pub use super::king_safety::*;
     pub use super::material::*;
+    pub use super::params::*;
     pub use super::pawn::*;
     pub use super::pst::*;
+    pub use super::rook::*;
+    pub use super::space::*;
+    pub use super::tune::*;
 }
 
 pub mod lib {
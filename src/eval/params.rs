@@ -0,0 +1,100 @@
+//! Evaluation parameters - Tunable weights used by the evaluator
+//!
+//! Every weight the evaluator applies lives in `EvalParams`, so tuners and
+//! A/B tests can swap in their own values via `Evaluator::with_params`.
+//! The defaults reproduce the engine's hand-picked values.
+
+use super::material::PIECE_VALUES;
+use crate::bitboard::Piece;
+
+/// Tunable evaluation weights
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct EvalParams {
+    /// Piece values in centipawns, indexed by `Piece`
+    pub piece_values: [i32; 6],
+    /// Weight of the piece-square table term
+    pub pst_weight: f32,
+    /// Weight of the pawn structure term
+    pub pawn_structure_weight: f32,
+    /// Weight of the king safety term
+    pub king_safety_weight: f32,
+    /// Weight of the mobility term
+    pub mobility_weight: f32,
+    /// Bonus per point of pawn shield in front of the king
+    pub king_shield_bonus: i32,
+    /// Penalty for an open file next to the king
+    pub king_open_file_penalty: i32,
+    /// Penalty for a semi-open file next to the king
+    pub king_semi_open_file_penalty: i32,
+    /// Bonus for a passed pawn the defending king can't catch in a pawn ending
+    pub unstoppable_passer_bonus: i32,
+    /// Penalty for a passed pawn whose square the defending king stands in
+    /// (doubled when the king blocks its path)
+    pub passer_king_penalty: i32,
+    /// Penalty per friendly pawn on a bishop's color complex
+    pub bad_bishop_penalty: i32,
+    /// Penalty for a bishop trapped on a7/h7 (a2/h2 for Black) by an enemy pawn
+    pub trapped_bishop_penalty: i32,
+    /// Bonus for each pair of rooks defending each other on a file or rank
+    pub connected_rooks_bonus: i32,
+    /// Bonus for a rook behind a passed pawn of either side
+    pub rook_behind_passer_bonus: i32,
+    /// Bonus per square of space, at full strength with all pieces on and
+    /// fading out with the game phase
+    pub space_bonus: i32,
+    /// Halfmove clock from which the evaluation shrinks linearly towards
+    /// zero at the fifty-move limit (100 plies)
+    pub fifty_move_scale_start: u32,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams {
+            piece_values: PIECE_VALUES,
+            pst_weight: 0.2,
+            pawn_structure_weight: 0.15,
+            king_safety_weight: 0.15,
+            mobility_weight: 0.1,
+            king_shield_bonus: 3,
+            king_open_file_penalty: 20,
+            king_semi_open_file_penalty: 10,
+            unstoppable_passer_bonus: 500,
+            passer_king_penalty: 15,
+            bad_bishop_penalty: 3,
+            trapped_bishop_penalty: 100,
+            connected_rooks_bonus: 15,
+            rook_behind_passer_bonus: 20,
+            space_bonus: 2,
+            fifty_move_scale_start: 80,
+        }
+    }
+}
+
+impl EvalParams {
+    /// Value of `piece` in centipawns under these weights
+    pub fn piece_value(&self, piece: Piece) -> i32 {
+        self.piece_values[piece as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::position::Position;
+    use crate::eval::Evaluator;
+
+    #[test]
+    fn test_piece_value_override_shifts_imbalance() {
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/pppb4/8/8/8/8/PPPN4/4K3 w - - 0 1").unwrap();
+
+        let mut params = EvalParams::default();
+        assert_eq!(params.piece_value(Piece::Knight), PIECE_VALUES[Piece::Knight as usize]);
+        let default_eval = Evaluator::with_params(params).evaluate(&pos);
+
+        params.piece_values[Piece::Knight as usize] += 80;
+        assert_eq!(params.piece_value(Piece::Knight), 400);
+        assert_eq!(Evaluator::with_params(params).evaluate(&pos), default_eval + 80);
+    }
+}
@@ -3,6 +3,8 @@
 //! This module evaluates pawn structure including doubled pawns,
 //! isolated pawns, passed pawns, and pawn chains.
 
+use super::params::EvalParams;
+use crate::bitboard::attacks::pawn_attacks;
 use crate::bitboard::{Bitboard, Color, Square};
 
 /// Evaluate pawn structure
@@ -16,6 +18,8 @@ pub fn evaluate_pawn_structure(white_pawns: Bitboard, black_pawns: Bitboard) ->
 /// Evaluate pawn structure for one color
 fn evaluate_single_color_pawns(friendly_pawns: Bitboard, enemy_pawns: Bitboard, color: Color) -> i32 {
     let mut score = 0;
+    let isolated = isolated_pawns(friendly_pawns);
+    let passed = passed_pawns(friendly_pawns, enemy_pawns, color);
 
     for pawn_sq in friendly_pawns.iter() {
         // Doubled pawns penalty
@@ -24,12 +28,12 @@ fn evaluate_single_color_pawns(friendly_pawns: Bitboard, enemy_pawns: Bitboard,
         }
 
         // Isolated pawns penalty
-        if is_isolated_pawn(pawn_sq, friendly_pawns) {
+        if isolated.is_occupied(pawn_sq) {
             score -= 10;
         }
 
         // Passed pawns bonus
-        if is_passed_pawn(pawn_sq, color, enemy_pawns) {
+        if passed.is_occupied(pawn_sq) {
             let rank = pawn_sq.rank();
             let advancement = if color == Color::White {
                 rank as i32
@@ -43,6 +47,54 @@ fn evaluate_single_color_pawns(friendly_pawns: Bitboard, enemy_pawns: Bitboard,
     score
 }
 
+/// King-aware score of the passed pawns of `color` against the defending
+/// king on `enemy_king`
+///
+/// Uses the rule of the square: a passer whose promotion square the king
+/// can't reach in time is unstoppable once the defender has only pawns
+/// left, while a passer the king can catch, or whose path it blocks, is
+/// worth less.
+pub fn evaluate_passer_race(
+    pawns: Bitboard,
+    enemy_pawns: Bitboard,
+    color: Color,
+    enemy_king: Square,
+    defender_to_move: bool,
+    defender_has_pieces: bool,
+    params: &EvalParams,
+) -> i32 {
+    let mut score = 0;
+
+    for pawn_sq in pawns.iter() {
+        if !is_passed_pawn(pawn_sq, color, enemy_pawns) {
+            continue;
+        }
+
+        let (promotion_rank, start_rank) = if color == Color::White { (7, 1) } else { (0, 6) };
+        let promotion_sq = Square::new(pawn_sq.file(), promotion_rank);
+        // A pawn on its starting rank saves a move with the double push
+        let pawn_moves = promotion_rank.abs_diff(pawn_sq.rank()) - (pawn_sq.rank() == start_rank) as u8;
+        let king_moves = enemy_king.distance(promotion_sq).saturating_sub(defender_to_move as u8);
+
+        let blocks_path = enemy_king.file() == pawn_sq.file()
+            && if color == Color::White {
+                enemy_king.rank() > pawn_sq.rank()
+            } else {
+                enemy_king.rank() < pawn_sq.rank()
+            };
+
+        if blocks_path {
+            score -= 2 * params.passer_king_penalty;
+        } else if king_moves <= pawn_moves {
+            score -= params.passer_king_penalty;
+        } else if !defender_has_pieces {
+            score += params.unstoppable_passer_bonus;
+        }
+    }
+
+    score
+}
+
 /// Check if a pawn is passed
 pub fn is_passed_pawn(pawn_sq: Square, color: Color, enemy_pawns: Bitboard) -> bool {
     let file = pawn_sq.file();
@@ -97,6 +149,49 @@ pub fn is_doubled_pawn(pawn_sq: Square, friendly_pawns: Bitboard) -> bool {
     (friendly_pawns & file_bb).count() > 1
 }
 
+/// Squares ahead of the pawns of `color` on their files, up to the last rank
+///
+/// With the other color's direction this is the span behind the pawns.
+pub fn pawn_front_span(pawns: Bitboard, color: Color) -> Bitboard {
+    let mut span = Bitboard::EMPTY;
+    let mut front = pawns.0;
+    for _ in 0..7 {
+        front = if color == Color::White { front << 8 } else { front >> 8 };
+        span |= Bitboard(front);
+    }
+    span
+}
+
+/// Squares on the files next to those of `bb`, on the same ranks
+fn adjacent_files(bb: Bitboard) -> Bitboard {
+    Bitboard(((bb & !Bitboard::FILE_H).0 << 1) | ((bb & !Bitboard::FILE_A).0 >> 1))
+}
+
+/// Squares the pawns of `color` can ever attack as they advance: the
+/// adjacent files ahead of each pawn, up to the last rank
+pub fn pawn_attack_spans(pawns: Bitboard, color: Color) -> Bitboard {
+    adjacent_files(pawn_front_span(pawns, color))
+}
+
+/// The pawns with no friendly pawn on an adjacent file, for the whole set
+/// at once (see `is_isolated_pawn`)
+pub fn isolated_pawns(pawns: Bitboard) -> Bitboard {
+    let files = pawns | pawn_front_span(pawns, Color::White) | pawn_front_span(pawns, Color::Black);
+    pawns & !adjacent_files(files)
+}
+
+/// The pawns of `color` with no enemy pawn ahead of them on their own or an
+/// adjacent file, for the whole set at once (see `is_passed_pawn`)
+pub fn passed_pawns(pawns: Bitboard, enemy_pawns: Bitboard, color: Color) -> Bitboard {
+    let enemy = color.opposite();
+    pawns & !(pawn_front_span(enemy_pawns, enemy) | pawn_attack_spans(enemy_pawns, enemy))
+}
+
+/// Squares attacked by the pawns of `color`
+pub fn pawn_attack_map(pawns: Bitboard, color: Color) -> Bitboard {
+    pawns.iter().fold(Bitboard::EMPTY, |attacked, sq| attacked | pawn_attacks(sq, color))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -106,4 +201,64 @@ mod tests {
         let score = evaluate_pawn_structure(Bitboard::EMPTY, Bitboard::EMPTY);
         assert_eq!(score, 0);
     }
+
+    #[test]
+    fn test_passer_race_rule_of_the_square() {
+        let params = EvalParams::default();
+        let pawn = Square::A5.to_bitboard();
+        let race = |king, defender_to_move, defender_has_pieces| {
+            evaluate_passer_race(pawn, Bitboard::EMPTY, Color::White, king, defender_to_move, defender_has_pieces, &params)
+        };
+
+        // Three pawn moves against four king moves: only the tempo decides
+        assert_eq!(race(Square::E5, false, false), params.unstoppable_passer_bonus);
+        assert_eq!(race(Square::E5, true, false), -params.passer_king_penalty);
+        // A defender with pieces left can still stop the pawn
+        assert_eq!(race(Square::E5, false, true), 0);
+        // King in front of the pawn
+        assert_eq!(race(Square::A8, true, false), -2 * params.passer_king_penalty);
+    }
+
+    #[test]
+    fn test_pawn_spans() {
+        let pawns = Square::D4.to_bitboard() | Square::G2.to_bitboard();
+        let front = pawn_front_span(pawns, Color::White);
+        assert_eq!(front.count(), 4 + 6);
+        assert!(front.is_occupied(Square::D8) && front.is_occupied(Square::G3));
+        assert!(!front.is_occupied(Square::D4) && !front.is_occupied(Square::D3));
+        assert_eq!(pawn_front_span(pawns, Color::Black).count(), 3 + 1);
+
+        let attacks = pawn_attack_map(pawns | Square::A2.to_bitboard(), Color::White);
+        for sq in [Square::C5, Square::E5, Square::F3, Square::H3, Square::B3] {
+            assert!(attacks.is_occupied(sq), "{:?}", sq);
+        }
+        assert_eq!(attacks.count(), 5);
+
+        let spans = pawn_attack_spans(pawns, Color::White);
+        assert_eq!(spans.count(), 2 * 4 + 2 * 6);
+        assert!(spans.is_occupied(Square::C8) && spans.is_occupied(Square::H3));
+        assert!(!spans.is_occupied(Square::C4) && !spans.is_occupied(Square::D5));
+        assert_eq!(pawn_attack_spans(Square::A7.to_bitboard(), Color::White), Square::B8.to_bitboard());
+    }
+
+    #[test]
+    fn test_span_structure_matches_per_pawn_checks() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(13);
+        let middle = !(Bitboard::RANK_1 | Bitboard::RANK_8);
+        for _ in 0..500 {
+            let white = Bitboard(rng.gen::<u64>() & rng.gen::<u64>()) & middle;
+            let black = Bitboard(rng.gen::<u64>() & rng.gen::<u64>()) & middle & !white;
+            for (pawns, enemy, color) in [(white, black, Color::White), (black, white, Color::Black)] {
+                let isolated = isolated_pawns(pawns);
+                let passed = passed_pawns(pawns, enemy, color);
+                for sq in pawns.iter() {
+                    assert_eq!(isolated.is_occupied(sq), is_isolated_pawn(sq, pawns), "{:?}", sq);
+                    assert_eq!(passed.is_occupied(sq), is_passed_pawn(sq, color, enemy), "{:?}", sq);
+                }
+                assert!(((isolated | passed) & !pawns).is_empty());
+            }
+        }
+    }
 }
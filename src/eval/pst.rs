@@ -3,7 +3,8 @@
 //! This module provides piece-square tables for positional evaluation,
 //! giving bonuses to pieces based on their position on the board.
 
-use crate::bitboard::{Bitboard, Color, Piece, Square};
+use crate::bitboard::position::Position;
+use crate::bitboard::{Color, Piece, Square};
 
 /// Piece-square table for pawns (from white's perspective)
 pub const PAWN_PST: [i32; 64] = [
@@ -48,76 +49,57 @@ pub const KING_PST: [i32; 64] = [
     10, 30, 20,
 ];
 
+/// Piece-square table for the king in the endgame, drawing it to the center
+/// where it supports its pawns and stops the enemy's
+pub const KING_PST_ENDGAME: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50, -30, -20, -10, 0, 0, -10, -20, -30, -30, -10, 20, 30,
+    30, 20, -10, -30, -30, -10, 30, 40, 40, 30, -10, -30, -30, -10, 30, 40, 40, 30, -10, -30, -30,
+    -10, 20, 30, 30, 20, -10, -30, -30, -20, -10, 0, 0, -10, -20, -30, -50, -40, -30, -20, -20, -30,
+    -40, -50,
+];
+
+/// Game phase (see `Position::phase`) at or below which the endgame king table
+/// applies: no more than a rook and a minor per side
+pub const ENDGAME_PHASE: i32 = 6;
+
 /// Array of piece-square tables indexed by piece type
 pub const PIECE_PST: [[i32; 64]; 6] = [
     PAWN_PST, KNIGHT_PST, BISHOP_PST, ROOK_PST, QUEEN_PST, KING_PST,
 ];
 
-/// Get the piece-square table value for a piece on a square
+/// Get the piece-square table value for a piece on a square, the king using
+/// its endgame table when `endgame` is set
 #[inline(always)]
-pub fn pst_value(piece: Piece, square: Square, color: Color) -> i32 {
-    let table = &PIECE_PST[piece as usize];
+pub fn pst_value(piece: Piece, square: Square, color: Color, endgame: bool) -> i32 {
+    let table = if endgame && piece == Piece::King {
+        &KING_PST_ENDGAME
+    } else {
+        &PIECE_PST[piece as usize]
+    };
     let index = if color == Color::White {
         square.0 as usize
     } else {
         // Flip the square for black
-        (square.0 ^ 56) as usize
+        square.flip_rank().0 as usize
     };
     table[index]
 }
 
-/// Evaluate piece-square table bonuses for all pieces
-pub fn evaluate_pst(
-    white_pawns: Bitboard,
-    white_knights: Bitboard,
-    white_bishops: Bitboard,
-    white_rooks: Bitboard,
-    white_queens: Bitboard,
-    white_king: Square,
-    black_pawns: Bitboard,
-    black_knights: Bitboard,
-    black_bishops: Bitboard,
-    black_rooks: Bitboard,
-    black_queens: Bitboard,
-    black_king: Square,
-) -> i32 {
+/// Evaluate piece-square table bonuses for all pieces, from White's
+/// perspective, with the endgame king table at `phase` up to `ENDGAME_PHASE`
+pub fn evaluate_pst(position: &Position, phase: i32) -> i32 {
+    let endgame = phase <= ENDGAME_PHASE;
     let mut score = 0;
 
-    // White pieces
-    for sq in white_pawns.iter() {
-        score += pst_value(Piece::Pawn, sq, Color::White);
-    }
-    for sq in white_knights.iter() {
-        score += pst_value(Piece::Knight, sq, Color::White);
-    }
-    for sq in white_bishops.iter() {
-        score += pst_value(Piece::Bishop, sq, Color::White);
-    }
-    for sq in white_rooks.iter() {
-        score += pst_value(Piece::Rook, sq, Color::White);
-    }
-    for sq in white_queens.iter() {
-        score += pst_value(Piece::Queen, sq, Color::White);
-    }
-    score += pst_value(Piece::King, white_king, Color::White);
-
-    // Black pieces (negated because PSTs are from white's perspective)
-    for sq in black_pawns.iter() {
-        score -= pst_value(Piece::Pawn, sq, Color::Black);
-    }
-    for sq in black_knights.iter() {
-        score -= pst_value(Piece::Knight, sq, Color::Black);
-    }
-    for sq in black_bishops.iter() {
-        score -= pst_value(Piece::Bishop, sq, Color::Black);
-    }
-    for sq in black_rooks.iter() {
-        score -= pst_value(Piece::Rook, sq, Color::Black);
-    }
-    for sq in black_queens.iter() {
-        score -= pst_value(Piece::Queen, sq, Color::Black);
+    for piece in [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King] {
+        for sq in position.piece_bb(piece, Color::White).iter() {
+            score += pst_value(piece, sq, Color::White, endgame);
+        }
+        // Black squares are flipped, so its bonuses count against White
+        for sq in position.piece_bb(piece, Color::Black).iter() {
+            score -= pst_value(piece, sq, Color::Black, endgame);
+        }
     }
-    score -= pst_value(Piece::King, black_king, Color::Black);
 
     score
 }
@@ -129,33 +111,39 @@ mod tests {
     #[test]
     fn test_pst_value() {
         // Test center square bonus for knight
-        let center_value = pst_value(Piece::Knight, Square::E4, Color::White);
+        let center_value = pst_value(Piece::Knight, Square::E4, Color::White, false);
         assert!(center_value > 0);
 
         // Test that black gets the same bonus on mirrored square
-        let black_value = pst_value(Piece::Knight, Square::E5, Color::Black);
+        let black_value = pst_value(Piece::Knight, Square::E5, Color::Black, false);
         assert_eq!(center_value, black_value);
     }
 
     #[test]
     fn test_pst_evaluation() {
-        // Simple test with one piece each
-        let score = evaluate_pst(
-            Square::E4.to_bitboard(), // white pawn on good central square
-            Bitboard::EMPTY,
-            Bitboard::EMPTY,
-            Bitboard::EMPTY,
-            Bitboard::EMPTY,
-            Square::E1,                        // white king
-            Square::H2.to_bitboard(), // black pawn on bad square
-            Bitboard::EMPTY,
-            Bitboard::EMPTY,
-            Bitboard::EMPTY,
-            Bitboard::EMPTY,
-            Square::E8, // black king
-        );
+        // White pawn on a good central square, black pawn on a bad one
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/8/8/4P3/8/7p/4K3 w - - 0 1").unwrap();
+        let score = evaluate_pst(&pos, pos.phase() as i32);
 
         // Should be positive (white has better position)
         assert!(score > 0);
     }
+
+    #[test]
+    fn test_endgame_king_table() {
+        // The middlegame table keeps the king away from the center, the
+        // endgame one draws it there
+        assert!(pst_value(Piece::King, Square::E4, Color::White, false) < 0);
+        assert!(pst_value(Piece::King, Square::E4, Color::White, true) > 0);
+        assert!(pst_value(Piece::King, Square::D5, Color::Black, true) > 0);
+        let corner = pst_value(Piece::King, Square::H1, Color::White, true);
+        assert!(corner < pst_value(Piece::King, Square::E4, Color::White, true));
+
+        // Other pieces don't change tables
+        assert_eq!(
+            pst_value(Piece::Knight, Square::E4, Color::White, true),
+            pst_value(Piece::Knight, Square::E4, Color::White, false)
+        );
+    }
 }
@@ -0,0 +1,97 @@
+//! Rook evaluation
+//!
+//! This module evaluates rook placement: rooks defending each other along a
+//! file or rank, and rooks behind passed pawns.
+
+use super::params::EvalParams;
+use super::pawn::is_passed_pawn;
+use crate::bitboard::attacks::ray_between;
+use crate::bitboard::{Bitboard, Color, Square};
+
+/// Score of the rooks of `color`
+///
+/// Each pair of rooks on a shared file or rank with nothing between them
+/// earns the connected bonus. Each rook on the file of a passed pawn, either
+/// side's, earns the behind-passer bonus when it stands behind the pawn
+/// with nothing in between.
+pub fn evaluate_rooks(
+    rooks: Bitboard,
+    pawns: Bitboard,
+    enemy_pawns: Bitboard,
+    occupied: Bitboard,
+    color: Color,
+    params: &EvalParams,
+) -> i32 {
+    let mut score = 0;
+
+    for rook in rooks.iter() {
+        for other in rooks.iter().filter(|&other| other.0 > rook.0) {
+            let shares_line = rook.file() == other.file() || rook.rank() == other.rank();
+            if shares_line && (ray_between(rook, other) & occupied).is_empty() {
+                score += params.connected_rooks_bonus;
+            }
+        }
+
+        let file = Bitboard::file(rook.file());
+        for pawn in (pawns & file).iter() {
+            if is_passed_pawn(pawn, color, enemy_pawns) && is_behind(rook, pawn, color, occupied) {
+                score += params.rook_behind_passer_bonus;
+            }
+        }
+        for pawn in (enemy_pawns & file).iter() {
+            if is_passed_pawn(pawn, color.opposite(), pawns)
+                && is_behind(rook, pawn, color.opposite(), occupied)
+            {
+                score += params.rook_behind_passer_bonus;
+            }
+        }
+    }
+
+    score
+}
+
+/// Whether `rook` stands behind the `pawn_color` pawn on its file, relative
+/// to the way the pawn advances, with no piece in between
+fn is_behind(rook: Square, pawn: Square, pawn_color: Color, occupied: Bitboard) -> bool {
+    let behind = if pawn_color == Color::White {
+        rook.rank() < pawn.rank()
+    } else {
+        rook.rank() > pawn.rank()
+    };
+    behind && (ray_between(rook, pawn) & occupied).is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(rooks: &[Square], pawns: &[Square], enemy_pawns: &[Square], color: Color) -> i32 {
+        let board = |squares: &[Square]| squares.iter().fold(Bitboard::EMPTY, |bb, sq| bb | sq.to_bitboard());
+        let (rooks, pawns, enemy_pawns) = (board(rooks), board(pawns), board(enemy_pawns));
+        evaluate_rooks(rooks, pawns, enemy_pawns, rooks | pawns | enemy_pawns, color, &EvalParams::default())
+    }
+
+    #[test]
+    fn test_doubled_rooks_beat_split_rooks() {
+        let doubled = score(&[Square::D1, Square::D2], &[Square::A2, Square::H2], &[Square::A7, Square::H7], Color::White);
+        let split = score(&[Square::D1, Square::F3], &[Square::A2, Square::H2], &[Square::A7, Square::H7], Color::White);
+        assert!(doubled > split);
+
+        // A piece between the rooks disconnects them
+        let blocked = score(&[Square::D1, Square::D4], &[Square::D2], &[Square::D7], Color::White);
+        assert_eq!(blocked, 0);
+    }
+
+    #[test]
+    fn test_rook_behind_passer() {
+        // Behind the friendly passer rather than in front of it
+        let behind = score(&[Square::D1], &[Square::D6], &[], Color::White);
+        let in_front = score(&[Square::D8], &[Square::D6], &[], Color::White);
+        assert!(behind > in_front);
+
+        // Behind an enemy passer counts too, from either side's view
+        assert!(score(&[Square::D8], &[], &[Square::D3], Color::White) > 0);
+        assert!(score(&[Square::D1], &[], &[Square::D6], Color::Black) > 0);
+        assert_eq!(score(&[Square::D1], &[], &[Square::D3], Color::White), 0);
+    }
+}
@@ -0,0 +1,68 @@
+//! Space evaluation
+//!
+//! This module evaluates space: the safe squares a side controls in the
+//! center of its own half, counted twice when sheltered behind its pawns.
+
+use super::pawn::{pawn_attack_map, pawn_front_span};
+use crate::bitboard::{Bitboard, Color};
+
+/// Central files c to f on the three ranks behind a side's fourth rank
+const WHITE_SPACE_AREA: Bitboard = Bitboard(0x0000_0000_3C3C_3C00);
+const BLACK_SPACE_AREA: Bitboard = Bitboard(0x003C_3C3C_0000_0000);
+
+/// Space count for `color`
+///
+/// Squares of the central area not occupied by its own pawns nor attacked
+/// by enemy pawns, plus those of them behind its own pawns again.
+pub fn evaluate_space(pawns: Bitboard, enemy_pawns: Bitboard, color: Color) -> i32 {
+    let area = if color == Color::White {
+        WHITE_SPACE_AREA
+    } else {
+        BLACK_SPACE_AREA
+    };
+    let safe = area & !pawns & !pawn_attack_map(enemy_pawns, color.opposite());
+    let behind = pawn_front_span(pawns, color.opposite());
+
+    (safe.count() + (safe & behind).count()) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square;
+
+    #[test]
+    fn test_pawn_advance_gains_space() {
+        let board = |squares: &[Square]| {
+            squares
+                .iter()
+                .fold(Bitboard::EMPTY, |bb, sq| bb | sq.to_bitboard())
+        };
+        let black = board(&[Square::C7, Square::D7, Square::E7, Square::F7]);
+
+        let home = evaluate_space(board(&[Square::D2, Square::E2]), black, Color::White);
+        let advanced = evaluate_space(board(&[Square::D4, Square::E4]), black, Color::White);
+        assert_eq!((home, advanced), (10, 14));
+
+        // Mirrored for Black
+        let white = board(&[Square::C2, Square::D2, Square::E2, Square::F2]);
+        assert_eq!(
+            evaluate_space(board(&[Square::D5, Square::E5]), white, Color::Black),
+            14
+        );
+
+        // Squares enemy pawns attack don't count
+        let attacked = evaluate_space(
+            board(&[Square::D4, Square::E4]),
+            board(&[Square::C5]),
+            Color::White,
+        );
+        assert_eq!(attacked, advanced);
+        let contested = evaluate_space(
+            board(&[Square::D2, Square::E2]),
+            board(&[Square::C5, Square::F4]),
+            Color::White,
+        );
+        assert!(contested < home);
+    }
+}
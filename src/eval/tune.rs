@@ -0,0 +1,197 @@
+//! Texel tuning - Fit evaluation weights to game results
+//!
+//! Reads labeled positions (`FEN;result` lines, result being 0, 0.5 or 1 from
+//! white's point of view), measures how well the static evaluation predicts
+//! the results, and improves `EvalParams` with a simple local search.
+
+use super::evaluator::Evaluator;
+use super::params::EvalParams;
+use crate::bitboard::position::Position;
+use crate::bitboard::Color;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Default scaling constant of the score-to-probability sigmoid
+pub const DEFAULT_SIGMOID_K: f64 = 1.0;
+
+/// Number of tunable entries in the parameter vector
+const PARAM_COUNT: usize = 12;
+
+/// A position labeled with the result of the game it came from
+#[derive(Clone)]
+pub struct TrainingPosition {
+    /// The position
+    pub position: Position,
+    /// Game result from white's point of view (0, 0.5 or 1)
+    pub result: f64,
+}
+
+/// Parse one `FEN;result` line, returning `None` if it is malformed
+pub fn parse_training_line(line: &str) -> Option<TrainingPosition> {
+    let (fen, result) = line.rsplit_once(';')?;
+    let result: f64 = result.trim().parse().ok()?;
+    if !(0.0..=1.0).contains(&result) {
+        return None;
+    }
+
+    let mut position = Position::empty();
+    position.set_fen(fen.trim()).ok()?;
+    Some(TrainingPosition { position, result })
+}
+
+/// Load labeled positions from a file, skipping malformed lines
+pub fn load_training_data<P: AsRef<Path>>(path: P) -> io::Result<Vec<TrainingPosition>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut data = Vec::new();
+    for line in reader.lines() {
+        if let Some(entry) = parse_training_line(&line?) {
+            data.push(entry);
+        }
+    }
+    Ok(data)
+}
+
+/// Expected score (win probability) for a centipawn evaluation
+pub fn sigmoid(score: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score / 400.0))
+}
+
+/// Mean logistic (cross-entropy) loss of the static evaluation against the results
+pub fn evaluation_loss(params: &EvalParams, data: &[TrainingPosition], k: f64) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let evaluator = Evaluator::with_params(*params);
+    let total: f64 = data
+        .iter()
+        .map(|entry| {
+            // The evaluator scores from the side to move; results are from white's side
+            let score = evaluator.evaluate(&entry.position) as f64;
+            let white_score = if entry.position.side_to_move == Color::White {
+                score
+            } else {
+                -score
+            };
+
+            let predicted = sigmoid(white_score, k).clamp(1e-9, 1.0 - 1e-9);
+            -(entry.result * predicted.ln() + (1.0 - entry.result) * (1.0 - predicted).ln())
+        })
+        .sum();
+
+    total / data.len() as f64
+}
+
+/// Flatten the tunable weights (the king value is fixed)
+fn to_vector(params: &EvalParams) -> [f64; PARAM_COUNT] {
+    [
+        params.piece_values[0] as f64,
+        params.piece_values[1] as f64,
+        params.piece_values[2] as f64,
+        params.piece_values[3] as f64,
+        params.piece_values[4] as f64,
+        params.pst_weight as f64,
+        params.pawn_structure_weight as f64,
+        params.king_safety_weight as f64,
+        params.mobility_weight as f64,
+        params.king_shield_bonus as f64,
+        params.king_open_file_penalty as f64,
+        params.king_semi_open_file_penalty as f64,
+    ]
+}
+
+/// Rebuild parameters from a vector produced by `to_vector`
+fn from_vector(base: &EvalParams, v: &[f64; PARAM_COUNT]) -> EvalParams {
+    let mut params = *base;
+    for (piece, &value) in v[..5].iter().enumerate() {
+        params.piece_values[piece] = value.round() as i32;
+    }
+    params.pst_weight = v[5] as f32;
+    params.pawn_structure_weight = v[6] as f32;
+    params.king_safety_weight = v[7] as f32;
+    params.mobility_weight = v[8] as f32;
+    params.king_shield_bonus = v[9].round() as i32;
+    params.king_open_file_penalty = v[10].round() as i32;
+    params.king_semi_open_file_penalty = v[11].round() as i32;
+    params
+}
+
+/// Step size used when nudging each entry of the parameter vector
+const STEP_SIZES: [f64; PARAM_COUNT] = [5.0, 5.0, 5.0, 5.0, 5.0, 0.02, 0.02, 0.02, 0.02, 1.0, 1.0, 1.0];
+
+/// One pass of local search: nudge each weight up or down by its step and
+/// keep the change if it lowers the loss
+///
+/// Returns the improved parameters and their loss.
+pub fn local_search_step(params: &EvalParams, data: &[TrainingPosition], k: f64) -> (EvalParams, f64) {
+    let mut best = *params;
+    let mut best_loss = evaluation_loss(&best, data, k);
+
+    for i in 0..PARAM_COUNT {
+        for direction in [1.0, -1.0] {
+            let mut v = to_vector(&best);
+            v[i] += direction * STEP_SIZES[i];
+            let candidate = from_vector(&best, &v);
+            let loss = evaluation_loss(&candidate, data, k);
+            if loss < best_loss {
+                best = candidate;
+                best_loss = loss;
+                break;
+            }
+        }
+    }
+
+    (best, best_loss)
+}
+
+/// Run local search until it stops improving or `max_iterations` passes are done
+pub fn tune(params: &EvalParams, data: &[TrainingPosition], k: f64, max_iterations: usize) -> EvalParams {
+    let mut best = *params;
+    let mut best_loss = evaluation_loss(&best, data, k);
+
+    for _ in 0..max_iterations {
+        let (candidate, loss) = local_search_step(&best, data, k);
+        if loss >= best_loss {
+            break;
+        }
+        best = candidate;
+        best_loss = loss;
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_training_line() {
+        let entry = parse_training_line("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1; 1").unwrap();
+        assert_eq!(entry.result, 1.0);
+        assert!(parse_training_line("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1;2").is_none());
+        assert!(parse_training_line("not a fen;0.5").is_none());
+    }
+
+    #[test]
+    fn test_local_search_step_lowers_loss() {
+        crate::bitboard::magic::init_magics();
+        let lines = [
+            "4k3/8/8/8/8/8/PPP5/4K3 w - - 0 1;1",
+            "4k3/8/8/8/8/8/PPP5/4K3 b - - 0 1;1",
+            "4k3/ppp5/8/8/8/8/8/4K3 w - - 0 1;0",
+            "4k3/pp6/8/8/8/8/P7/4K3 b - - 0 1;0",
+            "4k3/p7/8/8/8/8/P7/4K3 w - - 0 1;0.5",
+        ];
+        let data: Vec<TrainingPosition> =
+            lines.iter().map(|line| parse_training_line(line).unwrap()).collect();
+
+        let params = EvalParams::default();
+        let before = evaluation_loss(&params, &data, DEFAULT_SIGMOID_K);
+        let (tuned, after) = local_search_step(&params, &data, DEFAULT_SIGMOID_K);
+
+        assert!(after < before);
+        assert_eq!(after, evaluation_loss(&tuned, &data, DEFAULT_SIGMOID_K));
+    }
+}
@@ -17,6 +17,7 @@ pub mod utils;
 // Re-export commonly used types
 pub use bitboard::{Bitboard, CastleRights, Color, Piece, Square};
 pub use movegen::{Move, MoveList};
+pub use search::{SearchEngine, SearchLimits};
 pub use uci::UciEngine;
 
 /// Project-wide prelude for internal use
@@ -4,6 +4,7 @@
 //! It generates pseudo-legal moves that may need to be validated for legality.
 
 use crate::bitboard::attacks::*;
+use crate::bitboard::position::Position;
 use crate::bitboard::{Bitboard, Color, Piece, Square};
 use arrayvec::ArrayVec;
 
@@ -195,6 +196,28 @@ impl MoveList {
     pub fn iter_mut(&mut self) -> std::slice::IterMut<Move> {
         self.moves.iter_mut()
     }
+
+    /// Sort the moves by `score`, highest first, keeping the generation
+    /// order of equal scores
+    ///
+    /// Scores live in a stack array next to the moves, so sorting never
+    /// allocates; insertion sort is fast for lists this short. Any ordered
+    /// key works as a score, e.g. one with tie-breaks for a total order.
+    pub fn insertion_sort_by_score<K: Ord + Copy, F: FnMut(Move) -> K>(&mut self, score: F) {
+        let mut scores: ArrayVec<K, MAX_MOVES> = self.moves.iter().copied().map(score).collect();
+
+        for i in 1..self.moves.len() {
+            let (mv, mv_score) = (self.moves[i], scores[i]);
+            let mut j = i;
+            while j > 0 && scores[j - 1] < mv_score {
+                self.moves[j] = self.moves[j - 1];
+                scores[j] = scores[j - 1];
+                j -= 1;
+            }
+            self.moves[j] = mv;
+            scores[j] = mv_score;
+        }
+    }
 }
 
 impl Default for MoveList {
@@ -226,32 +249,30 @@ pub fn generate_pawn_moves(
     color: Color,
     en_passant: Option<Square>,
 ) {
-    let (direction, start_rank, promotion_rank) = match color {
-        Color::White => (8, 1, 6),  // White moves up (increasing rank)
-        Color::Black => (-8, 6, 1), // Black moves down (decreasing rank)
+    let (start_rank, promotion_rank) = match color {
+        Color::White => (1, 6), // White moves up (increasing rank)
+        Color::Black => (6, 1), // Black moves down (decreasing rank)
     };
 
     for pawn_sq in pawns.iter() {
         let pawn_rank = pawn_sq.rank();
 
-        // Single push
-        let target_sq = Square((pawn_sq.0 as i32 + direction) as u8);
-        if !occupied.is_occupied(target_sq) {
-            if pawn_rank == promotion_rank {
-                // Promotion moves
-                moves.push(Move::promotion(pawn_sq, target_sq, Piece::Queen));
-                moves.push(Move::promotion(pawn_sq, target_sq, Piece::Rook));
-                moves.push(Move::promotion(pawn_sq, target_sq, Piece::Bishop));
-                moves.push(Move::promotion(pawn_sq, target_sq, Piece::Knight));
-            } else {
-                moves.push(Move::new(pawn_sq, target_sq));
-            }
+        // Single push (none for a pawn on the last rank, e.g. from a bad FEN)
+        if let Some(target_sq) = pawn_push_square(pawn_sq, color) {
+            if !occupied.is_occupied(target_sq) {
+                if pawn_rank == promotion_rank {
+                    push_promotions(moves, pawn_sq, target_sq);
+                } else {
+                    moves.push(Move::new(pawn_sq, target_sq));
+                }
 
-            // Double push from starting position
-            if pawn_rank == start_rank {
-                let double_target = Square((pawn_sq.0 as i32 + 2 * direction) as u8);
-                if !occupied.is_occupied(double_target) {
-                    moves.push(Move::new(pawn_sq, double_target));
+                // Double push from starting position
+                if pawn_rank == start_rank {
+                    if let Some(double_target) = pawn_push_square(target_sq, color) {
+                        if !occupied.is_occupied(double_target) {
+                            moves.push(Move::new(pawn_sq, double_target));
+                        }
+                    }
                 }
             }
         }
@@ -424,6 +445,272 @@ pub fn generate_castling_moves(
     }
 }
 
+/// Occupied squares and enemy pieces from the point of view of `color`
+fn occupancy(position: &Position, color: Color) -> (Bitboard, Bitboard) {
+    (position.all_occupancy(), position.occupancy(color.opposite()))
+}
+
+/// The square one step ahead of a pawn, if it is on the board
+#[inline(always)]
+fn pawn_push_square(sq: Square, color: Color) -> Option<Square> {
+    match color {
+        Color::White if sq.rank() < 7 => Some(Square(sq.0 + 8)),
+        Color::Black if sq.rank() > 0 => Some(Square(sq.0 - 8)),
+        _ => None,
+    }
+}
+
+/// Add all four promotions of a pawn move
+#[inline(always)]
+fn push_promotions(moves: &mut MoveList, from: Square, to: Square) {
+    moves.push(Move::promotion(from, to, Piece::Queen));
+    moves.push(Move::promotion(from, to, Piece::Rook));
+    moves.push(Move::promotion(from, to, Piece::Bishop));
+    moves.push(Move::promotion(from, to, Piece::Knight));
+}
+
+/// Generate pseudo-legal captures and promotions for one side
+///
+/// En passant captures are included even though their target square is empty.
+pub fn generate_captures(moves: &mut MoveList, position: &Position, color: Color) {
+    let (occupied, enemies) = occupancy(position, color);
+    let promotion_rank = if color == Color::White { 6 } else { 1 };
+
+    for pawn_sq in position.piece_bb(Piece::Pawn, color).iter() {
+        let promoting = pawn_sq.rank() == promotion_rank;
+
+        for capture_sq in (pawn_attacks(pawn_sq, color) & enemies).iter() {
+            if promoting {
+                push_promotions(moves, pawn_sq, capture_sq);
+            } else {
+                moves.push(Move::new(pawn_sq, capture_sq));
+            }
+        }
+
+        // Quiet promotions are tactical enough to go with the captures
+        if promoting {
+            if let Some(push_sq) = pawn_push_square(pawn_sq, color) {
+                if !occupied.is_occupied(push_sq) {
+                    push_promotions(moves, pawn_sq, push_sq);
+                }
+            }
+        }
+
+        if let Some(ep_sq) = position.en_passant {
+            if pawn_attacks(pawn_sq, color).is_occupied(ep_sq) {
+                moves.push(Move::en_passant(pawn_sq, ep_sq));
+            }
+        }
+    }
+
+    for sq in position.piece_bb(Piece::Knight, color).iter() {
+        for target in (knight_attacks(sq) & enemies).iter() {
+            moves.push(Move::new(sq, target));
+        }
+    }
+    for sq in position.piece_bb(Piece::Bishop, color).iter() {
+        for target in (bishop_attacks(sq, occupied) & enemies).iter() {
+            moves.push(Move::new(sq, target));
+        }
+    }
+    for sq in position.piece_bb(Piece::Rook, color).iter() {
+        for target in (rook_attacks(sq, occupied) & enemies).iter() {
+            moves.push(Move::new(sq, target));
+        }
+    }
+    for sq in position.piece_bb(Piece::Queen, color).iter() {
+        for target in (queen_attacks(sq, occupied) & enemies).iter() {
+            moves.push(Move::new(sq, target));
+        }
+    }
+    for sq in position.piece_bb(Piece::King, color).iter() {
+        for target in (king_attacks(sq) & enemies).iter() {
+            moves.push(Move::new(sq, target));
+        }
+    }
+}
+
+/// Generate pseudo-legal quiet moves (no captures or promotions) for one side
+pub fn generate_quiets(moves: &mut MoveList, position: &Position, color: Color) {
+    let (occupied, _) = occupancy(position, color);
+    let (start_rank, promotion_rank) = match color {
+        Color::White => (1, 6),
+        Color::Black => (6, 1),
+    };
+
+    for pawn_sq in position.piece_bb(Piece::Pawn, color).iter() {
+        if pawn_sq.rank() == promotion_rank {
+            continue;
+        }
+        if let Some(push_sq) = pawn_push_square(pawn_sq, color) {
+            if !occupied.is_occupied(push_sq) {
+                moves.push(Move::new(pawn_sq, push_sq));
+
+                if pawn_sq.rank() == start_rank {
+                    if let Some(double_sq) = pawn_push_square(push_sq, color) {
+                        if !occupied.is_occupied(double_sq) {
+                            moves.push(Move::new(pawn_sq, double_sq));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for sq in position.piece_bb(Piece::Knight, color).iter() {
+        for target in (knight_attacks(sq) & !occupied).iter() {
+            moves.push(Move::new(sq, target));
+        }
+    }
+    for sq in position.piece_bb(Piece::Bishop, color).iter() {
+        for target in (bishop_attacks(sq, occupied) & !occupied).iter() {
+            moves.push(Move::new(sq, target));
+        }
+    }
+    for sq in position.piece_bb(Piece::Rook, color).iter() {
+        for target in (rook_attacks(sq, occupied) & !occupied).iter() {
+            moves.push(Move::new(sq, target));
+        }
+    }
+    for sq in position.piece_bb(Piece::Queen, color).iter() {
+        for target in (queen_attacks(sq, occupied) & !occupied).iter() {
+            moves.push(Move::new(sq, target));
+        }
+    }
+    if let Some(king_sq) = position.piece_bb(Piece::King, color).lsb() {
+        for target in (king_attacks(king_sq) & !occupied).iter() {
+            moves.push(Move::new(king_sq, target));
+        }
+        generate_castling_moves(moves, king_sq, position.castling_rights, occupied, color);
+    }
+}
+
+/// Generate check evasions for `color`, the side to move, which must be in check
+///
+/// King moves go only to squares the enemy doesn't attack once the king has
+/// left its square. Against a single checker the other pieces may also
+/// capture it or, for a slider, block on the squares between it and the
+/// king. Moves of pinned pieces still need the usual legality check.
+pub fn generate_evasions(moves: &mut MoveList, position: &Position, color: Color) {
+    let Some(king_sq) = position.piece_bb(Piece::King, color).lsb() else {
+        return;
+    };
+    let (occupied, _) = occupancy(position, color);
+    let own = position.occupancy(color);
+    let enemy = color.opposite();
+
+    // Sliders see through the king's square, so it is left out of the
+    // occupancy when looking for safe squares
+    let without_king = occupied & !king_sq.to_bitboard();
+    let mut attacked = Bitboard::EMPTY;
+    for piece in 0..6 {
+        let piece = Piece::from_u8(piece).unwrap();
+        for sq in position.piece_bb(piece, enemy).iter() {
+            attacked |= piece_attacks(piece, sq, enemy, without_king);
+        }
+    }
+    for target in (king_attacks(king_sq) & !own & !attacked).iter() {
+        moves.push(Move::new(king_sq, target));
+    }
+
+    // In double check only the king can move
+    let checkers = position.checkers();
+    if checkers.count() != 1 {
+        return;
+    }
+    let checker = checkers.lsb().unwrap();
+    let blocks = ray_between(king_sq, checker);
+    let targets = blocks | checkers;
+
+    let (start_rank, promotion_rank) = match color {
+        Color::White => (1, 6),
+        Color::Black => (6, 1),
+    };
+    for pawn_sq in position.piece_bb(Piece::Pawn, color).iter() {
+        let mut pawn_targets = pawn_attacks(pawn_sq, color) & checkers;
+        if let Some(push_sq) = pawn_push_square(pawn_sq, color) {
+            if !occupied.is_occupied(push_sq) {
+                pawn_targets |= blocks & push_sq.to_bitboard();
+                if pawn_sq.rank() == start_rank {
+                    if let Some(double_sq) = pawn_push_square(push_sq, color) {
+                        if !occupied.is_occupied(double_sq) {
+                            pawn_targets |= blocks & double_sq.to_bitboard();
+                        }
+                    }
+                }
+            }
+        }
+        for target in pawn_targets.iter() {
+            if pawn_sq.rank() == promotion_rank {
+                push_promotions(moves, pawn_sq, target);
+            } else {
+                moves.push(Move::new(pawn_sq, target));
+            }
+        }
+
+        // En passant removes a checking pawn, or lands on a blocking square
+        if let Some(ep_sq) = position.en_passant {
+            let victim = Square::new(ep_sq.file(), pawn_sq.rank());
+            if pawn_attacks(pawn_sq, color).is_occupied(ep_sq)
+                && (victim == checker || blocks.is_occupied(ep_sq))
+            {
+                moves.push(Move::en_passant(pawn_sq, ep_sq));
+            }
+        }
+    }
+
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for sq in position.piece_bb(piece, color).iter() {
+            for target in (piece_attacks(piece, sq, color, occupied) & targets).iter() {
+                moves.push(Move::new(sq, target));
+            }
+        }
+    }
+}
+
+/// Generate all pseudo-legal moves for `color`, the side to move
+///
+/// In check only the evasions from `generate_evasions` are generated,
+/// otherwise the captures followed by the quiet moves.
+pub fn generate_moves(moves: &mut MoveList, position: &Position, color: Color) {
+    if position.checkers().is_empty() {
+        generate_captures(moves, position, color);
+        generate_quiets(moves, position, color);
+    } else {
+        generate_evasions(moves, position, color);
+    }
+}
+
+/// Check whether a move (e.g. from the transposition table or a killer slot)
+/// is pseudo-legal for `color` in this position
+pub fn is_pseudo_legal(position: &Position, color: Color, mv: Move) -> bool {
+    let from = mv.from();
+    let piece = match (0..6).find(|&p| position.pieces[p][color as usize].is_occupied(from)) {
+        Some(p) => Piece::from_u8(p as u8).unwrap(),
+        None => return false,
+    };
+
+    // Generate only the moves of the piece on the from-square
+    let (occupied, enemies) = occupancy(position, color);
+    let from_bb = from.to_bitboard();
+    let mut moves = MoveList::new();
+    match piece {
+        Piece::Pawn => {
+            generate_pawn_moves(&mut moves, from_bb, occupied, enemies, color, position.en_passant)
+        }
+        Piece::Knight => generate_knight_moves(&mut moves, from_bb, occupied, enemies),
+        Piece::Bishop => generate_bishop_moves(&mut moves, from_bb, occupied, enemies),
+        Piece::Rook => generate_rook_moves(&mut moves, from_bb, occupied, enemies),
+        Piece::Queen => generate_queen_moves(&mut moves, from_bb, occupied, enemies),
+        Piece::King => {
+            generate_king_moves(&mut moves, from, occupied, enemies);
+            generate_castling_moves(&mut moves, from, position.castling_rights, occupied, color);
+        }
+    }
+
+    moves.iter().any(|&m| m == mv)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -457,6 +744,85 @@ mod tests {
         assert_eq!(mv.move_type(), MoveType::Castling);
     }
 
+    #[test]
+    fn test_edge_pawn_pushes_do_not_wrap() {
+        crate::bitboard::magic::init_magics();
+        let mut moves = MoveList::new();
+        generate_pawn_moves(
+            &mut moves,
+            Square::A1.to_bitboard(),
+            Square::A1.to_bitboard(),
+            Bitboard::EMPTY,
+            Color::Black,
+            None,
+        );
+        assert!(moves.is_empty());
+
+        generate_pawn_moves(
+            &mut moves,
+            Square::H8.to_bitboard(),
+            Square::H8.to_bitboard(),
+            Bitboard::EMPTY,
+            Color::White,
+            None,
+        );
+        assert!(moves.is_empty());
+    }
+
+    #[test]
+    fn test_promotion_captures_emit_all_four_pieces() {
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        // b7 promotes by push and by capture on a8/c8; d6 captures onto
+        // rank 7 and must not promote; black's g2 mirrors b7
+        pos.set_fen("r1n1k3/1P2p1p1/3P4/8/8/8/6p1/4KN1R w - - 0 1").unwrap();
+
+        for color in [Color::White, Color::Black] {
+            let (occupied, enemies) = occupancy(&pos, color);
+            let mut moves = MoveList::new();
+            let pawns = pos.piece_bb(Piece::Pawn, color);
+            generate_pawn_moves(&mut moves, pawns, occupied, enemies, color, None);
+
+            let promotion_rank = if color == Color::White { 6 } else { 1 };
+            for mv in moves.iter() {
+                let promoting = mv.from().rank() == promotion_rank;
+                assert_eq!(mv.move_type() == MoveType::Promotion, promoting, "{}", mv);
+            }
+
+            let promoting_pawn = if color == Color::White { Square::B7 } else { Square::G2 };
+            let targets: Vec<Square> = moves
+                .iter()
+                .filter(|mv| mv.from() == promoting_pawn)
+                .map(|mv| mv.to())
+                .collect();
+            // Push plus two captures, four pieces each
+            assert_eq!(targets.len(), 12);
+            for target in &targets {
+                let pieces: Vec<Piece> = moves
+                    .iter()
+                    .filter(|mv| mv.from() == promoting_pawn && mv.to() == *target)
+                    .map(|mv| mv.promotion_piece())
+                    .collect();
+                assert_eq!(pieces, vec![Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_captures_include_en_passant() {
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        // d7-d5 leaves d6 empty yet capturable by the pawns on c5 and e5
+        pos.set_fen("4k3/8/8/2PpP3/8/8/8/4K3 w - d6 0 2").unwrap();
+
+        let mut moves = MoveList::new();
+        generate_captures(&mut moves, &pos, Color::White);
+        for from in [Square::C5, Square::E5] {
+            assert!(moves.iter().any(|&mv| mv == Move::en_passant(from, Square::D6)));
+        }
+        assert_eq!(moves.len(), 2);
+    }
+
     #[test]
     fn test_move_list() {
         let mut list = MoveList::new();
@@ -469,4 +835,108 @@ mod tests {
 
         assert_eq!(list[0].from(), Square::E2);
     }
+
+    #[test]
+    fn test_insertion_sort_matches_stable_vec_sort() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(1867);
+        for len in [0, 1, 2, 17, 60, MAX_MOVES] {
+            let mut list = MoveList::new();
+            for i in 0..len {
+                list.push(Move(i as u16));
+            }
+            // Few distinct scores, so ties are common
+            let scores: Vec<i32> = (0..len).map(|_| rng.gen_range(-3..4)).collect();
+            let score = |mv: Move| scores[mv.0 as usize];
+
+            // The order `order_moves` used to produce
+            let mut expected: Vec<(Move, i32)> = list.iter().map(|&mv| (mv, score(mv))).collect();
+            expected.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+
+            list.insertion_sort_by_score(score);
+            let sorted: Vec<Move> = list.iter().copied().collect();
+            let expected: Vec<Move> = expected.into_iter().map(|(mv, _)| mv).collect();
+            assert_eq!(sorted, expected, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn test_evasions_are_the_legal_moves() {
+        use crate::movegen::legal::is_legal_move;
+
+        crate::bitboard::magic::init_magics();
+        let legal_sorted = |pos: &Position, moves: &MoveList| {
+            let mut legal: Vec<u16> = moves
+                .iter()
+                .filter(|&&mv| is_legal_move(mv, pos, pos.side_to_move))
+                .map(|mv| mv.0)
+                .collect();
+            legal.sort_unstable();
+            legal
+        };
+
+        for fen in [
+            // Rook check: block, capture or step aside
+            "4k3/8/8/8/8/2N5/8/r3K2R w K - 0 1",
+            // Knight check with a pinned defender
+            "4k3/8/8/8/4r3/3n4/4B3/4K3 w - - 0 1",
+            // Double check
+            "4k3/8/3N4/8/8/8/8/4RK2 b - - 0 1",
+            // A double-pushed pawn giving check, captured en passant
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+            // Promotion blocking a back-rank check
+            "r3K3/1P6/8/8/8/8/8/4k3 w - - 0 1",
+        ] {
+            let mut pos = Position::empty();
+            pos.set_fen(fen).unwrap();
+            let color = pos.side_to_move;
+            assert!(!pos.checkers().is_empty(), "{} is not check", fen);
+
+            let mut all = MoveList::new();
+            generate_captures(&mut all, &pos, color);
+            generate_quiets(&mut all, &pos, color);
+            let mut evasions = MoveList::new();
+            generate_evasions(&mut evasions, &pos, color);
+            assert_eq!(legal_sorted(&pos, &evasions), legal_sorted(&pos, &all), "{}", fen);
+
+            let mut routed = MoveList::new();
+            generate_moves(&mut routed, &pos, color);
+            assert_eq!(routed.len(), evasions.len());
+        }
+
+        // Every check met in random games from a tactical position
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(1884);
+        let mut checks = 0;
+        for _ in 0..100 {
+            let mut pos = Position::empty();
+            pos.set_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+            for _ in 0..80 {
+                let color = pos.side_to_move;
+                let mut all = MoveList::new();
+                generate_captures(&mut all, &pos, color);
+                generate_quiets(&mut all, &pos, color);
+                let legal = legal_sorted(&pos, &all);
+                if !pos.checkers().is_empty() {
+                    checks += 1;
+                    let mut evasions = MoveList::new();
+                    generate_evasions(&mut evasions, &pos, color);
+                    assert_eq!(legal_sorted(&pos, &evasions), legal, "{}", pos.to_fen());
+                }
+                if legal.is_empty() {
+                    break;
+                }
+                pos.make_move(Move(legal[rng.gen_range(0..legal.len())]));
+            }
+        }
+        assert!(checks > 100);
+
+        // Without pins every evasion is legal as generated
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/8/8/8/2N5/8/r3K2R w K - 0 1").unwrap();
+        let mut evasions = MoveList::new();
+        generate_evasions(&mut evasions, &pos, Color::White);
+        assert_eq!(evasions.len(), legal_sorted(&pos, &evasions).len());
+    }
 }
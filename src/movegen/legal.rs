@@ -7,55 +7,6 @@ use crate::bitboard::attacks::*;
 use crate::bitboard::{Bitboard, Color, Piece, Square, CastleRights};
 use super::generator::{Move, MoveList};
 
-/// Compute attacks by enemy pieces
-fn compute_enemy_attacks(position: &crate::bitboard::position::Position, enemy_color: Color) -> Bitboard {
-    use crate::bitboard::{attacks, Piece};
-    let occupied = (0..6).fold(Bitboard::EMPTY, |acc, p| {
-        acc | position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::White)
-            | position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::Black)
-    });
-
-    let mut enemy_attacks = Bitboard::EMPTY;
-
-    // Pawn attacks
-    let enemy_pawns = position.piece_bb(Piece::Pawn, enemy_color);
-    for sq in enemy_pawns.iter() {
-        enemy_attacks |= attacks::pawn_attacks(sq, enemy_color);
-    }
-
-    // Knight attacks
-    let enemy_knights = position.piece_bb(Piece::Knight, enemy_color);
-    for sq in enemy_knights.iter() {
-        enemy_attacks |= attacks::knight_attacks(sq);
-    }
-
-    // Bishop attacks
-    let enemy_bishops = position.piece_bb(Piece::Bishop, enemy_color);
-    for sq in enemy_bishops.iter() {
-        enemy_attacks |= attacks::bishop_attacks(sq, occupied);
-    }
-
-    // Rook attacks
-    let enemy_rooks = position.piece_bb(Piece::Rook, enemy_color);
-    for sq in enemy_rooks.iter() {
-        enemy_attacks |= attacks::rook_attacks(sq, occupied);
-    }
-
-    // Queen attacks
-    let enemy_queens = position.piece_bb(Piece::Queen, enemy_color);
-    for sq in enemy_queens.iter() {
-        enemy_attacks |= attacks::queen_attacks(sq, occupied);
-    }
-
-    // King attacks
-    let enemy_king = position.piece_bb(Piece::King, enemy_color);
-    for sq in enemy_king.iter() {
-        enemy_attacks |= attacks::king_attacks(sq);
-    }
-
-    enemy_attacks
-}
-
 /// Check if a move is legal in the current position
 ///
 /// This function assumes the move is pseudo-legal and checks if it leaves
@@ -89,7 +40,7 @@ fn is_legal_castling(
     let from = mv.from();
     let to = mv.to();
     let king_square = position.piece_bb(Piece::King, color).lsb().unwrap();
-    let enemy_attacks = compute_enemy_attacks(position, color.opposite());
+    let enemy_attacks = position.attacked_by(color.opposite());
 
     // King must not be in check
     if enemy_attacks.is_occupied(king_square) {
@@ -129,7 +80,7 @@ fn simulate_move_and_check_king(
     let undo = new_position.make_move(mv);
 
     // Compute enemy attacks in the new position
-    let enemy_attacks = compute_enemy_attacks(&new_position, color.opposite());
+    let enemy_attacks = new_position.attacked_by(color.opposite());
 
     // Get our king square in the new position
     let king_square = new_position.piece_bb(Piece::King, color).lsb().unwrap();
@@ -143,6 +94,68 @@ fn simulate_move_and_check_king(
     king_safe
 }
 
+/// Check whether a move gives check, without making it
+///
+/// Covers direct checks by the moved (or promoted) piece from its
+/// destination, the rook of a castling move, and discovered checks by
+/// sliders whose line to the enemy king the move opens (including the
+/// captured pawn's square for en passant).
+pub fn gives_check(position: &crate::bitboard::position::Position, mv: Move) -> bool {
+    use super::generator::MoveType;
+
+    let color = position.side_to_move;
+    let Some(king_sq) = position.piece_bb(Piece::King, color.opposite()).lsb() else {
+        return false;
+    };
+    let (from, to) = (mv.from(), mv.to());
+    let Some(piece) = (0..6)
+        .filter_map(Piece::from_u8)
+        .find(|&p| position.piece_bb(p, color).is_occupied(from))
+    else {
+        return false;
+    };
+
+    // Occupancy after the move
+    let mut occupied = position.all_occupancy();
+    occupied.clear(from);
+    occupied.set(to);
+
+    // Direct check by the piece standing on `to` after the move
+    let direct = match mv.move_type() {
+        MoveType::Promotion => piece_attacks(mv.promotion_piece(), to, color, occupied),
+        MoveType::Castling => {
+            let (rook_from, rook_to) = match to {
+                Square::G1 => (Square::H1, Square::F1),
+                Square::C1 => (Square::A1, Square::D1),
+                Square::G8 => (Square::H8, Square::F8),
+                _ => (Square::A8, Square::D8),
+            };
+            occupied.clear(rook_from);
+            occupied.set(rook_to);
+            rook_attacks(rook_to, occupied)
+        }
+        MoveType::EnPassant => {
+            let captured = if color == Color::White { to.0 - 8 } else { to.0 + 8 };
+            occupied.clear(Square(captured));
+            piece_attacks(piece, to, color, occupied)
+        }
+        MoveType::Normal => piece_attacks(piece, to, color, occupied),
+    };
+    if direct.is_occupied(king_sq) {
+        return true;
+    }
+
+    // Discovered check: our sliders that now see the king. The moved piece
+    // is excluded, its new square was handled above.
+    let mut from_mask = Bitboard::EMPTY;
+    from_mask.set(from);
+    let queens = position.piece_bb(Piece::Queen, color);
+    let diagonal = (position.piece_bb(Piece::Bishop, color) | queens) & !from_mask;
+    let orthogonal = (position.piece_bb(Piece::Rook, color) | queens) & !from_mask;
+    !(bishop_attacks(king_sq, occupied) & diagonal).is_empty()
+        || !(rook_attacks(king_sq, occupied) & orthogonal).is_empty()
+}
+
 /// Check if the current position is in check
 pub fn is_in_check(
     king_square: Square,
@@ -221,4 +234,26 @@ mod tests {
         assert!(is_checkmate(king_sq, enemy_attacks, &empty_moves));
         assert!(!is_stalemate(king_sq, enemy_attacks, &empty_moves));
     }
+
+    #[test]
+    fn test_gives_check() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+
+        // Direct rook check along the eighth rank
+        pos.set_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(gives_check(&pos, Move::new(Square::A1, Square::A8)));
+        // Not a check: the rook stays off the king's lines
+        assert!(!gives_check(&pos, Move::new(Square::A1, Square::B1)));
+
+        // Discovered check: the knight uncovers the bishop's diagonal
+        pos.set_fen("7k/8/8/8/3N4/8/1B6/4K3 w - - 0 1").unwrap();
+        assert!(gives_check(&pos, Move::new(Square::D4, Square::F3)));
+
+        // Castling checks with the rook
+        pos.set_fen("5k2/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(gives_check(&pos, Move::castling(Square::E1, Square::G1)));
+    }
 }
@@ -4,10 +4,12 @@
 //! - Pseudo-legal move generation
 //! - Legal move validation
 //! - Move ordering for search efficiency
+//! - Staged move picking for the search
 
 pub mod generator;
 pub mod legal;
 pub mod ordering;
+pub mod picker;
 
 pub use self::prelude::*;
 
@@ -15,6 +17,7 @@ pub mod prelude {
     pub use super::generator::*;
     pub use super::legal::*;
     pub use super::ordering::*;
+    pub use super::picker::*;
 }
 
 pub mod lib {
@@ -5,6 +5,7 @@
 
 use super::generator::{Move, MoveList, MoveType};
 use crate::bitboard::{Bitboard, Piece, Square};
+use std::cmp::Reverse;
 
 /// Move ordering scores for different move types
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -18,17 +19,50 @@ pub enum MoveScore {
     /// Killer moves (moves that caused cutoffs)
     Killer1 = 5000,
     Killer2 = 4000,
+    /// Quiet reply that last refuted the opponent's previous move
+    Countermove = 3000,
     /// Bad captures (losing material)
     BadCapture = 2000,
     /// Quiet moves with history heuristic
     Quiet = 0,
 }
 
+/// Ordering bonus for a promotion that also captures, enough to put any
+/// capturing promotion ahead of every quiet one
+pub const CAPTURE_PROMOTION_BONUS: i32 = 1000;
+
+/// Ordering score of a knight under-promotion. It ranks above the bishop:
+/// a knight reaches squares a queen can't (forks, checks, stalemate escapes)
+pub const KNIGHT_PROMOTION_SCORE: i32 = 400;
+
+/// Ordering score of promoting to `piece`
+pub fn promotion_score(piece: Piece) -> i32 {
+    match piece {
+        Piece::Queen => 900,
+        Piece::Rook => 500,
+        Piece::Knight => KNIGHT_PROMOTION_SCORE,
+        Piece::Bishop => 300,
+        _ => 0,
+    }
+}
+
+/// Key a move is ordered by, highest first: its `score`, then between equal
+/// scores the lower from-square, the lower to-square and the lower remaining
+/// move bits (type and promotion piece)
+///
+/// Distinct moves never tie, so the order depends only on the scores and not
+/// on generation order, and node counts are reproducible.
+pub fn ordering_key(mv: Move, score: i32) -> (i32, Reverse<u8>, Reverse<u8>, Reverse<u16>) {
+    (score, Reverse(mv.from().0), Reverse(mv.to().0), Reverse(mv.0 >> 12))
+}
+
 /// Assign a score to a move for ordering purposes
 pub fn score_move(
     mv: Move,
+    occupied: Bitboard,
     hash_move: Option<Move>,
     killer_moves: &[Move; 2],
+    countermove: Option<Move>,
     history_table: &[[i32; 64]; 64], // [from][to] history scores
     see_table: &mut SEE, // Static exchange evaluation
 ) -> i32 {
@@ -39,15 +73,12 @@ pub fn score_move(
 
     match mv.move_type() {
         MoveType::Promotion => {
-            // Score promotions based on piece value
-            let promo_score = match mv.promotion_piece() {
-                Piece::Queen => 900,
-                Piece::Rook => 500,
-                Piece::Bishop => 300,
-                Piece::Knight => 300,
-                _ => 0,
+            let capture_bonus = if mv.is_capture(occupied) {
+                CAPTURE_PROMOTION_BONUS
+            } else {
+                0
             };
-            MoveScore::Promotion as i32 + promo_score
+            MoveScore::Promotion as i32 + promotion_score(mv.promotion_piece()) + capture_bonus
         }
         MoveType::EnPassant => MoveScore::GoodCapture as i32 + 100, // En passant is usually good
         MoveType::Castling => MoveScore::Quiet as i32 + 50, // Castling is generally good
@@ -61,10 +92,12 @@ pub fn score_move(
             }
 
             // For captures, use MVV-LVA or SEE
-            if mv.is_capture(Bitboard::ALL) { // This would need proper occupied board
+            if mv.is_capture(occupied) {
                 // Placeholder: assume good capture for now
                 // In real implementation, use SEE to determine if capture is winning/losing
                 MoveScore::GoodCapture as i32
+            } else if Some(mv) == countermove {
+                MoveScore::Countermove as i32
             } else {
                 // Quiet move: use history heuristic
                 let from_idx = mv.from().0 as usize;
@@ -75,30 +108,29 @@ pub fn score_move(
     }
 }
 
-/// Order a list of moves using the given scoring function
+/// Order a list of moves using the given scoring function, in the total
+/// order of `ordering_key`
 pub fn order_moves(
     moves: &mut MoveList,
+    occupied: Bitboard,
     hash_move: Option<Move>,
     killer_moves: &[Move; 2],
+    countermove: Option<Move>,
     history_table: &[[i32; 64]; 64],
     see_table: &mut SEE,
 ) {
-    // Create a vector of (move, score) pairs
-    let mut scored_moves: Vec<(Move, i32)> = moves
-        .iter()
-        .map(|&mv| {
-            let score = score_move(mv, hash_move, killer_moves, history_table, see_table);
-            (mv, score)
-        })
-        .collect();
-
-    // Sort by score in descending order (highest scores first)
-    scored_moves.sort_by(|a, b| b.1.cmp(&a.1));
-
-    // Update the move list with sorted moves
-    for (i, (mv, _)) in scored_moves.into_iter().enumerate() {
-        moves[i] = mv;
-    }
+    moves.insertion_sort_by_score(|mv| {
+        let score = score_move(
+            mv,
+            occupied,
+            hash_move,
+            killer_moves,
+            countermove,
+            history_table,
+            see_table,
+        );
+        ordering_key(mv, score)
+    });
 }
 
 /// Static Exchange Evaluation (SEE) for captures
@@ -141,13 +173,109 @@ pub fn age_history(history_table: &mut [[i32; 64]; 64]) {
 }
 
 /// Update killer moves
+///
+/// Callers are expected to pass only quiet moves; captures are ordered by
+/// their own scores and would just push real killers out.
 pub fn update_killers(killer_moves: &mut [Move; 2], mv: Move) {
-    if mv.move_type() == MoveType::Normal && !mv.is_capture(Bitboard::ALL) {
-        if mv != killer_moves[0] {
-            killer_moves[1] = killer_moves[0];
-            killer_moves[0] = mv;
+    if mv.move_type() == MoveType::Normal && mv != killer_moves[0] {
+        killer_moves[1] = killer_moves[0];
+        killer_moves[0] = mv;
+    }
+}
+
+/// Countermove table - the quiet move that most recently caused a cutoff
+/// in reply to a given opponent move, indexed by that move's [from][to]
+#[derive(Clone)]
+pub struct CountermoveTable {
+    table: [[Option<Move>; 64]; 64],
+}
+
+impl CountermoveTable {
+    /// Create an empty countermove table
+    pub fn new() -> Self {
+        CountermoveTable {
+            table: [[None; 64]; 64],
         }
     }
+
+    /// Get the stored countermove for the opponent's previous move
+    #[inline(always)]
+    pub fn get(&self, prev_move: Move) -> Option<Move> {
+        self.table[prev_move.from().0 as usize][prev_move.to().0 as usize]
+    }
+
+    /// Record `mv` as the refutation of `prev_move`
+    #[inline(always)]
+    pub fn update(&mut self, prev_move: Move, mv: Move) {
+        self.table[prev_move.from().0 as usize][prev_move.to().0 as usize] = Some(mv);
+    }
+
+    /// Forget all stored countermoves
+    pub fn clear(&mut self) {
+        self.table = [[None; 64]; 64];
+    }
+}
+
+impl Default for CountermoveTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Number of ply slots kept for killer moves
+pub const KILLER_SLOTS: usize = crate::search::MAX_PLY;
+
+/// Move ordering state carried through a search: killers, history and countermoves
+#[derive(Clone)]
+pub struct OrderingTables {
+    /// Two killer moves per distance from the root
+    pub killers: [[Move; 2]; KILLER_SLOTS],
+    /// History scores indexed by [from][to]
+    pub history: [[i32; 64]; 64],
+    /// Countermoves indexed by the previous move
+    pub countermoves: CountermoveTable,
+}
+
+impl OrderingTables {
+    /// Create empty ordering tables
+    pub fn new() -> Self {
+        OrderingTables {
+            killers: [[Move(0); 2]; KILLER_SLOTS],
+            history: [[0; 64]; 64],
+            countermoves: CountermoveTable::new(),
+        }
+    }
+
+    /// Killer slot for a given distance from the root
+    #[inline(always)]
+    pub fn killers_at(&self, ply: i32) -> &[Move; 2] {
+        &self.killers[(ply.max(0) as usize).min(KILLER_SLOTS - 1)]
+    }
+
+    /// Prepare for a new search: halve the history scores so recent
+    /// searches dominate, and drop the killers, which belong to the plies
+    /// of the previous search
+    pub fn new_search(&mut self) {
+        age_history(&mut self.history);
+        self.killers = [[Move(0); 2]; KILLER_SLOTS];
+    }
+
+    /// Record a quiet move that caused a beta cutoff `ply` moves from the
+    /// root with `depth` left to search
+    pub fn record_cutoff(&mut self, mv: Move, prev_move: Option<Move>, ply: i32, depth: i32) {
+        let slot = (ply.max(0) as usize).min(KILLER_SLOTS - 1);
+        update_killers(&mut self.killers[slot], mv);
+        update_history(&mut self.history, mv, depth);
+        if let Some(prev) = prev_move {
+            self.countermoves.update(prev, mv);
+        }
+    }
+}
+
+impl Default for OrderingTables {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
@@ -163,15 +291,123 @@ mod tests {
 
         // Test promotion scoring
         let promo_move = Move::promotion(Square::E7, Square::E8, Piece::Queen);
-        let score = score_move(promo_move, None, &killers, &history, &mut see);
+        let score = score_move(
+            promo_move,
+            Bitboard::EMPTY,
+            None,
+            &killers,
+            None,
+            &history,
+            &mut see,
+        );
         assert!(score >= MoveScore::Promotion as i32);
 
         // Test killer move scoring
         let killer_move = Move::new(Square::A1, Square::A2);
-        let score = score_move(killer_move, None, &killers, &history, &mut see);
+        let score = score_move(
+            killer_move,
+            Bitboard::EMPTY,
+            None,
+            &killers,
+            None,
+            &history,
+            &mut see,
+        );
         assert_eq!(score, MoveScore::Killer1 as i32);
     }
 
+    #[test]
+    fn test_capturing_promotion_outranks_quiet_promotion() {
+        let mut see = SEE::new();
+        let history = [[0i32; 64]; 64];
+        let killers = [Move(0); 2];
+        // Black queen on d8, so e7xd8 captures while e7-e8 doesn't
+        let mut occupied = Bitboard::EMPTY;
+        occupied.set(Square::D8);
+        let mut score = |mv| score_move(mv, occupied, None, &killers, None, &history, &mut see);
+
+        let capture_queen = score(Move::promotion(Square::E7, Square::D8, Piece::Queen));
+        let quiet_queen = score(Move::promotion(Square::E7, Square::E8, Piece::Queen));
+        let quiet_knight = score(Move::promotion(Square::E7, Square::E8, Piece::Knight));
+        let quiet_bishop = score(Move::promotion(Square::E7, Square::E8, Piece::Bishop));
+
+        assert!(capture_queen > quiet_queen);
+        assert!(score(Move::promotion(Square::E7, Square::D8, Piece::Knight)) > quiet_queen);
+        assert!(quiet_queen > quiet_knight && quiet_knight > quiet_bishop);
+    }
+
+    #[test]
+    fn test_countermove_ordered_before_quiets() {
+        let mut see = SEE::new();
+        let history = [[0i32; 64]; 64];
+        let killers = [Move(0); 2];
+
+        let prev_move = Move::new(Square::E7, Square::E5);
+        let reply = Move::new(Square::G1, Square::F3);
+        let mut countermoves = CountermoveTable::new();
+        assert_eq!(countermoves.get(prev_move), None);
+        countermoves.update(prev_move, reply);
+
+        let mut moves = MoveList::new();
+        moves.push(Move::new(Square::A2, Square::A3));
+        moves.push(Move::new(Square::B1, Square::C3));
+        moves.push(reply);
+        moves.push(Move::new(Square::H2, Square::H3));
+
+        order_moves(
+            &mut moves,
+            Bitboard::EMPTY,
+            None,
+            &killers,
+            countermoves.get(prev_move),
+            &history,
+            &mut see,
+        );
+        assert_eq!(moves[0], reply);
+    }
+
+    #[test]
+    fn test_ordering_is_independent_of_generation_order() {
+        use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+        let history = [[0i32; 64]; 64];
+        let killers = [Move(0); 2];
+        // Plenty of ties: quiet moves without history, and the four
+        // promotions sharing their squares
+        let mut generated = vec![
+            Move::new(Square::A2, Square::A3),
+            Move::new(Square::A2, Square::A4),
+            Move::new(Square::B1, Square::C3),
+            Move::new(Square::B1, Square::A3),
+            Move::new(Square::G1, Square::F3),
+            Move::new(Square::H2, Square::H3),
+        ];
+        for piece in [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
+            generated.push(Move::promotion(Square::E7, Square::E8, piece));
+            generated.push(Move::promotion(Square::E7, Square::D8, piece));
+        }
+        let occupied = Square::D8.to_bitboard();
+
+        let mut rng = StdRng::seed_from_u64(1892);
+        let mut orders = Vec::new();
+        for _ in 0..8 {
+            generated.shuffle(&mut rng);
+            let mut moves = MoveList::new();
+            generated.iter().for_each(|&mv| moves.push(mv));
+            order_moves(&mut moves, occupied, None, &killers, None, &history, &mut SEE::new());
+            orders.push(moves.iter().copied().collect::<Vec<Move>>());
+        }
+
+        assert!(orders.windows(2).all(|pair| pair[0] == pair[1]));
+        // Equal quiet scores fall back to the squares, b1 before a2
+        let quiets: Vec<Move> = orders[0].iter().copied().filter(|mv| !mv.is_promotion()).collect();
+        assert_eq!(quiets[..3], [
+            Move::new(Square::B1, Square::A3),
+            Move::new(Square::B1, Square::C3),
+            Move::new(Square::G1, Square::F3),
+        ]);
+    }
+
     #[test]
     fn test_history_update() {
         let mut history = [[0i32; 64]; 64];
@@ -183,4 +419,22 @@ mod tests {
         age_history(&mut history);
         assert!(history[Square::E2.0 as usize][Square::E4.0 as usize] >= 0);
     }
+
+    #[test]
+    fn test_new_search_ages_history_and_clears_killers() {
+        let mut tables = OrderingTables::new();
+        let mv = Move::new(Square::G1, Square::F3);
+        for _ in 0..10 {
+            tables.record_cutoff(mv, None, 3, 10);
+        }
+        let before = tables.history[Square::G1.0 as usize][Square::F3.0 as usize];
+        // Killers belong to the ply, whatever depth was left there
+        assert_eq!(tables.killers_at(3)[0], mv);
+        assert_eq!(tables.killers_at(10), &[Move(0); 2]);
+
+        tables.new_search();
+
+        assert_eq!(tables.history[Square::G1.0 as usize][Square::F3.0 as usize], before / 2);
+        assert_eq!(tables.killers_at(3), &[Move(0); 2]);
+    }
 }
@@ -0,0 +1,390 @@
+//! Staged move picker - Generate and order moves lazily during search
+//!
+//! Instead of generating and sorting every move up front, the picker hands
+//! out moves in stages: the hash move, then captures (MVV-LVA), then killer
+//! moves, then the remaining quiets. Each stage is only generated once the
+//! previous one is exhausted, so a cutoff on an early move skips the work
+//! for the later stages entirely.
+//!
+//! In check, the captures, killers and quiets are replaced by a single
+//! evasion stage: only the moves that can answer the check are generated,
+//! captures of the checker first.
+//!
+//! Quiescence search uses a captures-only picker that stops after the
+//! capture stage.
+//!
+//! The picker yields pseudo-legal moves; the caller checks legality.
+
+use super::generator::{
+    generate_captures, generate_evasions, generate_quiets, is_pseudo_legal, Move, MoveList, MoveType, MAX_MOVES,
+};
+use super::ordering::{ordering_key, promotion_score, score_move, MoveScore, SEE};
+use crate::bitboard::position::Position;
+use crate::bitboard::{Bitboard, Color, Piece, Square};
+use crate::eval::material::PIECE_VALUES;
+
+/// Stages of the move picker, in the order they are visited
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Stage {
+    HashMove,
+    GenerateCaptures,
+    Captures,
+    Killers,
+    GenerateQuiets,
+    Quiets,
+    GenerateEvasions,
+    Evasions,
+    Done,
+}
+
+/// Lazily generates and orders the moves of one search node
+pub struct MovePicker<'a> {
+    position: &'a Position,
+    color: Color,
+    occupied: Bitboard,
+    hash_move: Option<Move>,
+    killers: [Move; 2],
+    countermove: Option<Move>,
+    stage: Stage,
+    moves: MoveList,
+    scores: [i32; MAX_MOVES],
+    index: usize,
+    killer_index: usize,
+    yielded_killers: [Option<Move>; 2],
+    captures_only: bool,
+    in_check: bool,
+}
+
+impl<'a> MovePicker<'a> {
+    /// Create a picker for the moves of `color` in `position`; if `color`
+    /// is in check, only evasions are generated
+    pub fn new(
+        position: &'a Position,
+        color: Color,
+        hash_move: Option<Move>,
+        killers: [Move; 2],
+        countermove: Option<Move>,
+    ) -> Self {
+        let occupied = position.all_occupancy();
+
+        MovePicker {
+            position,
+            color,
+            occupied,
+            hash_move,
+            killers,
+            countermove,
+            stage: Stage::HashMove,
+            moves: MoveList::new(),
+            scores: [0; MAX_MOVES],
+            index: 0,
+            killer_index: 0,
+            yielded_killers: [None; 2],
+            captures_only: false,
+            in_check: color == position.side_to_move && !position.checkers().is_empty(),
+        }
+    }
+
+    /// Create a picker that only yields captures and promotions, best
+    /// first (for quiescence search)
+    pub fn new_captures(position: &'a Position, color: Color) -> Self {
+        MovePicker {
+            stage: Stage::GenerateCaptures,
+            captures_only: true,
+            ..Self::new(position, color, None, [Move(0); 2], None)
+        }
+    }
+
+    /// Next move to search, or `None` once every stage is exhausted
+    ///
+    /// The history table is passed per call (rather than borrowed for the
+    /// picker's lifetime) so the search can update it between moves.
+    pub fn next_move(&mut self, history: &[[i32; 64]; 64]) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = if self.in_check { Stage::GenerateEvasions } else { Stage::GenerateCaptures };
+                    if let Some(mv) = self.hash_move {
+                        if is_pseudo_legal(self.position, self.color, mv) {
+                            return Some(mv);
+                        }
+                        // Not playable here (e.g. a hash collision)
+                        self.hash_move = None;
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    self.moves.clear();
+                    generate_captures(&mut self.moves, self.position, self.color);
+                    for i in 0..self.moves.len() {
+                        self.scores[i] = capture_score(self.position, self.moves[i]);
+                    }
+                    self.index = 0;
+                    self.stage = Stage::Captures;
+                }
+                Stage::Captures => match self.pick_best() {
+                    Some(mv) if Some(mv) == self.hash_move => continue,
+                    Some(mv) => return Some(mv),
+                    None if self.captures_only => self.stage = Stage::Done,
+                    None => self.stage = Stage::Killers,
+                },
+                Stage::Killers => {
+                    if self.killer_index >= self.killers.len() {
+                        self.stage = Stage::GenerateQuiets;
+                        continue;
+                    }
+                    let mv = self.killers[self.killer_index];
+                    self.killer_index += 1;
+                    if Some(mv) != self.hash_move
+                        && !self.yielded_killers.contains(&Some(mv))
+                        && self.is_quiet(mv)
+                        && is_pseudo_legal(self.position, self.color, mv)
+                    {
+                        self.yielded_killers[self.killer_index - 1] = Some(mv);
+                        return Some(mv);
+                    }
+                }
+                Stage::GenerateQuiets => {
+                    self.moves.clear();
+                    generate_quiets(&mut self.moves, self.position, self.color);
+                    let mut see = SEE::new();
+                    for i in 0..self.moves.len() {
+                        self.scores[i] = score_move(
+                            self.moves[i],
+                            self.occupied,
+                            None,
+                            &self.killers,
+                            self.countermove,
+                            history,
+                            &mut see,
+                        );
+                    }
+                    self.index = 0;
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => match self.pick_best() {
+                    Some(mv)
+                        if Some(mv) == self.hash_move
+                            || self.yielded_killers.contains(&Some(mv)) =>
+                    {
+                        continue
+                    }
+                    Some(mv) => return Some(mv),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::GenerateEvasions => {
+                    self.moves.clear();
+                    generate_evasions(&mut self.moves, self.position, self.color);
+                    let mut see = SEE::new();
+                    for i in 0..self.moves.len() {
+                        let mv = self.moves[i];
+                        self.scores[i] = if self.is_quiet(mv) {
+                            score_move(mv, self.occupied, None, &self.killers, self.countermove, history, &mut see)
+                        } else {
+                            MoveScore::GoodCapture as i32 + capture_score(self.position, mv)
+                        };
+                    }
+                    self.index = 0;
+                    self.stage = Stage::Evasions;
+                }
+                Stage::Evasions => match self.pick_best() {
+                    Some(mv) if Some(mv) == self.hash_move => continue,
+                    Some(mv) => return Some(mv),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Swap the best-scoring remaining move of the current stage to the
+    /// front and return it (a selection sort that stops at the first cutoff)
+    ///
+    /// Ties are broken by `ordering_key`, so the swaps don't make the order
+    /// depend on generation order.
+    fn pick_best(&mut self) -> Option<Move> {
+        if self.index >= self.moves.len() {
+            return None;
+        }
+
+        let mut best = self.index;
+        for i in self.index + 1..self.moves.len() {
+            if ordering_key(self.moves[i], self.scores[i]) > ordering_key(self.moves[best], self.scores[best]) {
+                best = i;
+            }
+        }
+
+        let mv = self.moves[best];
+        self.moves[best] = self.moves[self.index];
+        self.scores[best] = self.scores[self.index];
+        self.index += 1;
+        Some(mv)
+    }
+
+    /// Killer candidates must be quiet in the current position
+    fn is_quiet(&self, mv: Move) -> bool {
+        mv.move_type() != MoveType::Promotion
+            && mv.move_type() != MoveType::EnPassant
+            && !mv.is_capture(self.occupied)
+    }
+}
+
+/// Type of the piece standing on a square, if any
+fn piece_on(position: &Position, sq: Square) -> Option<Piece> {
+    position.piece_at(sq).map(|(piece, _)| piece)
+}
+
+/// MVV-LVA score for a capture or promotion (promotions count with their
+/// ordering score, so capturing promotions come before quiet ones)
+fn capture_score(position: &Position, mv: Move) -> i32 {
+    let victim = match mv.move_type() {
+        MoveType::EnPassant => PIECE_VALUES[Piece::Pawn as usize],
+        _ => piece_on(position, mv.to()).map_or(0, |p| PIECE_VALUES[p as usize]),
+    };
+    let promotion = if mv.move_type() == MoveType::Promotion {
+        promotion_score(mv.promotion_piece())
+    } else {
+        0
+    };
+    let attacker = piece_on(position, mv.from()).map_or(0, |p| p as i32);
+
+    (victim + promotion) * 8 - attacker
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::generator::*;
+    use crate::movegen::ordering::order_moves;
+
+    /// Generate every pseudo-legal move (castling included) and order it
+    fn generate_all_ordered(position: &Position, color: Color) -> MoveList {
+        let occupied = position.all_occupancy();
+        let enemies = position.occupancy(color.opposite());
+
+        let mut moves = MoveList::new();
+        generate_pawn_moves(
+            &mut moves,
+            position.piece_bb(Piece::Pawn, color),
+            occupied,
+            enemies,
+            color,
+            position.en_passant,
+        );
+        generate_knight_moves(&mut moves, position.piece_bb(Piece::Knight, color), occupied, enemies);
+        generate_bishop_moves(&mut moves, position.piece_bb(Piece::Bishop, color), occupied, enemies);
+        generate_rook_moves(&mut moves, position.piece_bb(Piece::Rook, color), occupied, enemies);
+        generate_queen_moves(&mut moves, position.piece_bb(Piece::Queen, color), occupied, enemies);
+        let king_sq = position.piece_bb(Piece::King, color).lsb().unwrap();
+        generate_king_moves(&mut moves, king_sq, occupied, enemies);
+        generate_castling_moves(&mut moves, king_sq, position.castling_rights, occupied, color);
+
+        order_moves(
+            &mut moves,
+            occupied,
+            None,
+            &[Move(0); 2],
+            None,
+            &[[0; 64]; 64],
+            &mut SEE::new(),
+        );
+        moves
+    }
+
+    #[test]
+    fn test_picker_yields_same_moves_as_generate_all() {
+        crate::bitboard::magic::init_magics();
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            // Castling both ways, captures and en passant
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            // Quiet and capturing promotions
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ];
+
+        for fen in fens {
+            let mut pos = Position::empty();
+            pos.set_fen(fen).unwrap();
+            let color = pos.side_to_move;
+
+            // Feed a hash move and a killer so every stage is exercised
+            let expected = generate_all_ordered(&pos, color);
+            let hash_move = expected.get(expected.len() / 2).copied();
+            let killers = [*expected.iter().last().unwrap(), Move(0)];
+
+            let mut picker = MovePicker::new(&pos, color, hash_move, killers, None);
+            let mut picked = Vec::new();
+            while let Some(mv) = picker.next_move(&[[0; 64]; 64]) {
+                picked.push(mv.0);
+            }
+
+            let mut expected: Vec<u16> = expected.iter().map(|mv| mv.0).collect();
+            let picked_count = picked.len();
+            picked.sort_unstable();
+            picked.dedup();
+            expected.sort_unstable();
+            assert_eq!(picked.len(), picked_count, "duplicate move in {}", fen);
+            assert_eq!(picked, expected, "move sets differ in {}", fen);
+        }
+    }
+
+    #[test]
+    fn test_picker_stage_order() {
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/8/3p4/8/8/8/R3K3 w Q - 0 1").unwrap();
+
+        let hash_move = Move::new(Square::A1, Square::A7);
+        let killer = Move::new(Square::E1, Square::F2);
+        let mut picker = MovePicker::new(&pos, Color::White, Some(hash_move), [killer, Move(0)], None);
+        let history = [[0; 64]; 64];
+
+        assert_eq!(picker.next_move(&history), Some(hash_move));
+        // No captures in this position, so the killer follows
+        assert_eq!(picker.next_move(&history), Some(killer));
+        let rest: Vec<Move> = std::iter::from_fn(|| picker.next_move(&history)).collect();
+        assert!(rest.contains(&Move::castling(Square::E1, Square::C1)));
+        assert!(!rest.contains(&hash_move) && !rest.contains(&killer));
+    }
+
+    #[test]
+    fn test_captures_only_picker() {
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/2n1q3/3P4/8/8/8/4K3 w - - 0 1").unwrap();
+
+        let mut picker = MovePicker::new_captures(&pos, Color::White);
+        let history = [[0; 64]; 64];
+        let picked: Vec<Move> = std::iter::from_fn(|| picker.next_move(&history)).collect();
+
+        // Queen before knight, and no quiet moves
+        assert_eq!(
+            picked,
+            vec![Move::new(Square::D5, Square::E6), Move::new(Square::D5, Square::C6)]
+        );
+    }
+
+    #[test]
+    fn test_picker_in_check_yields_evasions_only() {
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        // Rook check on the e-file: the king steps aside, the bishop blocks
+        // or the knight takes the rook
+        pos.set_fen("6k1/8/6B1/4r3/8/3N4/8/4K3 w - - 0 1").unwrap();
+
+        let mut expected = MoveList::new();
+        generate_evasions(&mut expected, &pos, Color::White);
+        let hash_move = Move::new(Square::G6, Square::E4);
+        let mut picker = MovePicker::new(&pos, Color::White, Some(hash_move), [Move(0); 2], None);
+        let history = [[0; 64]; 64];
+        let picked: Vec<Move> = std::iter::from_fn(|| picker.next_move(&history)).collect();
+
+        // Hash move first, then the capture of the checker
+        assert_eq!(picked[..2], [hash_move, Move::new(Square::D3, Square::E5)][..]);
+        let mut picked: Vec<u16> = picked.iter().map(|mv| mv.0).collect();
+        let mut expected: Vec<u16> = expected.iter().map(|mv| mv.0).collect();
+        picked.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(picked, expected);
+    }
+}
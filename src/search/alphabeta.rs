@@ -3,15 +3,110 @@
 //! This module implements the alpha-beta pruning algorithm for chess search.
 
 use super::quiescence::quiescence_search;
-use crate::bitboard::{Bitboard, Color};
+use super::MAX_PLY;
+use crate::bitboard::{Color, Piece};
 use crate::eval::Evaluator;
+use crate::movegen::generator::is_pseudo_legal;
+use crate::movegen::legal::{gives_check, is_legal_move};
+use crate::movegen::ordering::OrderingTables;
+use crate::movegen::picker::MovePicker;
 use crate::movegen::{Move, MoveList};
-use crate::search::transposition::{TTEntry, TranspositionTable};
+use crate::search::syzygy::{Tablebases, TB_WIN_SCORE};
+use crate::search::transposition::{NodeType, TTEntry, TranspositionTable};
 use crate::uci::commands::TimeControl;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
+/// Score of being checkmated at the root; mates further away score less
+pub const MATE_SCORE: i32 = 30000;
+
+/// Scores beyond this (either sign) are mates
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - MAX_PLY as i32;
+
+/// Scores beyond this (either sign) are tablebase wins or mates, which
+/// both count plies from the root
+const DISTANCE_SCORE_THRESHOLD: i32 = TB_WIN_SCORE - MAX_PLY as i32;
+
+/// Score as stored in the transposition table for a node `ply` moves from
+/// the root: mate and tablebase scores are made to count from the node
+/// itself, so a transposition at another ply can reuse them
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if score > DISTANCE_SCORE_THRESHOLD {
+        score + ply
+    } else if score < -DISTANCE_SCORE_THRESHOLD {
+        score - ply
+    } else {
+        score
+    }
+}
+
+/// Inverse of `score_to_tt` for a node `ply` moves from the root
+fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if score > DISTANCE_SCORE_THRESHOLD {
+        score - ply
+    } else if score < -DISTANCE_SCORE_THRESHOLD {
+        score + ply
+    } else {
+        score
+    }
+}
+
+/// Moves to mate for a mate score, negative when the side to move is the
+/// one mated, or `None` for any other score
+pub fn mate_in_moves(score: i32) -> Option<i32> {
+    let plies = MATE_SCORE - score.abs();
+    if !(0..MAX_PLY as i32).contains(&plies) {
+        return None;
+    }
+    let moves = (plies + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+/// Whether `score` is a mate for the side to move in at most `moves` moves
+pub fn is_mate_within(score: i32, moves: u32) -> bool {
+    score >= MATE_SCORE - (2 * moves as i32 - 1)
+}
+
+/// Half-widths of the aspiration window around the previous iteration's
+/// score, one per attempt; the failing side opens to full width after these
+const ASPIRATION_WINDOWS: [i32; 3] = [50, 200, 800];
+
+/// Deepest remaining depth at which late quiet moves are pruned
+pub const LMP_MAX_DEPTH: i32 = 3;
+
+/// Moves searched at a node, by remaining depth, before quiet moves without
+/// a history score are pruned
+const LMP_MOVE_COUNTS: [usize; LMP_MAX_DEPTH as usize + 1] = [0, 5, 8, 12];
+
+/// Shallowest depth at which null-move pruning is tried
+pub const NULL_MOVE_MIN_DEPTH: i32 = 3;
+
+/// Depth reduction of the search after a null move, on top of its ply
+const NULL_MOVE_REDUCTION: i32 = 2;
+
+/// Shallowest depth at which the hash move is tested for being singular
+pub const SINGULAR_MIN_DEPTH: i32 = 4;
+
+/// Margin per ply of depth, in centipawns, by which every other move must
+/// fall short of the hash move's score for it to be singular
+const SINGULAR_MARGIN_PER_DEPTH: i32 = 8;
+
+/// How long a search runs before the root starts reporting the move it is on
+pub const CURRMOVE_DELAY: Duration = Duration::from_secs(1);
+
+/// Time kept back from the clock for communication delays
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+/// Largest share of the remaining clock allocated to one move
+const MAX_ALLOCATION_FRACTION: f32 = 0.25;
+
+/// Largest share of the remaining clock the emergency limit may reach
+const MAX_TIME_FRACTION: f32 = 0.5;
+
 /// Time management for search
+///
+/// The limits only apply from depth 2 on: the first iteration always
+/// completes, so even a nearly flagged clock gets a searched move.
 struct TimeManager {
     start_time: Instant,
     time_limit: Option<Duration>,
@@ -40,24 +135,32 @@ impl TimeManager {
             };
 
             if let Some(our_time_ms) = our_time {
-                let our_time = Duration::from_millis(our_time_ms);
+                // Keep back the time lost to communication and the GUI
+                let our_time = Duration::from_millis(our_time_ms).saturating_sub(MOVE_OVERHEAD);
                 let our_inc = our_inc.map(|inc| Duration::from_millis(inc)).unwrap_or(Duration::ZERO);
 
                 // Calculate moves to go (default to 40 if not specified)
                 let moves_to_go = time_control.movestogo.unwrap_or(40) as u32;
 
-                // Allocate time for this move: (remaining_time / moves_to_go) + increment
-                // Use a more conservative allocation to avoid timeouts
+                // Allocate time for this move: (remaining_time / moves_to_go) + increment,
+                // but never more than a fixed share of the clock whatever `movestogo` says
                 let base_allocation = our_time.checked_div(moves_to_go).unwrap_or(Duration::from_millis(100));
-                let allocated = base_allocation.saturating_add(our_inc.saturating_mul(3).checked_div(4).unwrap_or(Duration::ZERO));
+                let allocated = base_allocation
+                    .saturating_add(our_inc.saturating_mul(3).checked_div(4).unwrap_or(Duration::ZERO))
+                    .min(our_time.mul_f32(MAX_ALLOCATION_FRACTION));
 
                 // Set hard time limit to 90% of allocated time to be safe
                 let time_limit = Some(allocated.mul_f32(0.9));
 
-                // Maximum time is 5x the allocated time (for emergencies)
-                let max_time = Some(allocated.saturating_mul(5));
+                // Maximum time is 5x the allocated time (for emergencies),
+                // still well inside the clock
+                let max_time = Some(allocated.saturating_mul(5).min(our_time.mul_f32(MAX_TIME_FRACTION)));
 
                 (time_limit, max_time, allocated)
+            } else if time_control.mate.is_some() {
+                // `go mate` without a clock ends on its depth, a found mate
+                // or `stop`
+                (None, None, Duration::from_secs(3600))
             } else {
                 // No time information - use default
                 let default_time = Duration::from_millis(1000);
@@ -113,85 +216,249 @@ impl TimeManager {
 }
 use std::sync::Arc;
 
-/// Search result containing the best move and score
-#[derive(Clone, Copy)]
-pub struct SearchResult {
-    pub best_move: Option<Move>,
-    pub score: i32,
-    pub nodes_searched: u64,
+/// Number of stop checks between two looks at the stop flag and the clock
+pub const STOP_CHECK_INTERVAL: u64 = 2048;
+
+/// Throttled check of the stop flag and time limit, shared by every node of
+/// a search
+///
+/// Reading the clock isn't free, so the flag and the clock are only looked
+/// at every `STOP_CHECK_INTERVAL` checks; a search overruns its limit by at
+/// most that many nodes. Once stopped it stays stopped, so the remaining
+/// nodes unwind without looking again.
+pub struct StopCheck<'a> {
+    stop_flag: &'a AtomicBool,
+    start_time: Instant,
+    time_limit: Option<Duration>,
+    checks: u64,
+    stopped: bool,
 }
 
-/// Compute attacks by enemy pieces
-fn compute_enemy_attacks(position: &crate::bitboard::position::Position, enemy_color: Color) -> Bitboard {
-    use crate::bitboard::{attacks, Piece};
-    let occupied = (0..6).fold(Bitboard::EMPTY, |acc, p| {
-        acc | position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::White)
-            | position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::Black)
-    });
-
-    let mut enemy_attacks = Bitboard::EMPTY;
-
-    // Pawn attacks
-    let enemy_pawns = position.piece_bb(Piece::Pawn, enemy_color);
-    for sq in enemy_pawns.iter() {
-        enemy_attacks |= attacks::pawn_attacks(sq, enemy_color);
+impl<'a> StopCheck<'a> {
+    /// Check `stop_flag`, and the clock against `time_limit` from
+    /// `start_time` if there is one
+    pub fn new(stop_flag: &'a AtomicBool, start_time: Instant, time_limit: Option<Duration>) -> Self {
+        StopCheck {
+            stop_flag,
+            start_time,
+            time_limit,
+            checks: 0,
+            stopped: false,
+        }
     }
 
-    // Knight attacks
-    let enemy_knights = position.piece_bb(Piece::Knight, enemy_color);
-    for sq in enemy_knights.iter() {
-        enemy_attacks |= attacks::knight_attacks(sq);
+    /// Whether the search should stop; the first call and every
+    /// `STOP_CHECK_INTERVAL`th after it look at the flag and the clock
+    pub fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.checks.is_multiple_of(STOP_CHECK_INTERVAL) {
+            self.stopped = self.stop_flag.load(Ordering::Relaxed)
+                || self.time_limit.is_some_and(|limit| self.start_time.elapsed() >= limit);
+        }
+        self.checks += 1;
+        self.stopped
     }
 
-    // Bishop attacks
-    let enemy_bishops = position.piece_bb(Piece::Bishop, enemy_color);
-    for sq in enemy_bishops.iter() {
-        enemy_attacks |= attacks::bishop_attacks(sq, occupied);
+    /// Time since the search started
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
     }
+}
 
-    // Rook attacks
-    let enemy_rooks = position.piece_bb(Piece::Rook, enemy_color);
-    for sq in enemy_rooks.iter() {
-        enemy_attacks |= attacks::rook_attacks(sq, occupied);
-    }
+/// State shared by every node of a search: the tables it reads and fills,
+/// the evaluation and tablebases, and where in the tree it is
+pub struct SearchContext<'a> {
+    /// Transposition table probed and filled at every node
+    pub tt: &'a mut TranspositionTable,
+    /// Killers, history and countermoves for move ordering
+    pub tables: &'a mut OrderingTables,
+    /// Static evaluation of leaves and pruning decisions
+    pub evaluator: &'a Evaluator,
+    /// Endgame tablebases probed below the root
+    pub tablebases: &'a Tablebases,
+    /// Distance from the root of the node being searched; callers step it
+    /// around each child they search
+    pub ply: i32,
+    /// Deepest ply visited, quiescence included
+    pub seldepth: i32,
+    /// Stop flag and time limit, looked at throttled from every node
+    pub stop: StopCheck<'a>,
+    /// Static evaluations computed rather than taken from the table
+    pub evaluations: u64,
+}
 
-    // Queen attacks
-    let enemy_queens = position.piece_bb(Piece::Queen, enemy_color);
-    for sq in enemy_queens.iter() {
-        enemy_attacks |= attacks::queen_attacks(sq, occupied);
+impl<'a> SearchContext<'a> {
+    /// Context for a search starting at the root
+    pub fn new(
+        tt: &'a mut TranspositionTable,
+        tables: &'a mut OrderingTables,
+        evaluator: &'a Evaluator,
+        tablebases: &'a Tablebases,
+        stop: StopCheck<'a>,
+    ) -> Self {
+        SearchContext {
+            tt,
+            tables,
+            evaluator,
+            tablebases,
+            ply: 0,
+            seldepth: 0,
+            stop,
+            evaluations: 0,
+        }
     }
 
-    // King attacks
-    let enemy_king = position.piece_bb(Piece::King, enemy_color);
-    for sq in enemy_king.iter() {
-        enemy_attacks |= attacks::king_attacks(sq);
+    /// Static evaluation of `position`, taken from its transposition table
+    /// entry `entry` if that holds one, otherwise computed and stored there
+    /// for the next visit
+    ///
+    /// Near the fifty-move limit the evaluation also depends on the halfmove
+    /// clock, which the hash leaves out, so it is neither read nor stored.
+    pub fn static_eval(
+        &mut self,
+        position: &crate::bitboard::position::Position,
+        hash: u64,
+        entry: Option<TTEntry>,
+    ) -> i32 {
+        let cached = !self.evaluator.is_fifty_move_scaled(position.halfmove_clock);
+        if let Some(eval) = entry.and_then(|entry| entry.static_eval).filter(|_| cached) {
+            return eval as i32;
+        }
+        let eval = self.evaluator.evaluate(position);
+        self.evaluations += 1;
+        if let Ok(eval) = i16::try_from(eval) {
+            if cached {
+                self.tt.store_static_eval(hash, eval);
+            }
+        }
+        eval
     }
+}
 
-    enemy_attacks
+/// Whether the searched position was still playable at the root
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RootStatus {
+    /// The side to move has legal moves
+    Normal,
+    /// The side to move is checkmated
+    Checkmate,
+    /// The side to move is stalemated
+    Stalemate,
+}
+
+/// Search result containing the best move and score
+#[derive(Clone, Copy)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub nodes_searched: u64,
+    /// Set by iterative deepening when the root has no legal move
+    pub status: RootStatus,
+}
+
+/// Progress report for one principal variation of a completed
+/// iterative-deepening iteration
+#[derive(Clone)]
+pub struct SearchInfo {
+    /// Depth of the completed iteration
+    pub depth: i32,
+    /// Deepest ply reached in the iteration, quiescence included
+    pub seldepth: i32,
+    /// Index of this line among the MultiPV lines (1 = best)
+    pub multipv: usize,
+    /// Score of the line from the side to move's perspective
+    pub score: i32,
+    /// Whether `score` is exact or only a bound (aspiration fail high/low)
+    pub bound: NodeType,
+    /// Alpha-beta window the root was searched with
+    pub window: (i32, i32),
+    /// Nodes searched so far
+    pub nodes: u64,
+    /// Principal variation, starting with the root move
+    pub pv: Vec<Move>,
+    /// Time since the search started
+    pub elapsed: Duration,
+}
+
+/// Progress reported by iterative deepening while it searches
+pub enum SearchEvent<'a> {
+    /// A line of a completed iteration
+    Iteration(&'a SearchInfo),
+    /// The root move about to be searched, reported once the search has run
+    /// for `CURRMOVE_DELAY`
+    CurrMove {
+        /// Depth of the iteration in progress
+        depth: i32,
+        /// Root move being searched
+        mv: Move,
+        /// Position of the move in the root pass, counting from 1
+        number: usize,
+    },
 }
 
 /// Alpha-beta search with transposition table
+///
+/// The node is `ctx.ply` plies from the root; the deepest ply visited
+/// (quiescence included) is recorded in `ctx.seldepth`. An `excluded` move
+/// is skipped, for the singular extension's verification search: such a
+/// node neither takes a cutoff from nor stores to the transposition table,
+/// and fails low if no other move is left.
 pub fn alpha_beta_search(
+    ctx: &mut SearchContext,
     depth: i32,
     mut alpha: i32,
     mut beta: i32,
-    color: Color,
-    tt: &mut TranspositionTable,
-    evaluator: &Evaluator,
     position: &crate::bitboard::position::Position,
-    stop_flag: &Arc<AtomicBool>,
-    start_time: Instant,
-    time_limit: Option<Duration>,
+    prev_move: Option<Move>,
+    excluded: Option<Move>,
 ) -> SearchResult {
+    let color = position.side_to_move;
+    let ply = ctx.ply;
+    ctx.seldepth = ctx.seldepth.max(ply);
     let mut result = SearchResult {
         best_move: None,
         score: 0,
         nodes_searched: 1, // Count this node
+        status: RootStatus::Normal,
     };
 
-    // Check transposition table
+    // A repeated position below the root is scored as a draw
+    if ply > 0 && position.is_repetition() {
+        return result;
+    }
+
+    // Too deep to go on: settle for the static evaluation
+    if ply as usize >= MAX_PLY {
+        result.score = ctx.evaluator.evaluate(position);
+        return result;
+    }
+
+    // Mate distance pruning: nothing found from here can beat being mated
+    // right now or mating on the next move
+    alpha = alpha.max(-MATE_SCORE + ply);
+    beta = beta.min(MATE_SCORE - ply - 1);
+    if alpha >= beta {
+        result.score = alpha;
+        return result;
+    }
+
+    // Iterative deepening rejects kingless roots and legal moves never
+    // capture a king, so this can only fire on a corrupted position. Score
+    // it neutrally rather than checking legality against a missing king.
+    let has_king = !position.piece_bb(Piece::King, color).is_empty();
+    debug_assert!(has_king, "side to move has no king: {}", position.to_fen());
+    if !has_king {
+        return result;
+    }
+
+    // Check transposition table; an entry too shallow for a cutoff still
+    // supplies its best move to be searched first
     let pos_hash = position.zobrist_hash().value();
-    if let Some(tt_entry) = tt.probe(pos_hash) {
+    let tt_probe = ctx
+        .tt
+        .probe(pos_hash)
+        .map(|entry| TTEntry { score: score_from_tt(entry.score, ply), ..entry });
+    let hash_move = tt_probe.map(|entry| entry.best_move);
+    if let Some(tt_entry) = tt_probe.filter(|_| excluded.is_none()) {
         if tt_entry.depth >= depth {
             match tt_entry.node_type {
                 crate::search::transposition::NodeType::Exact => {
@@ -199,6 +466,7 @@ pub fn alpha_beta_search(
                         best_move: Some(tt_entry.best_move),
                         score: tt_entry.score,
                         nodes_searched: 1,
+                        status: RootStatus::Normal,
                     };
                 }
                 crate::search::transposition::NodeType::Lower => {
@@ -213,118 +481,166 @@ pub fn alpha_beta_search(
                     best_move: Some(tt_entry.best_move),
                     score: tt_entry.score,
                     nodes_searched: 1,
+                    status: RootStatus::Normal,
                 };
             }
         }
     }
 
-    // Base case: depth 0, go to quiescence
+    // Tablebase probe below the root (the root is probed by iterative deepening)
+    if ply > 0 {
+        if let Some(wdl) = ctx.tablebases.probe_wdl(position) {
+            result.score = wdl.to_score(ply);
+            return result;
+        }
+    }
+
+    // Base case: depth 0 stands pat in a quiet position, as quiescence
+    // would, and otherwise goes to quiescence
+    if depth == 0 && position.is_quiet() {
+        let stand_pat = ctx.static_eval(position, pos_hash, tt_probe);
+        result.score = if stand_pat >= beta { beta } else { alpha.max(stand_pat) };
+        return result;
+    }
     if depth == 0 {
-        result.score = quiescence_search(alpha, beta, color, evaluator, position, stop_flag, start_time, time_limit);
+        result.score = quiescence_search(ctx, alpha, beta, position);
         return result;
     }
 
-    // Generate pseudo-legal moves
-    use crate::bitboard::Piece;
-    use crate::movegen::generator::*;
-    use crate::movegen::legal::filter_legal_moves;
+    let occupied = position.all_occupancy();
 
-    let mut moves = MoveList::new();
-    let color = color;
-    let occupied = (0..6).fold(Bitboard::EMPTY, |acc, p| {
-        acc | position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::White)
-            | position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::Black)
-    });
-    let enemies = (0..6).fold(crate::bitboard::Bitboard::EMPTY, |acc, p| {
-        acc | position.piece_bb(Piece::from_u8(p).unwrap(), color.opposite())
-    });
+    // Moves come from the staged picker: the hash move first, then killers
+    // and the countermove to the opponent's last move ahead of the quiets
+    let countermove = prev_move.and_then(|prev| ctx.tables.countermoves.get(prev));
+    let mut picker = MovePicker::new(position, color, hash_move, *ctx.tables.killers_at(ply), countermove);
 
-    generate_pawn_moves(
-        &mut moves,
-        position.piece_bb(Piece::Pawn, color),
-        occupied,
-        enemies,
-        color,
-        position.en_passant,
-    );
-    generate_knight_moves(
-        &mut moves,
-        position.piece_bb(Piece::Knight, color),
-        occupied,
-        enemies,
-    );
-    generate_bishop_moves(
-        &mut moves,
-        position.piece_bb(Piece::Bishop, color),
-        occupied,
-        enemies,
-    );
-    generate_rook_moves(
-        &mut moves,
-        position.piece_bb(Piece::Rook, color),
-        occupied,
-        enemies,
-    );
-    generate_queen_moves(
-        &mut moves,
-        position.piece_bb(Piece::Queen, color),
-        occupied,
-        enemies,
-    );
-    if let Some(king_sq) = position.piece_bb(Piece::King, color).lsb() {
-        generate_king_moves(&mut moves, king_sq, occupied, enemies);
-    }
+    let mut best_score = i32::MIN;
+    let mut best_move = None;
+    let mut node_type = crate::search::transposition::NodeType::Upper;
+    let mut has_legal_move = false;
+    let mut moves_searched = 0;
 
-    // Filter legal moves
-    let king_sq = position
+    // Late move pruning: near the leaves, once enough moves have been
+    // searched, quiet moves that never caused a cutoff are skipped. Root
+    // moves are all searched.
+    let in_check = position
         .piece_bb(Piece::King, color)
         .lsb()
-        .unwrap_or(crate::bitboard::Square::E1);
-    // Compute enemy attacks for legality check
-    let enemy_attacks = compute_enemy_attacks(position, color.opposite());
-    let legal_moves = filter_legal_moves(
-        &moves,
-        position,
-        color,
-    );
+        .is_some_and(|sq| position.attacked_by(color.opposite()).is_occupied(sq));
+    // Null-move pruning: if passing the turn still holds beta in a reduced
+    // search, some real move will too. Not tried twice in a row (the null
+    // move's child has no previous move), in check, or without pieces,
+    // where zugzwang makes passing better than any move.
+    if ply > 0
+        && prev_move.is_some()
+        && excluded.is_none()
+        && !in_check
+        && depth >= NULL_MOVE_MIN_DEPTH
+        && beta.abs() < MATE_THRESHOLD
+        && position.has_non_pawn_material(color)
+        && ctx.static_eval(position, pos_hash, tt_probe) >= beta
+    {
+        let mut null_position = position.clone();
+        null_position.make_null_move();
+        ctx.ply += 1;
+        let null_result = alpha_beta_search(
+            ctx,
+            depth - 1 - NULL_MOVE_REDUCTION,
+            -beta,
+            -beta + 1,
+            &null_position,
+            None,
+            None,
+        );
+        ctx.ply -= 1;
+        result.nodes_searched += null_result.nodes_searched;
+        if -null_result.score >= beta {
+            result.score = beta;
+            return result;
+        }
+    }
 
-    let mut best_score = i32::MIN;
-    let mut best_move = None;
-    let mut node_type = crate::search::transposition::NodeType::Upper;
+    let lmp_move_count = (ply > 0 && depth <= LMP_MAX_DEPTH && !in_check).then(|| LMP_MOVE_COUNTS[depth as usize]);
+
+    // Singular extension: a hash move that looks like the only good move
+    // is searched one ply deeper
+    let singular_move = match tt_probe {
+        Some(entry)
+            if ply > 0
+                && excluded.is_none()
+                && depth >= SINGULAR_MIN_DEPTH
+                && entry.depth >= depth - 3
+                && entry.node_type != NodeType::Upper
+                && entry.score.abs() < MATE_THRESHOLD
+                && is_singular(
+                    ctx,
+                    entry.best_move,
+                    entry.score,
+                    depth,
+                    position,
+                    prev_move,
+                    &mut result.nodes_searched,
+                ) =>
+        {
+            Some(entry.best_move)
+        }
+        _ => None,
+    };
 
-    for &mv in legal_moves.iter() {
-        if stop_flag.load(Ordering::Relaxed) {
+    while let Some(mv) = picker.next_move(&ctx.tables.history) {
+        if Some(mv) == excluded || !is_legal_move(mv, position, color) {
+            continue;
+        }
+        has_legal_move = true;
+
+        if ctx.stop.should_stop() {
             break;
         }
 
-        // Check time limit
-        if let Some(limit) = time_limit {
-            if start_time.elapsed() >= limit {
-                break;
-            }
+        if lmp_move_count.is_some_and(|count| moves_searched >= count)
+            && Some(mv) != hash_move
+            && !mv.is_capture(occupied)
+            && !mv.is_promotion()
+            && !mv.is_en_passant()
+            && ctx.tables.history[mv.from().0 as usize][mv.to().0 as usize] <= 0
+            && !gives_check(position, mv)
+        {
+            continue;
         }
+        moves_searched += 1;
 
         let mut child_position = position.clone();
+        // Debug builds check that unmaking the move restores the position
+        #[cfg(debug_assertions)]
+        let hash_before = child_position.zobrist_hash();
         let undo = child_position.make_move(mv);
 
         // Recursive search with negated score
+        let extension = (Some(mv) == singular_move) as i32;
+        ctx.ply += 1;
         let child_result = alpha_beta_search(
-            depth - 1,
+            ctx,
+            depth - 1 + extension,
             -beta,
             -alpha,
-            color.opposite(),
-            tt,
-            evaluator,
             &child_position,
-            stop_flag,
-            start_time,
-            time_limit,
+            Some(mv),
+            None,
         );
+        ctx.ply -= 1;
 
         let score = -child_result.score;
         result.nodes_searched += child_result.nodes_searched;
 
         child_position.unmake_move(undo);
+        #[cfg(debug_assertions)]
+        assert_eq!(
+            child_position.zobrist_hash(),
+            hash_before,
+            "unmake_move({}) did not restore {}",
+            mv,
+            position.to_fen()
+        );
 
         if score > best_score {
             best_score = score;
@@ -334,23 +650,43 @@ pub fn alpha_beta_search(
         alpha = alpha.max(score);
         if alpha >= beta {
             // Beta cutoff
+            if !mv.is_capture(occupied) && !mv.is_promotion() && !mv.is_en_passant() {
+                ctx.tables.record_cutoff(mv, prev_move, ply, depth);
+            }
             node_type = crate::search::transposition::NodeType::Lower;
             break;
         }
     }
 
+    if best_move.is_none() && excluded.is_some() {
+        result.score = alpha;
+        return result;
+    }
+    if best_move.is_none() {
+        // No move searched: checkmate, stalemate, or stopped before the
+        // first move (the caller discards a stopped search's score)
+        result.score = if !has_legal_move && in_check {
+            -MATE_SCORE + ply
+        } else {
+            0
+        };
+        return result;
+    }
+
     result.score = best_score;
     result.best_move = best_move;
 
-    // Store in transposition table
-    if let Some(mv) = best_move {
-        tt.store(
+    // Store in transposition table, unless a move was left out or the
+    // search was cut short and the score is not to be trusted
+    if let (Some(mv), None, false) = (best_move, excluded, ctx.stop.should_stop()) {
+        ctx.tt.store(
             pos_hash,
             TTEntry {
-                score: best_score,
+                score: score_to_tt(best_score, ply),
                 best_move: mv,
                 depth,
                 node_type,
+                static_eval: None,
             },
         );
     }
@@ -358,25 +694,235 @@ pub fn alpha_beta_search(
     result
 }
 
+/// Whether `tt_move` is the only good move: a search of half the depth
+/// with it left out fails low against a bound `SINGULAR_MARGIN_PER_DEPTH`
+/// per ply below its transposition table score `tt_score`
+///
+/// The verification search's nodes are added to `nodes`.
+pub fn is_singular(
+    ctx: &mut SearchContext,
+    tt_move: Move,
+    tt_score: i32,
+    depth: i32,
+    position: &crate::bitboard::position::Position,
+    prev_move: Option<Move>,
+    nodes: &mut u64,
+) -> bool {
+    let singular_beta = tt_score - SINGULAR_MARGIN_PER_DEPTH * depth;
+    let verification = alpha_beta_search(
+        ctx,
+        depth / 2,
+        singular_beta - 1,
+        singular_beta,
+        position,
+        prev_move,
+        Some(tt_move),
+    );
+    *nodes += verification.nodes_searched;
+    verification.score < singular_beta
+}
+
+/// Search the root position, skipping the `excluded` root moves
+///
+/// Used by iterative deepening so MultiPV can find the next-best line by
+/// re-searching with the better moves excluded. Unlike interior nodes the
+/// root never takes a transposition table cutoff, and only stores its
+/// result when no moves are excluded. A move whose search the stop cuts
+/// short is left out, so a search stopped during its first move returns no
+/// move and nothing is stored. The deepest ply reached is recorded
+/// in `ctx.seldepth`, and each move is passed to `on_event` as it is
+/// searched once the search is older than `CURRMOVE_DELAY`.
+pub fn search_root(
+    ctx: &mut SearchContext,
+    depth: i32,
+    mut alpha: i32,
+    beta: i32,
+    position: &crate::bitboard::position::Position,
+    excluded: &[Move],
+    on_event: &mut dyn FnMut(SearchEvent),
+) -> SearchResult {
+    let color = position.side_to_move;
+    let mut result = SearchResult {
+        best_move: None,
+        score: i32::MIN,
+        nodes_searched: 1,
+        status: RootStatus::Normal,
+    };
+    let original_alpha = alpha;
+    let mut move_number = 0;
+
+    let pos_hash = position.zobrist_hash().value();
+    let hash_move = ctx.tt.probe(pos_hash).map(|entry| entry.best_move);
+    let mut picker = MovePicker::new(position, color, hash_move, *ctx.tables.killers_at(ctx.ply), None);
+
+    while let Some(mv) = picker.next_move(&ctx.tables.history) {
+        if excluded.contains(&mv) || !is_legal_move(mv, position, color) {
+            continue;
+        }
+
+        if ctx.stop.should_stop() {
+            break;
+        }
+
+        move_number += 1;
+        if ctx.stop.elapsed() >= CURRMOVE_DELAY {
+            on_event(SearchEvent::CurrMove { depth, mv, number: move_number });
+        }
+
+        let mut child_position = position.clone();
+        child_position.make_move(mv);
+
+        ctx.ply += 1;
+        let child_result = alpha_beta_search(ctx, depth - 1, -beta, -alpha, &child_position, Some(mv), None);
+        ctx.ply -= 1;
+
+        let score = -child_result.score;
+        result.nodes_searched += child_result.nodes_searched;
+
+        // A move whose search was cut short has no score
+        if ctx.stop.should_stop() {
+            break;
+        }
+
+        if score > result.score {
+            result.score = score;
+            result.best_move = Some(mv);
+        }
+
+        alpha = alpha.max(score);
+        if alpha >= beta {
+            break;
+        }
+    }
+
+    if let (Some(mv), true, false) = (result.best_move, excluded.is_empty(), ctx.stop.should_stop()) {
+        let node_type = if result.score >= beta {
+            crate::search::transposition::NodeType::Lower
+        } else if result.score <= original_alpha {
+            crate::search::transposition::NodeType::Upper
+        } else {
+            crate::search::transposition::NodeType::Exact
+        };
+        ctx.tt.store(
+            pos_hash,
+            TTEntry {
+                score: score_to_tt(result.score, ctx.ply),
+                best_move: mv,
+                depth,
+                node_type,
+                static_eval: None,
+            },
+        );
+    }
+
+    result
+}
+
+/// Follow best moves through the transposition table to build a principal
+/// variation of at most `max_len` moves starting with `first_move`
+pub fn extract_pv(
+    position: &crate::bitboard::position::Position,
+    tt: &TranspositionTable,
+    first_move: Move,
+    max_len: usize,
+) -> Vec<Move> {
+    let mut pv = vec![first_move];
+    let mut pos = position.clone();
+    pos.make_move(first_move);
+
+    while pv.len() < max_len {
+        let Some(entry) = tt.probe(pos.zobrist_hash().value()) else {
+            break;
+        };
+        let mv = entry.best_move;
+        let color = pos.side_to_move;
+        if !is_pseudo_legal(&pos, color, mv) || !is_legal_move(mv, &pos, color) {
+            break;
+        }
+        pos.make_move(mv);
+        pv.push(mv);
+    }
+
+    pv
+}
+
 /// Iterative deepening alpha-beta search
+///
+/// Searches the best `multipv` root moves at every depth and reports each
+/// line of a completed depth, plus the current root move on long searches,
+/// through `on_event`.
 pub fn iterative_deepening(
     time_control: &TimeControl,
     color: Color,
     tt: &mut TranspositionTable,
+    tables: &mut OrderingTables,
     evaluator: &Evaluator,
+    tablebases: &Tablebases,
     position: &crate::bitboard::position::Position,
+    multipv: usize,
     stop_flag: &Arc<AtomicBool>,
+    on_event: &mut dyn FnMut(SearchEvent),
 ) -> SearchResult {
+    // Without a king there is nothing meaningful to search (e.g. an empty
+    // board handed in by a library user)
+    if position.piece_bb(Piece::King, color).is_empty() {
+        return SearchResult {
+            best_move: None,
+            score: 0,
+            nodes_searched: 0,
+            status: RootStatus::Normal,
+        };
+    }
+
+    // With no legal move the game is over; there is nothing to search
+    let status = root_status(position, color);
+    if status != RootStatus::Normal {
+        return SearchResult {
+            best_move: None,
+            score: if status == RootStatus::Checkmate { -MATE_SCORE } else { 0 },
+            nodes_searched: 0,
+            status,
+        };
+    }
+
+    // A tablebase hit at the root decides the move without searching
+    if let Some(mv) = tablebases.probe_root(position) {
+        return SearchResult {
+            best_move: Some(mv),
+            score: tablebases.probe_wdl(position).map_or(0, |wdl| wdl.to_score(0)),
+            nodes_searched: 0,
+            status: RootStatus::Normal,
+        };
+    }
+
     let time_manager = TimeManager::new(time_control, color);
-    let max_depth = time_control.depth.unwrap_or(8) as i32;
+    // `go mate` searches deep enough for the mate and only for lines that
+    // beat `mate_alpha`, a mate within the requested number of moves. If
+    // none is found the score is only known to be no better than that.
+    let mate_plies = time_control.mate.map(|moves| (2 * moves as i32 - 1).min(MAX_PLY as i32 - 1));
+    let mate_alpha = mate_plies.map(|plies| MATE_SCORE - plies - 1);
+    let max_depth = time_control
+        .depth
+        .or(mate_plies.map(|plies| plies as u32))
+        .unwrap_or(8)
+        .min(MAX_PLY as u32) as i32;
     let mut result = SearchResult {
         best_move: None,
         score: 0,
         nodes_searched: 0,
+        status: RootStatus::Normal,
     };
 
     // Generate at least one legal move as fallback
     let fallback_move = generate_fallback_move(position, color);
+    let mut total_nodes = 0;
+    let mut ctx = SearchContext::new(
+        tt,
+        tables,
+        evaluator,
+        tablebases,
+        StopCheck::new(stop_flag, time_manager.start_time, None),
+    );
 
     // Iterative deepening with time management
     for depth in 1..=max_depth {
@@ -396,25 +942,94 @@ pub fn iterative_deepening(
             }
         }
 
-        let window_result = alpha_beta_search(
-            depth,
-            i32::MIN / 2,
-            i32::MAX / 2,
-            color,
-            tt,
-            evaluator,
-            position,
-            stop_flag,
-            time_manager.start_time,
-            time_manager.time_limit,
-        );
+        // Each further line re-searches the root without the moves already found
+        let mut excluded: Vec<Move> = Vec::new();
+        ctx.seldepth = 0;
+        for pv_index in 1..=multipv.max(1) {
+            // The main line searches a narrow window around the previous
+            // iteration's score and widens it on each fail high or low
+            let mut fails = 0;
+            let (mut alpha, mut beta) = if let Some(mate_alpha) = mate_alpha {
+                (mate_alpha, i32::MAX / 2)
+            } else if pv_index == 1 && depth > 1 {
+                aspiration_window(result.score, 0)
+            } else {
+                (i32::MIN / 2, i32::MAX / 2)
+            };
 
-        result = window_result;
+            let line = loop {
+                ctx.stop = StopCheck::new(
+                    stop_flag,
+                    time_manager.start_time,
+                    if depth == 1 { None } else { time_manager.time_limit },
+                );
+                let line = search_root(&mut ctx, depth, alpha, beta, position, &excluded, on_event);
+                total_nodes += line.nodes_searched;
+
+                let bound = if line.score <= alpha && alpha > i32::MIN / 2 {
+                    NodeType::Upper
+                } else if line.score >= beta && beta < i32::MAX / 2 {
+                    NodeType::Lower
+                } else {
+                    NodeType::Exact
+                };
+
+                // Failing low in a mate search only says there is no mate yet
+                let no_mate = mate_alpha.is_some() && bound == NodeType::Upper;
+                if let (Some(mv), false) = (line.best_move, no_mate) {
+                    on_event(SearchEvent::Iteration(&SearchInfo {
+                        depth,
+                        seldepth: ctx.seldepth,
+                        multipv: pv_index,
+                        score: line.score,
+                        bound,
+                        window: (alpha, beta),
+                        nodes: total_nodes,
+                        pv: extract_pv(position, ctx.tt, mv, depth as usize),
+                        elapsed: time_manager.elapsed(),
+                    }));
+                }
 
-        // Check time after each depth
+                let stopped = stop_flag.load(Ordering::Relaxed) || time_manager.should_stop();
+                if bound == NodeType::Exact || stopped || mate_alpha.is_some() {
+                    break line;
+                }
+
+                fails += 1;
+                let (wide_alpha, wide_beta) = aspiration_window(result.score, fails);
+                if bound == NodeType::Upper {
+                    alpha = wide_alpha;
+                } else {
+                    beta = wide_beta;
+                }
+            };
+
+            // A stopped iteration that didn't finish its first move keeps
+            // the previous depth's result
+            let Some(mv) = line.best_move else {
+                break;
+            };
+            if pv_index == 1 {
+                result = line;
+            }
+            result.nodes_searched = total_nodes;
+            excluded.push(mv);
+
+            if stop_flag.load(Ordering::Relaxed) || time_manager.should_stop() {
+                break;
+            }
+        }
+
+        // Check time and the node budget after each depth
         if time_manager.should_stop() {
             break;
         }
+        if time_control.mate.is_some_and(|moves| is_mate_within(result.score, moves)) {
+            break;
+        }
+        if time_control.nodes.is_some_and(|nodes| total_nodes >= nodes) {
+            break;
+        }
 
         // For deeper searches, be more conservative about time
         if depth >= 6 {
@@ -435,6 +1050,39 @@ pub fn iterative_deepening(
     result
 }
 
+/// Aspiration window around `score` after `fails` failed attempts, full
+/// width once `ASPIRATION_WINDOWS` runs out
+fn aspiration_window(score: i32, fails: usize) -> (i32, i32) {
+    match ASPIRATION_WINDOWS.get(fails) {
+        Some(&delta) => (
+            score.saturating_sub(delta).max(i32::MIN / 2),
+            score.saturating_add(delta).min(i32::MAX / 2),
+        ),
+        None => (i32::MIN / 2, i32::MAX / 2),
+    }
+}
+
+/// Checkmate, stalemate or a normal position for the side to move
+fn root_status(position: &crate::bitboard::position::Position, color: Color) -> RootStatus {
+    use crate::movegen::generator::generate_moves;
+
+    let mut moves = MoveList::new();
+    generate_moves(&mut moves, position, color);
+    if moves.iter().any(|&mv| is_legal_move(mv, position, color)) {
+        return RootStatus::Normal;
+    }
+
+    let in_check = position
+        .piece_bb(Piece::King, color)
+        .lsb()
+        .is_some_and(|king_sq| position.attacked_by(color.opposite()).is_occupied(king_sq));
+    if in_check {
+        RootStatus::Checkmate
+    } else {
+        RootStatus::Stalemate
+    }
+}
+
 /// Generate a fallback move (first legal move found)
 fn generate_fallback_move(position: &crate::bitboard::position::Position, color: Color) -> Option<Move> {
     use crate::bitboard::Piece;
@@ -442,13 +1090,8 @@ fn generate_fallback_move(position: &crate::bitboard::position::Position, color:
     use crate::movegen::legal::filter_legal_moves;
 
     let mut moves = MoveList::new();
-    let occupied = (0..6).fold(Bitboard::EMPTY, |acc, p| {
-        acc | position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::White)
-            | position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::Black)
-    });
-    let enemies = (0..6).fold(crate::bitboard::Bitboard::EMPTY, |acc, p| {
-        acc | position.piece_bb(Piece::from_u8(p).unwrap(), color.opposite())
-    });
+    let occupied = position.all_occupancy();
+    let enemies = position.occupancy(color.opposite());
 
     generate_pawn_moves(
         &mut moves,
@@ -498,25 +1141,727 @@ mod tests {
     fn test_alpha_beta_structure() {
         // Basic test that the functions exist and can be called
         let mut tt = TranspositionTable::new();
+        let mut tables = OrderingTables::new();
         let evaluator = Evaluator::new();
         let stop_flag = Arc::new(AtomicBool::new(false));
         let start_time = Instant::now();
 
-        let dummy_position = crate::bitboard::position::Position::empty();
+        crate::bitboard::magic::init_magics();
+        let mut position = crate::bitboard::position::Position::empty();
+        position.set_startpos();
+        let tablebases = Tablebases::new();
         let result = alpha_beta_search(
+            &mut SearchContext::new(&mut tt, &mut tables, &evaluator, &tablebases, StopCheck::new(&stop_flag, start_time, Some(Duration::from_secs(1)))),
             1,
             i32::MIN / 2,
             i32::MAX / 2,
-            Color::White,
-            &mut tt,
-            &evaluator,
-            &dummy_position,
-            &stop_flag,
-            start_time,
-            Some(Duration::from_secs(1)),
+            &position,
+            None,
+            None,
         );
 
         // In a real test, we'd have a position and check the result
         assert!(result.nodes_searched >= 1);
     }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "side to move has no king")]
+    fn test_kingless_side_trips_debug_assert() {
+        use crate::bitboard::position::Position;
+        use crate::bitboard::Square;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_piece(Piece::Rook, Color::White, Square::A1);
+        pos.set_piece(Piece::King, Color::Black, Square::E8);
+
+        alpha_beta_search(
+            &mut SearchContext::new(
+                &mut TranspositionTable::with_size(1),
+                &mut OrderingTables::new(),
+                &Evaluator::new(),
+                &Tablebases::new(),
+                StopCheck::new(&AtomicBool::new(false), Instant::now(), None),
+            ),
+            2,
+            i32::MIN / 2,
+            i32::MAX / 2,
+            &pos,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn test_finds_back_rank_mate() {
+        use crate::bitboard::position::Position;
+        use crate::bitboard::Square;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let time_control = TimeControl {
+            depth: Some(3),
+            ..TimeControl::default()
+        };
+
+        let result = iterative_deepening(
+            &time_control,
+            Color::White,
+            &mut TranspositionTable::with_size(1),
+            &mut OrderingTables::new(),
+            &Evaluator::new(),
+            &Tablebases::new(),
+            &pos,
+            1,
+            &Arc::new(AtomicBool::new(false)),
+            &mut |_| {},
+        );
+
+        assert_eq!(result.best_move, Some(Move::new(Square::A1, Square::A8)));
+        assert_eq!(result.score, MATE_SCORE - 1);
+    }
+
+    #[test]
+    fn test_tt_mate_scores_follow_the_ply() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let (evaluator, tablebases, stop_flag) = (Evaluator::new(), Tablebases::new(), AtomicBool::new(false));
+        let mut tt = TranspositionTable::with_size(1);
+        let mut search = |ply| {
+            let mut tables = OrderingTables::new();
+            let stop = StopCheck::new(&stop_flag, Instant::now(), None);
+            let mut ctx = SearchContext::new(&mut tt, &mut tables, &evaluator, &tablebases, stop);
+            ctx.ply = ply;
+            alpha_beta_search(&mut ctx, 2, -MATE_SCORE, MATE_SCORE, &pos, None, None)
+        };
+
+        // Mate in one found two plies down, then reached again through a
+        // transposition four plies down, where it is a hash hit
+        assert_eq!(search(2).score, MATE_SCORE - 3);
+        let hit = search(4);
+        assert_eq!(hit.nodes_searched, 1);
+        assert_eq!(hit.score, MATE_SCORE - 5);
+
+        assert_eq!(score_from_tt(score_to_tt(-MATE_SCORE + 7, 3), 5), -MATE_SCORE + 9);
+        assert_eq!(score_from_tt(score_to_tt(TB_WIN_SCORE - 4, 4), 1), TB_WIN_SCORE - 1);
+        assert_eq!(score_to_tt(250, 6), 250);
+    }
+
+    #[test]
+    fn test_root_without_moves_reports_game_over() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let search = |fen: &str| {
+            let mut pos = Position::empty();
+            pos.set_fen(fen).unwrap();
+            iterative_deepening(
+                &TimeControl {
+                    depth: Some(3),
+                    ..TimeControl::default()
+                },
+                pos.side_to_move,
+                &mut TranspositionTable::with_size(1),
+                &mut OrderingTables::new(),
+                &Evaluator::new(),
+                &Tablebases::new(),
+                &pos,
+                1,
+                &Arc::new(AtomicBool::new(false)),
+                &mut |_| {},
+            )
+        };
+
+        let mated = search("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1");
+        assert_eq!(mated.status, RootStatus::Checkmate);
+        assert_eq!((mated.best_move, mated.score), (None, -MATE_SCORE));
+
+        let stalemated = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(stalemated.status, RootStatus::Stalemate);
+        assert_eq!((stalemated.best_move, stalemated.score), (None, 0));
+
+        let start = search("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(start.status, RootStatus::Normal);
+    }
+
+    #[test]
+    fn test_search_stops_at_max_ply() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        // Open kings and queens: nearly every line is a string of checks
+        pos.set_fen("3qk3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let evaluator = Evaluator::new();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let search = |depth, ply| {
+            let (mut tt, mut tables, tablebases) = (TranspositionTable::with_size(1), OrderingTables::new(), Tablebases::new());
+            let stop = StopCheck::new(&stop_flag, Instant::now(), None);
+            let mut ctx = SearchContext::new(&mut tt, &mut tables, &evaluator, &tablebases, stop);
+            ctx.ply = ply;
+            let result = alpha_beta_search(&mut ctx, depth, i32::MIN / 2, i32::MAX / 2, &pos, None, None);
+            (result, ctx.seldepth)
+        };
+
+        let (capped, _) = search(4, MAX_PLY as i32);
+        assert_eq!(capped.score, evaluator.evaluate(&pos));
+        assert_eq!(capped.nodes_searched, 1);
+
+        let (near_cap, seldepth) = search(4, MAX_PLY as i32 - 2);
+        assert!(near_cap.nodes_searched > 1);
+        assert_eq!(seldepth, MAX_PLY as i32);
+    }
+
+    #[test]
+    fn test_late_move_pruning_saves_nodes() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        // Quiet rook endgame: many quiet moves, none of them forcing
+        pos.set_fen("r4rk1/pp3ppp/2p5/8/8/2P5/PP3PPP/R4RK1 w - - 0 1").unwrap();
+        let evaluator = Evaluator::new();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let search = |tables: &mut OrderingTables| {
+            alpha_beta_search(
+                &mut SearchContext::new(&mut TranspositionTable::with_size(1), tables, &evaluator, &Tablebases::new(), StopCheck::new(&stop_flag, Instant::now(), None)),
+                3,
+                i32::MIN / 2,
+                i32::MAX / 2,
+                &pos,
+                None,
+                None,
+            )
+        };
+
+        // A history score on every move keeps them all from being pruned
+        // without changing the order they are searched in
+        let mut unpruned_tables = OrderingTables::new();
+        unpruned_tables.history.iter_mut().flatten().for_each(|score| *score = 1);
+        let unpruned = search(&mut unpruned_tables);
+        let pruned = search(&mut OrderingTables::new());
+
+        assert!(
+            pruned.nodes_searched < unpruned.nodes_searched,
+            "{} nodes with pruning, {} without",
+            pruned.nodes_searched,
+            unpruned.nodes_searched
+        );
+        assert_eq!(pruned.best_move, unpruned.best_move);
+    }
+
+    #[test]
+    fn test_singular_hash_move_detected() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let evaluator = Evaluator::new();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let depth = SINGULAR_MIN_DEPTH;
+        let singular = |fen: &str| {
+            let mut pos = Position::empty();
+            pos.set_fen(fen).unwrap();
+            let mut tt = TranspositionTable::with_size(1);
+            let mut tables = OrderingTables::new();
+            let tablebases = Tablebases::new();
+            let stop = StopCheck::new(&stop_flag, Instant::now(), None);
+            let mut ctx = SearchContext::new(&mut tt, &mut tables, &evaluator, &tablebases, stop);
+            let best = alpha_beta_search(&mut ctx, depth, i32::MIN / 2, i32::MAX / 2, &pos, None, None);
+            let mut nodes = 0;
+            let singular = is_singular(&mut ctx, best.best_move.unwrap(), best.score, depth, &pos, None, &mut nodes);
+            assert!(nodes > 0);
+            singular
+        };
+
+        // Only taking the queen keeps White from losing material
+        assert!(singular("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1"));
+        // Plenty of opening moves are about as good as the best one
+        assert!(!singular("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"));
+    }
+
+    #[test]
+    fn test_search_unmakes_every_move_type() {
+        use crate::bitboard::position::Position;
+        use crate::movegen::generator::{generate_moves, MoveType};
+
+        crate::bitboard::magic::init_magics();
+        // Captures, promotions (capturing or not), en passant and both castles
+        let mut pos = Position::empty();
+        pos.set_fen("r3k2r/pPpp1ppp/8/3Pp3/8/8/PPP2PPP/R3K2R w KQkq e6 0 1").unwrap();
+        let mut moves = MoveList::new();
+        generate_moves(&mut moves, &pos, Color::White);
+        let occupied = pos.all_occupancy();
+        for move_type in [MoveType::Normal, MoveType::Promotion, MoveType::EnPassant, MoveType::Castling] {
+            assert!(moves.iter().any(|mv| mv.move_type() == move_type));
+        }
+        assert!(moves.iter().any(|mv| mv.is_promotion() && mv.is_capture(occupied)));
+
+        // Debug builds assert after every unmake that the position is restored
+        let result = alpha_beta_search(
+            &mut SearchContext::new(
+                &mut TranspositionTable::with_size(1),
+                &mut OrderingTables::new(),
+                &Evaluator::new(),
+                &Tablebases::new(),
+                StopCheck::new(&AtomicBool::new(false), Instant::now(), None),
+            ),
+            3,
+            -MATE_SCORE,
+            MATE_SCORE,
+            &pos,
+            None,
+            None,
+        );
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_static_eval_reused_from_tt() {
+        use crate::bitboard::position::Position;
+        use crate::bitboard::Square;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_fen("r4rk1/pp3ppp/2p5/8/8/2P5/PP3PPP/R4RK1 w - - 0 1").unwrap();
+        let (evaluator, tablebases, stop_flag) = (Evaluator::new(), Tablebases::new(), AtomicBool::new(false));
+        let prev_move = Some(Move::new(Square::G7, Square::G6));
+        // Search one ply down (so null moves are tried), returning the
+        // score and the number of evaluator calls
+        let search = |tt: &mut TranspositionTable, depth| {
+            let mut tables = OrderingTables::new();
+            let stop = StopCheck::new(&stop_flag, Instant::now(), None);
+            let mut ctx = SearchContext::new(tt, &mut tables, &evaluator, &tablebases, stop);
+            ctx.ply = 1;
+            let result = alpha_beta_search(&mut ctx, depth, -100, 100, &pos, prev_move, None);
+            (result.score, ctx.evaluations)
+        };
+
+        // Depth 0 stands pat on the evaluation, which a revisit reads back
+        let mut tt = TranspositionTable::with_size(1);
+        let (score, evaluations) = search(&mut tt, 0);
+        assert_eq!((score, evaluations), (evaluator.evaluate(&pos), 1));
+        assert_eq!(search(&mut tt, 0), (score, 0));
+        tt.set_enabled(false);
+        assert_eq!(search(&mut tt, 0), (score, 1));
+
+        // The null move gate takes the evaluation from the table too
+        let full = search(&mut TranspositionTable::with_size(1), 3);
+        let mut tt = TranspositionTable::with_size(1);
+        tt.store_static_eval(pos.zobrist_hash().value(), score as i16);
+        let (hit_score, hit_evaluations) = search(&mut tt, 3);
+        assert_eq!((hit_score, hit_evaluations), (full.0, full.1 - 1));
+    }
+
+    #[test]
+    fn test_static_eval_not_cached_near_fifty_move_limit() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let (evaluator, tablebases, stop_flag) = (Evaluator::new(), Tablebases::new(), AtomicBool::new(false));
+        let mut tt = TranspositionTable::with_size(1);
+        let mut tables = OrderingTables::new();
+        let stop = StopCheck::new(&stop_flag, Instant::now(), None);
+        let mut ctx = SearchContext::new(&mut tt, &mut tables, &evaluator, &tablebases, stop);
+
+        // The same position, hash and all, before and after the clock counts
+        let mut fresh = Position::empty();
+        fresh.set_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let mut late = fresh.clone();
+        late.halfmove_clock = 95;
+        let hash = fresh.zobrist_hash().value();
+        assert_eq!(late.zobrist_hash().value(), hash);
+
+        let fresh_eval = ctx.static_eval(&fresh, hash, None);
+        let entry = ctx.tt.probe(hash);
+        assert_eq!(ctx.static_eval(&late, hash, entry), evaluator.evaluate(&late));
+        assert!(ctx.tt.probe(hash).and_then(|entry| entry.static_eval) == Some(fresh_eval as i16));
+        assert_eq!(ctx.evaluations, 2);
+    }
+
+    #[test]
+    fn test_shallow_tt_move_searched_first() {
+        use crate::bitboard::position::Position;
+        use crate::bitboard::Square;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_startpos();
+        let evaluator = Evaluator::new();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        // A quiet move the picker would otherwise try late, stored too
+        // shallow to cut the node off
+        let tt_move = Move::new(Square::H2, Square::H3);
+        let mut tt = TranspositionTable::with_size(1);
+        tt.store(
+            pos.zobrist_hash().value(),
+            TTEntry {
+                score: 0,
+                best_move: tt_move,
+                depth: 0,
+                node_type: NodeType::Exact,
+                static_eval: None,
+            },
+        );
+
+        // Every move fails high against this window, so the first move
+        // searched is the one returned
+        let result = alpha_beta_search(
+            &mut SearchContext::new(&mut tt, &mut OrderingTables::new(), &evaluator, &Tablebases::new(), StopCheck::new(&stop_flag, Instant::now(), None)),
+            2,
+            -MATE_SCORE,
+            -MATE_SCORE + 1,
+            &pos,
+            None,
+            None,
+        );
+        assert_eq!(result.best_move, Some(tt_move));
+    }
+
+    #[test]
+    fn test_throttled_checks_respect_tight_time_limit() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_startpos();
+        let stop_flag = AtomicBool::new(false);
+        let time_limit = Duration::from_millis(1);
+        let (mut tt, mut tables) = (TranspositionTable::with_size(1), OrderingTables::new());
+        let (evaluator, tablebases) = (Evaluator::new(), Tablebases::new());
+
+        // The first check (normally) finds time left, the next one
+        // STOP_CHECK_INTERVAL checks later finds it gone: every node below
+        // the root was checked for on the way in, so the search ends within
+        // one interval
+        let start_time = Instant::now();
+        let result = alpha_beta_search(
+            &mut SearchContext::new(&mut tt, &mut tables, &evaluator, &tablebases, StopCheck::new(&stop_flag, start_time, Some(time_limit))),
+            20,
+            i32::MIN / 2,
+            i32::MAX / 2,
+            &pos,
+            None,
+            None,
+        );
+        assert!(result.nodes_searched <= STOP_CHECK_INTERVAL + 1, "{} nodes", result.nodes_searched);
+        assert!(start_time.elapsed() < Duration::from_secs(5));
+        // The root's score was cut short, so it isn't stored
+        assert!(tt.probe(pos.zobrist_hash().value()).is_none());
+
+        // The stop flag is throttled the same way, and sticks once seen
+        stop_flag.store(true, Ordering::Relaxed);
+        let mut stop = StopCheck::new(&stop_flag, Instant::now(), None);
+        assert!(stop.should_stop());
+        stop_flag.store(false, Ordering::Relaxed);
+        assert!(stop.should_stop());
+    }
+
+    #[test]
+    fn test_root_stopped_during_first_move_returns_no_move() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_startpos();
+        let (mut tt, mut tables) = (TranspositionTable::with_size(1), OrderingTables::new());
+        let (evaluator, tablebases) = (Evaluator::new(), Tablebases::new());
+
+        // The flag is already set but only looked at one interval in, far
+        // too early to finish the first move at this depth
+        let stop_flag = AtomicBool::new(true);
+        let stop = StopCheck { checks: 1, ..StopCheck::new(&stop_flag, Instant::now(), None) };
+        let mut ctx = SearchContext::new(&mut tt, &mut tables, &evaluator, &tablebases, stop);
+        let result = search_root(&mut ctx, 20, i32::MIN / 2, i32::MAX / 2, &pos, &[], &mut |_| {});
+        assert!(result.best_move.is_none());
+        assert!(result.nodes_searched > 1);
+        assert!(tt.probe(pos.zobrist_hash().value()).is_none());
+    }
+
+    #[test]
+    fn test_aspiration_window_widens_in_steps() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        // A rook up until depth 3 finds the mate: 1. Kb6 Kb8 2. Rh8#
+        let mut pos = Position::empty();
+        pos.set_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        let mut searches = Vec::new();
+        let result = iterative_deepening(
+            &TimeControl {
+                depth: Some(3),
+                ..TimeControl::default()
+            },
+            Color::White,
+            &mut TranspositionTable::with_size(1),
+            &mut OrderingTables::new(),
+            &Evaluator::new(),
+            &Tablebases::new(),
+            &pos,
+            1,
+            &Arc::new(AtomicBool::new(false)),
+            &mut |event| {
+                if let SearchEvent::Iteration(info) = event {
+                    searches.push((info.depth, info.window, info.score, info.bound == NodeType::Exact));
+                }
+            },
+        );
+        assert_eq!(result.score, MATE_SCORE - 3);
+
+        // Depth 2 lands inside the window around depth 1's score
+        let (first, second) = (searches[0].2, searches[1].2);
+        assert_eq!(
+            searches[..2],
+            [(1, (i32::MIN / 2, i32::MAX / 2), first, true), (2, (first - 50, first + 50), second, true)]
+        );
+
+        // The mate fails high through each wider window before the full one,
+        // the lower bound staying where it was
+        let windows: Vec<_> = searches[2..].iter().map(|&(depth, window, _, exact)| (depth, window, exact)).collect();
+        assert_eq!(
+            windows,
+            [
+                (3, (second - 50, second + 50), false),
+                (3, (second - 50, second + 200), false),
+                (3, (second - 50, second + 800), false),
+                (3, (second - 50, i32::MAX / 2), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multipv_reports_distinct_root_moves() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_startpos();
+        let time_control = TimeControl {
+            depth: Some(2),
+            ..TimeControl::default()
+        };
+
+        let mut lines = Vec::new();
+        let result = iterative_deepening(
+            &time_control,
+            Color::White,
+            &mut TranspositionTable::with_size(1),
+            &mut OrderingTables::new(),
+            &Evaluator::new(),
+            &Tablebases::new(),
+            &pos,
+            2,
+            &Arc::new(AtomicBool::new(false)),
+            &mut |event| {
+                if let SearchEvent::Iteration(info) = event {
+                    if info.depth == 2 {
+                        lines.push((info.multipv, info.pv[0], info.score));
+                    }
+                }
+            },
+        );
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].0, lines[1].0), (1, 2));
+        assert_ne!(lines[0].1, lines[1].1);
+        assert!(lines[0].2 >= lines[1].2);
+        assert_eq!(result.best_move, Some(lines[0].1));
+        for (_, mv, _) in lines {
+            assert!(is_pseudo_legal(&pos, Color::White, mv) && is_legal_move(mv, &pos, Color::White));
+        }
+    }
+
+    #[test]
+    fn test_go_mate_finds_mate_within_bound() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        // Mate in two: 1. Nf6+ gxf6 2. Bxf7#
+        let mut pos = Position::empty();
+        pos.set_fen("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 10").unwrap();
+        let mate_search = |moves| {
+            let mut lines = Vec::new();
+            let result = iterative_deepening(
+                &TimeControl { depth: None, mate: Some(moves), ..TimeControl::default() },
+                Color::White,
+                &mut TranspositionTable::with_size(1),
+                &mut OrderingTables::new(),
+                &Evaluator::new(),
+                &Tablebases::new(),
+                &pos,
+                1,
+                &Arc::new(AtomicBool::new(false)),
+                &mut |event| {
+                    if let SearchEvent::Iteration(info) = event {
+                        lines.push((info.depth, info.score, info.pv.clone()));
+                    }
+                },
+            );
+            (result, lines)
+        };
+
+        let (found, lines) = mate_search(2);
+        assert_eq!(mate_in_moves(found.score), Some(2));
+        let (_, score, pv) = lines.last().unwrap();
+        assert_eq!(mate_in_moves(*score), Some(2));
+        assert_eq!(pv[0].to_string(), "d5f6");
+
+        let (not_found, lines) = mate_search(1);
+        assert!(!is_mate_within(not_found.score, 1));
+        assert!(lines.is_empty());
+        assert!(not_found.best_move.is_some());
+    }
+
+    #[test]
+    fn test_seldepth_reaches_nominal_depth() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        // Pawns and minor pieces in contact keep quiescence busy past the horizon
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/2n5/3p4/4P3/5N2/8/4K3 w - - 0 1").unwrap();
+        let time_control = TimeControl {
+            depth: Some(3),
+            infinite: true,
+            ..TimeControl::default()
+        };
+
+        let mut reports = Vec::new();
+        iterative_deepening(
+            &time_control,
+            Color::White,
+            &mut TranspositionTable::with_size(1),
+            &mut OrderingTables::new(),
+            &Evaluator::new(),
+            &Tablebases::new(),
+            &pos,
+            1,
+            &Arc::new(AtomicBool::new(false)),
+            &mut |event| {
+                if let SearchEvent::Iteration(info) = event {
+                    reports.push((info.depth, info.seldepth));
+                }
+            },
+        );
+
+        assert_eq!(reports.last().map(|&(depth, _)| depth), Some(3));
+        for (depth, seldepth) in reports {
+            assert!(seldepth >= depth, "seldepth {} below depth {}", seldepth, depth);
+        }
+    }
+
+    #[test]
+    fn test_long_search_reports_currmove_numbers() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_startpos();
+        // A node budget far beyond what the test waits for keeps the search
+        // running past CURRMOVE_DELAY; it is stopped once a full pass is seen
+        let time_control = TimeControl {
+            depth: Some(MAX_PLY as u32),
+            nodes: Some(u64::MAX),
+            infinite: true,
+            ..TimeControl::default()
+        };
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let mut reports = Vec::new();
+        iterative_deepening(
+            &time_control,
+            Color::White,
+            &mut TranspositionTable::with_size(1),
+            &mut OrderingTables::new(),
+            &Evaluator::new(),
+            &Tablebases::new(),
+            &pos,
+            1,
+            &stop_flag,
+            &mut |event| {
+                if let SearchEvent::CurrMove { depth, mv, number } = event {
+                    reports.push((depth, mv, number));
+                    if number == 20 {
+                        stop_flag.store(true, Ordering::Relaxed);
+                    }
+                }
+            },
+        );
+
+        assert!(reports.iter().any(|&(_, _, number)| number == 20));
+        for pair in reports.windows(2) {
+            let ((depth, _, prev), (next_depth, _, next)) = (pair[0], pair[1]);
+            assert!(next == 1 || (next == prev + 1 && next_depth == depth), "{:?}", pair);
+        }
+        for (_, mv, number) in reports {
+            assert!((1..=20).contains(&number));
+            assert!(is_pseudo_legal(&pos, Color::White, mv) && is_legal_move(mv, &pos, Color::White));
+        }
+    }
+
+    #[test]
+    fn test_time_allocation_short_and_long_clocks() {
+        use crate::bitboard::position::Position;
+
+        let clock = |ms| TimeControl {
+            wtime: Some(ms),
+            btime: Some(ms),
+            depth: None,
+            ..TimeControl::default()
+        };
+
+        // A nearly flagged clock keeps the overhead back and stays inside it
+        let short = TimeManager::new(&clock(50), Color::White);
+        let usable = Duration::from_millis(50) - MOVE_OVERHEAD;
+        assert!(short.time_limit.unwrap() <= usable.mul_f32(MAX_ALLOCATION_FRACTION));
+        assert!(short.max_time.unwrap() <= usable.mul_f32(MAX_TIME_FRACTION));
+
+        // Half a minute gives roughly a fortieth of the clock per move
+        let long = TimeManager::new(&clock(30_000), Color::White);
+        let limit = long.time_limit.unwrap();
+        assert!(limit > Duration::from_millis(500) && limit < Duration::from_millis(1000), "{:?}", limit);
+        assert!(long.max_time.unwrap() < Duration::from_millis(15_000));
+
+        // Asking for every remaining move at once is capped all the same
+        let last_move = TimeControl { movestogo: Some(1), ..clock(30_000) };
+        let capped = TimeManager::new(&last_move, Color::White);
+        assert!(capped.allocated_time <= Duration::from_millis(30_000).mul_f32(MAX_ALLOCATION_FRACTION));
+
+        // A mate search has no time limit without a clock, and keeps the
+        // clock's when there is one
+        let mate = TimeControl { mate: Some(5), depth: None, ..TimeControl::default() };
+        let unlimited = TimeManager::new(&mate, Color::White);
+        assert!(unlimited.time_limit.is_none() && unlimited.max_time.is_none());
+        let timed = TimeManager::new(&TimeControl { mate: Some(5), ..clock(30_000) }, Color::White);
+        assert_eq!(timed.time_limit, long.time_limit);
+
+        // Depth 1 still completes on the short clock
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_startpos();
+        let mut depths = Vec::new();
+        let result = iterative_deepening(
+            &clock(50),
+            Color::White,
+            &mut TranspositionTable::with_size(1),
+            &mut OrderingTables::new(),
+            &Evaluator::new(),
+            &Tablebases::new(),
+            &pos,
+            1,
+            &Arc::new(AtomicBool::new(false)),
+            &mut |event| {
+                if let SearchEvent::Iteration(info) = event {
+                    depths.push(info.depth);
+                }
+            },
+        );
+        assert_eq!(depths.first(), Some(&1));
+        assert!(result.best_move.is_some());
+    }
 }
@@ -0,0 +1,311 @@
+//! Search engine - Library entry point for searching positions
+//!
+//! `SearchEngine` owns the state a search needs across moves (position,
+//! transposition table, history and killer tables, evaluator, tablebases
+//! and stop flag) and takes its limits as a `SearchLimits` value, so library
+//! users don't have to thread the long parameter lists of the search
+//! functions themselves.
+
+use super::alphabeta::{iterative_deepening, SearchEvent, SearchResult};
+use super::syzygy::Tablebases;
+use super::transposition::{TranspositionTable, DEFAULT_HASH_MB};
+use crate::bitboard::position::Position;
+use crate::eval::Evaluator;
+use crate::movegen::generator::generate_moves;
+use crate::movegen::legal::filter_legal_moves;
+use crate::movegen::ordering::OrderingTables;
+use crate::movegen::{Move, MoveList};
+use crate::uci::commands::TimeControl;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Limits of one search; the search stops at whichever is reached first
+#[derive(Clone, Copy, Debug)]
+pub struct SearchLimits {
+    /// Deepest iteration to search, the search's default depth when `None`
+    pub depth: Option<u32>,
+    /// Node budget, checked between iterations
+    pub nodes: Option<u64>,
+    /// Time to spend on the search, unlimited when `None`
+    pub movetime: Option<Duration>,
+    /// Number of principal variations to search (1 = best move only)
+    pub multipv: usize,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {
+            depth: None,
+            nodes: None,
+            movetime: None,
+            multipv: 1,
+        }
+    }
+}
+
+impl SearchLimits {
+    /// Search to a fixed depth with no other limit
+    pub fn depth(depth: u32) -> Self {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    /// The equivalent UCI time control
+    pub fn to_time_control(&self) -> TimeControl {
+        TimeControl {
+            depth: self.depth,
+            nodes: self.nodes,
+            movetime: self.movetime.map(|time| time.as_millis() as u64),
+            infinite: self.movetime.is_none(),
+            ..TimeControl::default()
+        }
+    }
+}
+
+/// A chess search engine with state kept between searches
+pub struct SearchEngine {
+    position: Position,
+    tt: TranspositionTable,
+    tables: OrderingTables,
+    evaluator: Evaluator,
+    tablebases: Tablebases,
+    stop_flag: Arc<AtomicBool>,
+}
+
+impl Default for SearchEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchEngine {
+    /// Create an engine with the default hash size, set to the start position
+    pub fn new() -> Self {
+        Self::with_hash_size(DEFAULT_HASH_MB)
+    }
+
+    /// Create an engine with a transposition table of `size_mb` megabytes
+    pub fn with_hash_size(size_mb: usize) -> Self {
+        crate::bitboard::magic::init_magics();
+        let mut position = Position::empty();
+        position.set_startpos();
+
+        SearchEngine {
+            position,
+            tt: TranspositionTable::with_size(size_mb),
+            tables: OrderingTables::new(),
+            evaluator: Evaluator::new(),
+            tablebases: Tablebases::new(),
+            stop_flag: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Set the position the next search starts from
+    pub fn set_position(&mut self, position: Position) {
+        self.position = position;
+    }
+
+    /// The position the next search starts from
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    /// Use the transposition table in later searches or not; without it
+    /// every node is searched in full, which makes a reference to check
+    /// the table's cutoffs against
+    pub fn set_tt_enabled(&mut self, tt_enabled: bool) {
+        self.tt.set_enabled(tt_enabled);
+    }
+
+    /// Search the current position for the side to move within `limits`
+    ///
+    /// A stop requested before the search starts is discarded. History
+    /// scores carry over from earlier searches at half weight.
+    pub fn search(&mut self, limits: &SearchLimits) -> SearchResult {
+        self.stop_flag.store(false, Ordering::Relaxed);
+        self.tables.new_search();
+        iterative_deepening(
+            &limits.to_time_control(),
+            self.position.side_to_move,
+            &mut self.tt,
+            &mut self.tables,
+            &self.evaluator,
+            &self.tablebases,
+            &self.position,
+            limits.multipv,
+            &self.stop_flag,
+            &mut |_| {},
+        )
+    }
+
+    /// Score every legal root move of the current position within `limits`
+    ///
+    /// Searches with as many principal variations as there are legal moves
+    /// and returns each move with its score and line, best first. Moves keep
+    /// the line of the deepest iteration that reached them, so a search
+    /// stopped mid-iteration may mix depths.
+    pub fn analyze(&mut self, limits: &SearchLimits) -> Vec<(Move, i32, Vec<Move>)> {
+        let color = self.position.side_to_move;
+        let mut moves = MoveList::new();
+        generate_moves(&mut moves, &self.position, color);
+        let root_moves = filter_legal_moves(&moves, &self.position, color).len();
+        if root_moves == 0 {
+            return Vec::new();
+        }
+
+        let mut lines: Vec<Option<(Move, i32, Vec<Move>)>> = vec![None; root_moves];
+        self.stop_flag.store(false, Ordering::Relaxed);
+        self.tables.new_search();
+        iterative_deepening(
+            &limits.to_time_control(),
+            color,
+            &mut self.tt,
+            &mut self.tables,
+            &self.evaluator,
+            &self.tablebases,
+            &self.position,
+            root_moves,
+            &self.stop_flag,
+            &mut |event| {
+                if let SearchEvent::Iteration(info) = event {
+                    if let Some(&mv) = info.pv.first() {
+                        lines[info.multipv - 1] = Some((mv, info.score, info.pv.clone()));
+                    }
+                }
+            },
+        );
+
+        // A line stopped mid-iteration may repeat a move reported at the
+        // previous depth under another index
+        let mut analysis: Vec<(Move, i32, Vec<Move>)> = Vec::new();
+        for (mv, score, pv) in lines.into_iter().flatten() {
+            if !analysis.iter().any(|&(seen, _, _)| seen == mv) {
+                analysis.push((mv, score, pv));
+            }
+        }
+        analysis
+    }
+
+    /// Ask a running search to stop; it returns the best move found so far
+    pub fn stop(&self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Handle to the stop flag, for stopping a search from another thread
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop_flag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::generator::is_pseudo_legal;
+    use crate::movegen::legal::is_legal_move;
+
+    /// Whether `mv` is a legal move in `position`
+    fn is_legal(position: &Position, mv: crate::movegen::Move) -> bool {
+        let color = position.side_to_move;
+        is_pseudo_legal(position, color, mv) && is_legal_move(mv, position, color)
+    }
+
+    #[test]
+    fn test_search_to_fixed_depth() {
+        let mut engine = SearchEngine::with_hash_size(1);
+        let mut position = Position::empty();
+        position.set_startpos();
+        engine.set_position(position);
+
+        let result = engine.search(&SearchLimits::depth(2));
+
+        let mv = result.best_move.expect("start position has moves");
+        assert!(is_legal(engine.position(), mv));
+        assert!(result.nodes_searched > 0);
+    }
+
+    #[test]
+    fn test_new_engine_starts_from_start_position() {
+        let mut engine = SearchEngine::with_hash_size(1);
+        let mut start = Position::empty();
+        start.set_startpos();
+        assert_eq!(engine.position().to_fen(), start.to_fen());
+
+        let mv = engine.search(&SearchLimits::depth(1)).best_move.unwrap();
+        assert!(is_legal(&start, mv));
+    }
+
+    #[test]
+    fn test_stop_ends_an_unlimited_search() {
+        let mut engine = SearchEngine::with_hash_size(1);
+        let stop = engine.stop_handle();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            stop.store(true, Ordering::Relaxed);
+        });
+
+        let result = engine.search(&SearchLimits::depth(64));
+        stopper.join().unwrap();
+
+        let mv = result.best_move.expect("a move even when stopped");
+        assert!(is_legal(engine.position(), mv));
+    }
+
+    /// Search `fen` to `depth` from a fresh engine, with or without the
+    /// transposition table
+    fn search_fen(fen: &str, depth: u32, tt_enabled: bool) -> SearchResult {
+        let mut engine = SearchEngine::with_hash_size(1);
+        let mut position = Position::empty();
+        position.set_fen(fen).unwrap();
+        engine.set_position(position);
+        engine.set_tt_enabled(tt_enabled);
+        engine.search(&SearchLimits::depth(depth))
+    }
+
+    #[test]
+    fn test_tt_agrees_with_full_search() {
+        let suite = [
+            // Mate in one on the back rank
+            "6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1",
+            // Scholar's mate
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+            // Hanging queen
+            "4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1",
+            // Promotion
+            "8/P6k/8/8/8/8/8/K7 w - - 0 1",
+            // Knight fork of king and rook
+            "r3k3/8/8/1N6/8/8/8/4K3 w - - 0 1",
+        ];
+
+        for fen in suite {
+            let full = search_fen(fen, 4, false);
+            let with_tt = search_fen(fen, 4, true);
+            assert_eq!(with_tt.best_move, full.best_move, "best move differs in {}", fen);
+            assert_eq!(with_tt.score, full.score, "score differs in {}", fen);
+        }
+    }
+
+    #[test]
+    fn test_analyze_scores_every_root_move_once() {
+        let mut engine = SearchEngine::with_hash_size(1);
+        let mut position = Position::empty();
+        position.set_fen("4k3/8/8/8/8/8/4P3/R3K3 w Q - 0 1").unwrap();
+        engine.set_position(position.clone());
+
+        let analysis = engine.analyze(&SearchLimits::depth(3));
+
+        let mut moves = MoveList::new();
+        generate_moves(&mut moves, &position, position.side_to_move);
+        let legal = filter_legal_moves(&moves, &position, position.side_to_move);
+        assert_eq!(analysis.len(), legal.len());
+        for &mv in legal.iter() {
+            assert_eq!(analysis.iter().filter(|(m, _, _)| *m == mv).count(), 1);
+        }
+        for (mv, _, pv) in &analysis {
+            assert_eq!(pv.first(), Some(mv));
+        }
+        assert!(analysis.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+}
@@ -6,20 +6,32 @@
 //! - Principal variation search (PVS)
 //! - Quiescence search
 //! - Transposition table
+//! - Syzygy tablebase probing hooks
+//! - Skill levels for weakened play
+//! - `SearchEngine`, the library entry point
 
 pub mod alphabeta;
+pub mod engine;
 pub mod negamax;
 pub mod pvs;
 pub mod quiescence;
+pub mod skill;
+pub mod syzygy;
 pub mod transposition;
 
+/// Deepest ply the search reaches; ply-indexed tables are sized by it
+pub const MAX_PLY: usize = 128;
+
 pub use self::prelude::*;
 
 pub mod prelude {
     pub use super::alphabeta::*;
+    pub use super::engine::*;
     pub use super::negamax::*;
     pub use super::pvs::*;
     pub use super::quiescence::*;
+    pub use super::skill::*;
+    pub use super::syzygy::*;
     pub use super::transposition::*;
 }
 
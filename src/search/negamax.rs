@@ -29,20 +29,14 @@ pub fn negamax(
     let mut max_score = i32::MIN;
 
     // Generate pseudo-legal moves
-    use crate::bitboard::Color as BoardColor;
     use crate::bitboard::Piece;
     use crate::movegen::generator::*;
     use crate::movegen::legal::filter_legal_moves;
 
     let color = color;
     let mut moves = crate::movegen::MoveList::new();
-    let occupied = (0..6).fold(crate::bitboard::Bitboard::EMPTY, |acc, p| {
-        acc | position.piece_bb(Piece::from_u8(p).unwrap(), BoardColor::White)
-            | position.piece_bb(Piece::from_u8(p).unwrap(), BoardColor::Black)
-    });
-    let enemies = (0..6).fold(crate::bitboard::Bitboard::EMPTY, |acc, p| {
-        acc | position.piece_bb(Piece::from_u8(p).unwrap(), color.opposite())
-    });
+    let occupied = position.all_occupancy();
+    let enemies = position.occupancy(color.opposite());
 
     generate_pawn_moves(
         &mut moves,
@@ -123,6 +117,9 @@ pub fn negamax(
 
     for &mv in legal_moves.iter() {
         let mut child_position = position.clone();
+        // Debug builds check that unmaking the move restores the position
+        #[cfg(debug_assertions)]
+        let hash_before = child_position.zobrist_hash();
         let undo = child_position.make_move(mv);
 
         // Compute enemy attacks for the child position
@@ -134,10 +131,7 @@ pub fn negamax(
         let child_opp_rooks = child_position.piece_bb(Piece::Rook, child_opp_color);
         let child_opp_queens = child_position.piece_bb(Piece::Queen, child_opp_color);
         let child_opp_king = child_position.piece_bb(Piece::King, child_opp_color);
-        let child_occupied = (0..6).fold(crate::bitboard::Bitboard::EMPTY, |acc, p| {
-            acc | child_position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::White)
-                | child_position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::Black)
-        });
+        let child_occupied = child_position.all_occupancy();
 
         for sq in child_opp_pawns.iter() {
             child_enemy_attacks |= crate::bitboard::attacks::pawn_attacks(sq, child_opp_color);
@@ -163,6 +157,14 @@ pub fn negamax(
         let score = -result.score;
 
         child_position.unmake_move(undo);
+        #[cfg(debug_assertions)]
+        assert_eq!(
+            child_position.zobrist_hash(),
+            hash_before,
+            "unmake_move({}) did not restore {}",
+            mv,
+            position.to_fen()
+        );
 
         if score > max_score {
             max_score = score;
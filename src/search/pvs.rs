@@ -43,21 +43,8 @@ pub fn pvs_search(
 
     // Generate pseudo-legal moves
     let mut moves = MoveList::new();
-    let occupied = (0..6).fold(crate::bitboard::Bitboard::EMPTY, |acc, p| {
-        acc | position.piece_bb(
-            crate::bitboard::Piece::from_u8(p).unwrap(),
-            crate::bitboard::Color::White,
-        ) | position.piece_bb(
-            crate::bitboard::Piece::from_u8(p).unwrap(),
-            crate::bitboard::Color::Black,
-        )
-    });
-    let enemies = (0..6).fold(crate::bitboard::Bitboard::EMPTY, |acc, p| {
-        acc | position.piece_bb(
-            crate::bitboard::Piece::from_u8(p).unwrap(),
-            color.opposite(),
-        )
-    });
+    let occupied = position.all_occupancy();
+    let enemies = position.occupancy(color.opposite());
 
     generate_pawn_moves(
         &mut moves,
@@ -117,6 +104,9 @@ pub fn pvs_search(
 
     for &mv in legal_moves.iter() {
         let mut child_position = position.clone();
+        // Debug builds check that unmaking the move restores the position
+        #[cfg(debug_assertions)]
+        let hash_before = child_position.zobrist_hash();
         let undo = child_position.make_move(mv);
 
         let score = if first_move {
@@ -157,6 +147,14 @@ pub fn pvs_search(
         };
 
         child_position.unmake_move(undo);
+        #[cfg(debug_assertions)]
+        assert_eq!(
+            child_position.zobrist_hash(),
+            hash_before,
+            "unmake_move({}) did not restore {}",
+            mv,
+            position.to_fen()
+        );
 
         if score > best_score {
             best_score = score;
@@ -3,128 +3,92 @@
 //! This module implements quiescence search, which extends the main search
 //! into positions with captures and checks to avoid the horizon effect.
 
-use crate::bitboard::Color;
-use crate::eval::Evaluator;
+use super::alphabeta::{SearchContext, MATE_SCORE};
+use super::MAX_PLY;
+use crate::bitboard::Piece;
+use crate::movegen::legal::is_legal_move;
+use crate::movegen::picker::MovePicker;
 use crate::movegen::Move;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::{Duration, Instant};
+
+/// Quiescence moves are not ordered by history, so it passes an empty table
+const NO_HISTORY: [[i32; 64]; 64] = [[0; 64]; 64];
 
 /// Quiescence search to evaluate quiet positions
 ///
 /// This function searches captures and other tactical moves to ensure
 /// the evaluation is stable and not affected by the horizon effect.
+/// The node is `ctx.ply` plies from the root; the deepest ply visited is
+/// recorded in `ctx.seldepth`.
 pub fn quiescence_search(
+    ctx: &mut SearchContext,
     mut alpha: i32,
     beta: i32,
-    color: Color,
-    evaluator: &Evaluator,
     position: &crate::bitboard::position::Position,
-    stop_flag: &Arc<AtomicBool>,
-    start_time: Instant,
-    time_limit: Option<Duration>,
 ) -> i32 {
-    // Stand pat: evaluate the current position
-    let stand_pat = evaluator.evaluate(position);
-    let stand_pat = if color == crate::bitboard::Color::White {
-        stand_pat
-    } else {
-        -stand_pat
-    };
+    let color = position.side_to_move;
+    let ply = ctx.ply;
+    ctx.seldepth = ctx.seldepth.max(ply);
 
-    // Beta cutoff: if standing pat is better than beta, we can stop
-    if stand_pat >= beta {
-        return beta;
-    }
+    // Stand pat: the evaluator already scores from the side to move
+    let hash = position.zobrist_hash().value();
+    let stand_pat = ctx.static_eval(position, hash, ctx.tt.probe(hash));
 
-    // Update alpha with stand pat
-    alpha = alpha.max(stand_pat);
-
-    // Generate all capture moves
-    use crate::bitboard::Piece;
-    use crate::movegen::generator::*;
-    use crate::movegen::legal::filter_legal_moves;
-
-    let mut captures = crate::movegen::MoveList::new();
-    let occupied = (0..6).fold(crate::bitboard::Bitboard::EMPTY, |acc, p| {
-        acc | position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::White)
-            | position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::Black)
-    });
-    let enemies = (0..6).fold(crate::bitboard::Bitboard::EMPTY, |acc, p| {
-        acc | position.piece_bb(Piece::from_u8(p).unwrap(), color.opposite())
-    });
-
-    // Only generate captures for each piece type
-    generate_pawn_moves(
-        &mut captures,
-        position.piece_bb(Piece::Pawn, color),
-        occupied,
-        enemies,
-        color,
-        position.en_passant,
-    );
-    generate_knight_moves(
-        &mut captures,
-        position.piece_bb(Piece::Knight, color),
-        occupied,
-        enemies,
-    );
-    generate_bishop_moves(
-        &mut captures,
-        position.piece_bb(Piece::Bishop, color),
-        occupied,
-        enemies,
-    );
-    generate_rook_moves(
-        &mut captures,
-        position.piece_bb(Piece::Rook, color),
-        occupied,
-        enemies,
-    );
-    generate_queen_moves(
-        &mut captures,
-        position.piece_bb(Piece::Queen, color),
-        occupied,
-        enemies,
-    );
-    if let Some(king_sq) = position.piece_bb(Piece::King, color).lsb() {
-        generate_king_moves(&mut captures, king_sq, occupied, enemies);
+    if ply as usize >= MAX_PLY {
+        return stand_pat;
     }
 
-    // Filter only capturing moves
-    let captures: Vec<_> = captures
-        .iter()
-        .cloned()
-        .filter(|mv| {
-            // A move is a capture if the destination square is occupied by an enemy piece
-            let to = mv.to();
-            (0..6).any(|p| {
-                position
-                    .piece_bb(Piece::from_u8(p).unwrap(), color.opposite())
-                    .is_occupied(to)
-            }) || mv.is_en_passant()
-        })
-        .collect();
-
-    for mv in captures {
-        if stop_flag.load(Ordering::Relaxed) {
-            break;
+    let in_check = position
+        .piece_bb(Piece::King, color)
+        .lsb()
+        .is_some_and(|king_sq| position.attacked_by(color.opposite()).is_occupied(king_sq));
+
+    // In check there is no standing pat: every evasion is searched, quiet
+    // ones included, and having none is checkmate. Otherwise captures and
+    // promotions, most valuable victim first.
+    let mut picker = if in_check {
+        MovePicker::new(position, color, None, [Move(0); 2], None)
+    } else {
+        // Beta cutoff: if standing pat is better than beta, we can stop
+        if stand_pat >= beta {
+            return beta;
         }
 
-        // Check time limit
-        if let Some(limit) = time_limit {
-            if start_time.elapsed() >= limit {
-                break;
-            }
+        // Update alpha with stand pat
+        alpha = alpha.max(stand_pat);
+        MovePicker::new_captures(position, color)
+    };
+    let mut has_legal_move = false;
+
+    while let Some(mv) = picker.next_move(&NO_HISTORY) {
+        if !is_legal_move(mv, position, color) {
+            continue;
+        }
+        has_legal_move = true;
+
+        if ctx.stop.should_stop() {
+            break;
         }
 
         let mut child_position = position.clone();
+        // Debug builds check that unmaking the move restores the position
+        #[cfg(debug_assertions)]
+        let hash_before = child_position.zobrist_hash();
         let undo = child_position.make_move(mv);
 
         // Recursive quiescence search
-        let score = -quiescence_search(-beta, -alpha, color.opposite(), evaluator, &child_position, stop_flag, start_time, time_limit);
+        ctx.ply += 1;
+        let score = -quiescence_search(ctx, -beta, -alpha, &child_position);
+        ctx.ply -= 1;
 
         child_position.unmake_move(undo);
+        #[cfg(debug_assertions)]
+        assert_eq!(
+            child_position.zobrist_hash(),
+            hash_before,
+            "unmake_move({}) did not restore {}",
+            mv,
+            position.to_fen()
+        );
 
         // Beta cutoff
         if score >= beta {
@@ -135,44 +99,120 @@ pub fn quiescence_search(
         alpha = alpha.max(score);
     }
 
-    alpha
-}
+    if in_check && !has_legal_move {
+        return -MATE_SCORE + ply;
+    }
 
-/// Check if a position is quiet (no captures or checks)
-pub fn is_quiet_position(
-    // Position parameters
-    _captures_available: bool,
-    _in_check: bool,
-) -> bool {
-    // A position is quiet if there are no captures and we're not in check
-    !_captures_available && !_in_check
+    alpha
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::eval::Evaluator;
+    use crate::movegen::ordering::OrderingTables;
+    use crate::search::alphabeta::StopCheck;
+    use crate::search::syzygy::Tablebases;
+    use crate::search::transposition::TranspositionTable;
+    use std::sync::atomic::AtomicBool;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_quiescence_structure() {
         let evaluator = Evaluator::new();
-        let stop_flag = Arc::new(AtomicBool::new(false));
+        let stop_flag = AtomicBool::new(false);
         let start_time = Instant::now();
 
         // Basic test that quiescence search can be called
         let dummy_position = crate::bitboard::position::Position::empty();
+        let (mut tt, mut tables, tablebases) = (TranspositionTable::with_size(1), OrderingTables::new(), Tablebases::new());
+        let stop = StopCheck::new(&stop_flag, start_time, Some(Duration::from_secs(1)));
         let score = quiescence_search(
+            &mut SearchContext::new(&mut tt, &mut tables, &evaluator, &tablebases, stop),
             i32::MIN / 2,
             i32::MAX / 2,
-            Color::White,
-            &evaluator,
             &dummy_position,
-            &stop_flag,
-            start_time,
-            Some(Duration::from_secs(1)),
         );
 
         // In a real test, we'd check the score bounds
         assert!(score >= i32::MIN / 2 && score <= i32::MAX / 2);
     }
+
+    #[test]
+    fn test_checkmate_at_horizon_scores_as_mate() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        // Smothered mate: Black is two queens up but has no legal move
+        let mut pos = Position::empty();
+        pos.set_fen("6rk/5Npp/8/8/8/7K/8/qq6 b - - 0 1").unwrap();
+        let evaluator = Evaluator::new();
+        assert!(evaluator.evaluate(&pos) > 0);
+
+        let (mut tt, mut tables, tablebases) = (TranspositionTable::with_size(1), OrderingTables::new(), Tablebases::new());
+        let stop_flag = AtomicBool::new(false);
+        let stop = StopCheck::new(&stop_flag, Instant::now(), None);
+        let mut ctx = SearchContext::new(&mut tt, &mut tables, &evaluator, &tablebases, stop);
+        ctx.ply = 3;
+        let score = quiescence_search(&mut ctx, i32::MIN / 2, i32::MAX / 2, &pos);
+        assert_eq!(score, -MATE_SCORE + 3);
+    }
+
+    #[test]
+    fn test_stand_pat_reused_from_tt() {
+        use crate::bitboard::position::Position;
+
+        crate::bitboard::magic::init_magics();
+        // A string of captures on d5 and e4
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/2n1q3/3P4/4p3/3N1B2/8/4K3 w - - 0 1").unwrap();
+        let (evaluator, tablebases, stop_flag) = (Evaluator::new(), Tablebases::new(), AtomicBool::new(false));
+        let search = |tt: &mut TranspositionTable| {
+            let mut tables = OrderingTables::new();
+            let stop = StopCheck::new(&stop_flag, Instant::now(), None);
+            let mut ctx = SearchContext::new(tt, &mut tables, &evaluator, &tablebases, stop);
+            let score = quiescence_search(&mut ctx, i32::MIN / 2, i32::MAX / 2, &pos);
+            (score, ctx.evaluations)
+        };
+
+        // Every stand pat of the second search is a table hit
+        let mut tt = TranspositionTable::with_size(1);
+        let (score, evaluations) = search(&mut tt);
+        assert!(evaluations > 1);
+        assert_eq!(search(&mut tt), (score, 0));
+        tt.set_enabled(false);
+        assert_eq!(search(&mut tt), (score, evaluations));
+    }
+
+    #[test]
+    fn test_stand_pat_keeps_deep_entry_of_colliding_key() {
+        use crate::bitboard::position::Position;
+        use crate::bitboard::Square;
+        use crate::search::transposition::{NodeType, TTEntry};
+
+        crate::bitboard::magic::init_magics();
+        // A quiet position, so the search only evaluates the root
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let (evaluator, tablebases, stop_flag) = (Evaluator::new(), Tablebases::new(), AtomicBool::new(false));
+        let mut tt = TranspositionTable::with_size(1);
+        let other = pos.zobrist_hash().value().wrapping_add(tt.size() as u64);
+        let deep = TTEntry {
+            score: 250,
+            best_move: Move::new(Square::E2, Square::E4),
+            depth: 12,
+            node_type: NodeType::Exact,
+            static_eval: None,
+        };
+        tt.store(other, deep);
+
+        let mut tables = OrderingTables::new();
+        let stop = StopCheck::new(&stop_flag, Instant::now(), None);
+        let mut ctx = SearchContext::new(&mut tt, &mut tables, &evaluator, &tablebases, stop);
+        quiescence_search(&mut ctx, i32::MIN / 2, i32::MAX / 2, &pos);
+
+        let kept = tt.probe(other).unwrap();
+        assert_eq!((kept.score, kept.depth, kept.best_move), (250, 12, deep.best_move));
+    }
+
 }
@@ -0,0 +1,115 @@
+//! Skill levels - Deliberately weaker play
+//!
+//! Below the top level the engine searches a few root lines and plays one
+//! picked at random among those close enough to the best, within a score
+//! margin that widens as the level drops. Picks come from a seeded
+//! generator, so the same seed replays the same choices.
+
+use crate::movegen::Move;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Full strength: the best move is always played
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+/// Root lines searched below full strength, to pick the move from
+pub const SKILL_LINES: usize = 4;
+
+/// Seed of the move picks at the start of a game
+pub const SKILL_SEED: u64 = 0x4D34_4B5F_534B_494C;
+
+/// Score margin, in centipawns, added for each level below the top
+const SKILL_MARGIN_PER_LEVEL: i32 = 15;
+
+/// How far behind the best line, in centipawns, a move may score and still
+/// be played at skill `level`
+pub fn skill_margin(level: u8) -> i32 {
+    MAX_SKILL_LEVEL.saturating_sub(level) as i32 * SKILL_MARGIN_PER_LEVEL
+}
+
+/// Move to play at skill `level` from the root `lines` (move and score,
+/// best first), picked with a generator seeded by `seed`
+///
+/// At `MAX_SKILL_LEVEL` this is always the first line's move; below it any
+/// line within `skill_margin(level)` of the first is equally likely.
+pub fn pick_skill_move(lines: &[(Move, i32)], level: u8, seed: u64) -> Option<Move> {
+    let &(best, best_score) = lines.first()?;
+    if level >= MAX_SKILL_LEVEL {
+        return Some(best);
+    }
+
+    let margin = skill_margin(level);
+    let candidates: Vec<Move> = lines
+        .iter()
+        .filter(|&&(_, score)| score >= best_score - margin)
+        .map(|&(mv, _)| mv)
+        .collect();
+    let mut rng = StdRng::seed_from_u64(seed);
+    Some(candidates[rng.gen_range(0..candidates.len())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::position::Position;
+    use crate::eval::Evaluator;
+    use crate::movegen::ordering::OrderingTables;
+    use crate::search::alphabeta::{iterative_deepening, SearchEvent};
+    use crate::search::syzygy::Tablebases;
+    use crate::search::transposition::{NodeType, TranspositionTable};
+    use crate::uci::commands::TimeControl;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_skill_levels_pick_among_searched_lines() {
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_startpos();
+
+        // Exact root lines of the last completed depth, best first
+        let mut lines: Vec<(Move, i32)> = Vec::new();
+        let mut record = |event: SearchEvent| {
+            if let SearchEvent::Iteration(info) = event {
+                if info.bound == NodeType::Exact {
+                    if info.multipv == 1 {
+                        lines.clear();
+                    }
+                    lines.push((info.pv[0], info.score));
+                }
+            }
+        };
+        let time_control = TimeControl {
+            depth: Some(2),
+            ..TimeControl::default()
+        };
+        iterative_deepening(
+            &time_control,
+            pos.side_to_move,
+            &mut TranspositionTable::with_size(1),
+            &mut OrderingTables::new(),
+            &Evaluator::new(),
+            &Tablebases::new(),
+            &pos,
+            SKILL_LINES,
+            &Arc::new(AtomicBool::new(false)),
+            &mut record,
+        );
+        assert_eq!(lines.len(), SKILL_LINES);
+
+        let legal = |mv: Move| {
+            let mut next = pos.clone();
+            next.make_move_checked(mv).is_ok()
+        };
+        let weakest: Vec<Move> = (0..32).map(|seed| pick_skill_move(&lines, 0, seed).unwrap()).collect();
+        assert!(weakest.iter().all(|&mv| legal(mv)));
+        assert!(weakest.iter().any(|&mv| mv != lines[0].0));
+        // The same seed picks the same move
+        assert_eq!(pick_skill_move(&lines, 0, 7), pick_skill_move(&lines, 0, 7));
+
+        for seed in 0..32 {
+            assert_eq!(pick_skill_move(&lines, MAX_SKILL_LEVEL, seed), Some(lines[0].0));
+        }
+        assert_eq!(pick_skill_move(&[], 0, 0), None);
+    }
+}
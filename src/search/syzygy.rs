@@ -0,0 +1,221 @@
+//! Syzygy tablebase probing interface
+//!
+//! This module provides the hooks the search uses to consult endgame
+//! tablebases. Probing is delegated to a `TablebaseBackend`, and is only
+//! attempted once the number of pieces on the board is within the size of
+//! the loaded tables.
+
+use crate::bitboard::position::Position;
+use crate::movegen::Move;
+
+/// Score reported for a tablebase win (below any real mate score)
+pub const TB_WIN_SCORE: i32 = 15000;
+
+/// Win/draw/loss result from the perspective of the side to move
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Wdl {
+    /// Forced loss
+    Loss,
+    /// Loss that the fifty-move rule turns into a draw
+    BlessedLoss,
+    /// Draw
+    Draw,
+    /// Win that the fifty-move rule turns into a draw
+    CursedWin,
+    /// Forced win
+    Win,
+}
+
+impl Wdl {
+    /// Convert the result into a search score for the side to move, `ply`
+    /// moves from the root; like mates, nearer wins score higher
+    pub fn to_score(self, ply: i32) -> i32 {
+        match self {
+            Wdl::Win => TB_WIN_SCORE - ply,
+            Wdl::Loss => -TB_WIN_SCORE + ply,
+            Wdl::BlessedLoss | Wdl::Draw | Wdl::CursedWin => 0,
+        }
+    }
+}
+
+/// A source of tablebase results (file decoder, stub for tests, ...)
+pub trait TablebaseBackend: Send + Sync {
+    /// Largest number of pieces (kings included) covered by the tables
+    fn max_pieces(&self) -> u32;
+
+    /// Probe the win/draw/loss value of a position
+    fn probe_wdl(&self, position: &Position) -> Option<Wdl>;
+
+    /// Pick a tablebase-correct move at the root
+    fn probe_root(&self, position: &Position) -> Option<Move>;
+}
+
+/// Loaded tablebases, consulted by the search
+#[derive(Default)]
+pub struct Tablebases {
+    backend: Option<Box<dyn TablebaseBackend>>,
+}
+
+impl Tablebases {
+    /// No tablebases loaded; every probe misses
+    pub fn new() -> Self {
+        Tablebases { backend: None }
+    }
+
+    /// Use the given backend for probing
+    pub fn with_backend(backend: Box<dyn TablebaseBackend>) -> Self {
+        Tablebases {
+            backend: Some(backend),
+        }
+    }
+
+    /// Largest piece count the loaded tables cover (0 if none are loaded)
+    pub fn max_pieces(&self) -> u32 {
+        self.backend.as_ref().map_or(0, |backend| backend.max_pieces())
+    }
+
+    /// Get the backend if the position is small enough to be covered
+    fn backend_for(&self, position: &Position) -> Option<&dyn TablebaseBackend> {
+        let backend = self.backend.as_deref()?;
+        if piece_count(position) <= backend.max_pieces() {
+            Some(backend)
+        } else {
+            None
+        }
+    }
+
+    /// Probe the win/draw/loss value, or `None` if the tables don't cover the position
+    pub fn probe_wdl(&self, position: &Position) -> Option<Wdl> {
+        self.backend_for(position)?.probe_wdl(position)
+    }
+
+    /// Probe the best root move, or `None` if the tables don't cover the position
+    pub fn probe_root(&self, position: &Position) -> Option<Move> {
+        self.backend_for(position)?.probe_root(position)
+    }
+}
+
+/// Total number of pieces on the board, kings included
+fn piece_count(position: &Position) -> u32 {
+    position.all_occupancy().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square;
+
+    /// Backend that claims every covered position is a win
+    struct StubBackend {
+        max_pieces: u32,
+        root_move: Move,
+    }
+
+    impl TablebaseBackend for StubBackend {
+        fn max_pieces(&self) -> u32 {
+            self.max_pieces
+        }
+
+        fn probe_wdl(&self, _position: &Position) -> Option<Wdl> {
+            Some(Wdl::Win)
+        }
+
+        fn probe_root(&self, _position: &Position) -> Option<Move> {
+            Some(self.root_move)
+        }
+    }
+
+    fn stub_tablebases() -> Tablebases {
+        Tablebases::with_backend(Box::new(StubBackend {
+            max_pieces: 3,
+            root_move: Move::new(Square::D1, Square::D7),
+        }))
+    }
+
+    #[test]
+    fn test_probe_within_table_size() {
+        let tablebases = stub_tablebases();
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+
+        assert_eq!(tablebases.probe_wdl(&pos), Some(Wdl::Win));
+        assert_eq!(
+            tablebases.probe_root(&pos),
+            Some(Move::new(Square::D1, Square::D7))
+        );
+    }
+
+    #[test]
+    fn test_probe_falls_back_when_too_many_pieces() {
+        let tablebases = stub_tablebases();
+        let mut pos = Position::empty();
+        pos.set_startpos();
+
+        assert_eq!(tablebases.probe_wdl(&pos), None);
+        assert_eq!(tablebases.probe_root(&pos), None);
+        assert_eq!(Tablebases::new().probe_wdl(&pos), None);
+    }
+
+    #[test]
+    fn test_search_uses_root_probe() {
+        use crate::eval::Evaluator;
+        use crate::movegen::ordering::OrderingTables;
+        use crate::search::alphabeta::iterative_deepening;
+        use crate::search::transposition::TranspositionTable;
+        use crate::uci::commands::TimeControl;
+        use std::sync::atomic::AtomicBool;
+        use std::sync::Arc;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+
+        let time_control = TimeControl {
+            depth: Some(2),
+            ..TimeControl::default()
+        };
+        let result = iterative_deepening(
+            &time_control,
+            pos.side_to_move,
+            &mut TranspositionTable::with_size(1),
+            &mut OrderingTables::new(),
+            &Evaluator::new(),
+            &stub_tablebases(),
+            &pos,
+            1,
+            &Arc::new(AtomicBool::new(false)),
+            &mut |_| {},
+        );
+
+        assert_eq!(result.best_move, Some(Move::new(Square::D1, Square::D7)));
+        assert_eq!(result.score, TB_WIN_SCORE);
+    }
+
+    #[test]
+    fn test_search_probes_every_node_below_root() {
+        use crate::eval::Evaluator;
+        use crate::movegen::ordering::OrderingTables;
+        use crate::search::alphabeta::{alpha_beta_search, SearchContext, StopCheck};
+        use crate::search::transposition::TranspositionTable;
+        use std::sync::atomic::AtomicBool;
+        use std::time::Instant;
+
+        crate::bitboard::magic::init_magics();
+        let mut pos = Position::empty();
+        pos.set_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let (evaluator, tablebases, stop_flag) = (Evaluator::new(), stub_tablebases(), AtomicBool::new(false));
+        let search = |ply| {
+            let (mut tt, mut tables) = (TranspositionTable::with_size(1), OrderingTables::new());
+            let stop = StopCheck::new(&stop_flag, Instant::now(), None);
+            let mut ctx = SearchContext::new(&mut tt, &mut tables, &evaluator, &tablebases, stop);
+            ctx.ply = ply;
+            // No previous move, as below a null move
+            alpha_beta_search(&mut ctx, 2, i32::MIN / 2, i32::MAX / 2, &pos, None, None)
+        };
+
+        let probed = search(3);
+        assert_eq!(probed.score, TB_WIN_SCORE - 3);
+        assert_eq!(probed.nodes_searched, 1);
+        assert!(search(0).nodes_searched > 1);
+    }
+}
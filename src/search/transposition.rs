@@ -1,17 +1,78 @@
 //! Transposition table for search optimization
 //!
 //! This module implements a transposition table to cache search results
-//! and avoid redundant computation.
+//! and avoid redundant computation. The table can be saved to disk and
+//! loaded back, so a long analysis survives a restart.
 
 use crate::movegen::Move;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Default transposition table size in MB
+pub const DEFAULT_HASH_MB: usize = 16;
+
+/// Marks a saved transposition table file
+const FILE_MAGIC: &[u8; 8] = b"M4KTTBL\0";
+/// Format version of saved tables; files of other versions are ignored
+const FILE_VERSION: u32 = 2;
+/// Bytes of the file header: magic, version and entry count
+const FILE_HEADER_LEN: usize = 8 + 4 + 8;
+/// Bytes per saved entry: key, score, move, depth, node type and static
+/// evaluation with its presence flag
+const FILE_ENTRY_LEN: usize = 8 + 4 + 2 + 4 + 1 + 1 + 2;
 
 /// Entry in the transposition table
+///
+/// Slots hold it packed into 64 bits (see `pack`), so the score is kept to
+/// 16 bits and the depth to 8.
 #[derive(Clone, Copy)]
 pub struct TTEntry {
     pub score: i32,
     pub best_move: Move,
     pub depth: i32,
     pub node_type: NodeType,
+    /// Static evaluation of the position, if it was computed, so a revisit
+    /// can skip the evaluator
+    pub static_eval: Option<i16>,
+}
+
+impl TTEntry {
+    /// Pack the entry into 64 bits: the move in bits 0-15, the score in
+    /// 16-31, the depth in 32-39, the node type in 40-41 and the static
+    /// evaluation in 43-58, flagged present by bit 42
+    ///
+    /// Scores beyond 16 bits and depths beyond 8 are clamped. A packed
+    /// entry is never 0, which marks an empty slot.
+    pub fn pack(&self) -> u64 {
+        let score = self.score.clamp(i16::MIN as i32, i16::MAX as i32) as i16 as u16 as u64;
+        let depth = self.depth.clamp(0, u8::MAX as i32) as u64;
+        let node_type = match self.node_type {
+            NodeType::Exact => 1,
+            NodeType::Lower => 2,
+            NodeType::Upper => 3,
+        };
+        let static_eval = self.static_eval.map_or(0, |eval| 1 | (eval as u16 as u64) << 1);
+        self.best_move.0 as u64 | score << 16 | depth << 32 | node_type << 40 | static_eval << 42
+    }
+
+    /// Unpack an entry packed by `pack`, or `None` for an empty slot
+    pub fn unpack(data: u64) -> Option<TTEntry> {
+        let node_type = match (data >> 40) & 0b11 {
+            1 => NodeType::Exact,
+            2 => NodeType::Lower,
+            3 => NodeType::Upper,
+            _ => return None,
+        };
+        Some(TTEntry {
+            score: (data >> 16) as u16 as i16 as i32,
+            best_move: Move(data as u16),
+            depth: (data >> 32) as u8 as i32,
+            node_type,
+            static_eval: ((data >> 42) & 1 == 1).then_some((data >> 43) as u16 as i16),
+        })
+    }
 }
 
 /// Type of node stored in the transposition table
@@ -22,30 +83,56 @@ pub enum NodeType {
     Upper,  // Upper bound (fail low)
 }
 
+/// Slot of the table: the full key an entry was stored under and the
+/// packed entry, 0 while the slot is empty
+#[derive(Clone, Copy)]
+struct Slot {
+    key: u64,
+    data: u64,
+}
+
+impl Slot {
+    const EMPTY: Slot = Slot { key: 0, data: 0 };
+
+    /// The entry stored for `key`, if that is the key this slot holds
+    fn entry_for(&self, key: u64) -> Option<TTEntry> {
+        if self.key == key {
+            TTEntry::unpack(self.data)
+        } else {
+            None
+        }
+    }
+}
+
 /// Transposition table using a simple hash map
+///
+/// Each slot keeps the full key of its entry, so a probe only hits for the
+/// position that was stored and saved entries can be placed again in a
+/// table of another size.
 pub struct TranspositionTable {
-    table: Vec<Option<TTEntry>>,
+    table: Vec<Slot>,
     size: usize,
+    probes: AtomicU64,
+    hits: AtomicU64,
+    enabled: bool,
 }
 
 impl TranspositionTable {
-    /// Create a new transposition table with the given size in MB
+    /// Create a new transposition table of `DEFAULT_HASH_MB` megabytes
     pub fn new() -> Self {
-        let size = 16 * 1024 * 1024; // 16MB default
-        let num_entries = size / std::mem::size_of::<Option<TTEntry>>();
-        Self {
-            table: vec![None; num_entries],
-            size: num_entries,
-        }
+        Self::with_size(DEFAULT_HASH_MB)
     }
 
     /// Create a new transposition table with custom size in MB
     pub fn with_size(size_mb: usize) -> Self {
         let size_bytes = size_mb * 1024 * 1024;
-        let num_entries = size_bytes / std::mem::size_of::<Option<TTEntry>>();
+        let num_entries = size_bytes / std::mem::size_of::<Slot>();
         Self {
-            table: vec![None; num_entries],
+            table: vec![Slot::EMPTY; num_entries],
             size: num_entries,
+            probes: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            enabled: true,
         }
     }
 
@@ -54,23 +141,138 @@ impl TranspositionTable {
         (hash as usize) % self.size
     }
 
+    /// Turn the table on or off; while off, probes miss and stores are
+    /// dropped, so a search runs as if it had no table
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Whether the table is in use
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
     /// Probe the transposition table for a position
     pub fn probe(&self, hash: u64) -> Option<TTEntry> {
+        if !self.enabled {
+            return None;
+        }
         let index = self.hash_index(hash);
-        self.table[index]
+        let entry = self.table[index].entry_for(hash);
+        self.probes.fetch_add(1, Ordering::Relaxed);
+        if entry.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+        entry
     }
 
     /// Store an entry in the transposition table
-    pub fn store(&mut self, hash: u64, entry: TTEntry) {
+    ///
+    /// An entry without a static evaluation keeps the one already stored
+    /// for the same position.
+    pub fn store(&mut self, hash: u64, mut entry: TTEntry) {
+        if !self.enabled {
+            return;
+        }
         let index = self.hash_index(hash);
-        self.table[index] = Some(entry);
+        if let Some(old) = self.table[index].entry_for(hash) {
+            entry.static_eval = entry.static_eval.or(old.static_eval);
+        }
+        self.table[index] = Slot { key: hash, data: entry.pack() };
+    }
+
+    /// Store the static evaluation of a position
+    ///
+    /// An entry already stored for the position keeps its bound and move.
+    /// An empty slot gets a depth 0 entry with no move and a lower bound
+    /// below any score, which never cuts a search off; a slot holding
+    /// another position is left alone.
+    pub fn store_static_eval(&mut self, hash: u64, eval: i16) {
+        if !self.enabled {
+            return;
+        }
+        let index = self.hash_index(hash);
+        let slot = &self.table[index];
+        if slot.key != hash && TTEntry::unpack(slot.data).is_some() {
+            return;
+        }
+        let entry = slot.entry_for(hash).unwrap_or(TTEntry {
+            score: i16::MIN as i32,
+            best_move: Move(0),
+            depth: 0,
+            node_type: NodeType::Lower,
+            static_eval: None,
+        });
+        let entry = TTEntry { static_eval: Some(eval), ..entry };
+        self.table[index] = Slot { key: hash, data: entry.pack() };
+    }
+
+    /// Make the table `size_mb` megabytes and empty it; the slots are only
+    /// reallocated when the entry count changes
+    pub fn resize(&mut self, size_mb: usize) {
+        let num_entries = size_mb * 1024 * 1024 / std::mem::size_of::<Slot>();
+        if num_entries == self.size {
+            self.clear();
+        } else {
+            *self = Self {
+                enabled: self.enabled,
+                ..Self::with_size(size_mb)
+            };
+        }
     }
 
     /// Clear the transposition table
     pub fn clear(&mut self) {
-        for entry in &mut self.table {
-            *entry = None;
+        self.table.fill(Slot::EMPTY);
+        self.probes.store(0, Ordering::Relaxed);
+        self.hits.store(0, Ordering::Relaxed);
+    }
+
+    /// Save the stored entries to `path` in a compact binary format
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let entries: Vec<(u64, TTEntry)> = self
+            .table
+            .iter()
+            .filter_map(|slot| Some((slot.key, TTEntry::unpack(slot.data)?)))
+            .collect();
+        let mut bytes = Vec::with_capacity(FILE_HEADER_LEN + entries.len() * FILE_ENTRY_LEN);
+        bytes.extend_from_slice(FILE_MAGIC);
+        bytes.extend_from_slice(&FILE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+        for (key, entry) in entries {
+            bytes.extend_from_slice(&key.to_le_bytes());
+            bytes.extend_from_slice(&entry.score.to_le_bytes());
+            bytes.extend_from_slice(&entry.best_move.0.to_le_bytes());
+            bytes.extend_from_slice(&entry.depth.to_le_bytes());
+            bytes.push(match entry.node_type {
+                NodeType::Exact => 0,
+                NodeType::Lower => 1,
+                NodeType::Upper => 2,
+            });
+            bytes.push(entry.static_eval.is_some() as u8);
+            bytes.extend_from_slice(&entry.static_eval.unwrap_or(0).to_le_bytes());
+        }
+
+        let mut file = fs::File::create(path)?;
+        file.write_all(&bytes)?;
+        file.flush()
+    }
+
+    /// Store the entries saved by `save` at `path` into this table
+    ///
+    /// Entries are placed by their keys, so the table may differ in size
+    /// from the one that was saved. A file that isn't a saved table of this
+    /// version, or is truncated, leaves the table untouched and returns
+    /// `Ok(false)`; errors reading the file are returned as such.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        let bytes = fs::read(path)?;
+        let Some(entries) = parse_saved_table(&bytes) else {
+            return Ok(false);
+        };
+        for (key, entry) in entries {
+            self.store(key, entry);
         }
+        Ok(true)
     }
 
     /// Get the number of entries in the table
@@ -80,26 +282,75 @@ impl TranspositionTable {
 
     /// Get statistics about table usage
     pub fn stats(&self) -> TTStats {
-        let mut used = 0;
-        for entry in &self.table {
-            if entry.is_some() {
-                used += 1;
-            }
-        }
+        let used = self.table.iter().filter(|slot| slot.data != 0).count();
 
         TTStats {
             total_entries: self.size,
             used_entries: used,
             usage_percent: (used as f32 / self.size as f32) * 100.0,
+            probes: self.probes.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
         }
     }
 }
 
+/// Read the entries of a file written by `TranspositionTable::save`, or
+/// `None` if it has the wrong format, version or length
+fn parse_saved_table(bytes: &[u8]) -> Option<Vec<(u64, TTEntry)>> {
+    let (header, body) = bytes.split_at_checked(FILE_HEADER_LEN)?;
+    if &header[..8] != FILE_MAGIC || u32::from_le_bytes(header[8..12].try_into().ok()?) != FILE_VERSION {
+        return None;
+    }
+    let count = u64::from_le_bytes(header[12..20].try_into().ok()?);
+    if body.len() as u64 != count.checked_mul(FILE_ENTRY_LEN as u64)? {
+        return None;
+    }
+
+    body.chunks_exact(FILE_ENTRY_LEN)
+        .map(|chunk| {
+            Some((
+                u64::from_le_bytes(chunk[0..8].try_into().ok()?),
+                TTEntry {
+                    score: i32::from_le_bytes(chunk[8..12].try_into().ok()?),
+                    best_move: Move(u16::from_le_bytes(chunk[12..14].try_into().ok()?)),
+                    depth: i32::from_le_bytes(chunk[14..18].try_into().ok()?),
+                    node_type: match chunk[18] {
+                        0 => NodeType::Exact,
+                        1 => NodeType::Lower,
+                        2 => NodeType::Upper,
+                        _ => return None,
+                    },
+                    static_eval: match chunk[19] {
+                        0 => None,
+                        1 => Some(i16::from_le_bytes(chunk[20..22].try_into().ok()?)),
+                        _ => return None,
+                    },
+                },
+            ))
+        })
+        .collect()
+}
+
 /// Statistics about transposition table usage
 pub struct TTStats {
     pub total_entries: usize,
     pub used_entries: usize,
     pub usage_percent: f32,
+    /// Number of probes since the table was created or cleared
+    pub probes: u64,
+    /// Number of probes that found an entry
+    pub hits: u64,
+}
+
+impl TTStats {
+    /// Percentage of probes that found an entry
+    pub fn hit_rate_percent(&self) -> f32 {
+        if self.probes == 0 {
+            0.0
+        } else {
+            (self.hits as f32 / self.probes as f32) * 100.0
+        }
+    }
 }
 
 #[cfg(test)]
@@ -122,6 +373,7 @@ mod tests {
             best_move: Move::new(Square::E2, Square::E4),
             depth: 5,
             node_type: NodeType::Exact,
+            static_eval: None,
         };
 
         tt.store(hash, entry);
@@ -142,6 +394,7 @@ mod tests {
             best_move: Move::new(Square::E2, Square::E4),
             depth: 5,
             node_type: NodeType::Exact,
+            static_eval: None,
         };
 
         tt.store(hash, entry);
@@ -150,4 +403,175 @@ mod tests {
         tt.clear();
         assert!(tt.probe(hash).is_none());
     }
+
+    #[test]
+    fn test_tt_hit_stats() {
+        let mut tt = TranspositionTable::with_size(1);
+        let entry = TTEntry {
+            score: 0,
+            best_move: Move::new(Square::E2, Square::E4),
+            depth: 1,
+            node_type: NodeType::Exact,
+            static_eval: None,
+        };
+
+        tt.store(1, entry);
+        assert!(tt.probe(1).is_some());
+        assert!(tt.probe(2).is_none());
+
+        let stats = tt.stats();
+        assert_eq!((stats.probes, stats.hits), (2, 1));
+        assert_eq!(stats.hit_rate_percent(), 50.0);
+    }
+
+    #[test]
+    fn test_tt_rejects_colliding_key() {
+        let mut tt = TranspositionTable::with_size(1);
+        let entry = |best_move| TTEntry {
+            score: 0,
+            best_move,
+            depth: 1,
+            node_type: NodeType::Exact,
+            static_eval: None,
+        };
+        // Both keys map to the same slot
+        let hash = 0x0123_4567_89ab_cdef;
+        let colliding = hash + tt.size() as u64;
+        assert_eq!(tt.hash_index(hash), tt.hash_index(colliding));
+
+        tt.store(hash, entry(Move::new(Square::E2, Square::E4)));
+        assert!(tt.probe(colliding).is_none());
+
+        // The newer position takes the slot, and only it can read it back
+        tt.store(colliding, entry(Move::new(Square::D2, Square::D4)));
+        assert!(tt.probe(hash).is_none());
+        assert_eq!(tt.probe(colliding).map(|e| e.best_move), Some(Move::new(Square::D2, Square::D4)));
+    }
+
+    #[test]
+    fn test_tt_keys_sharing_slot_and_high_bits_do_not_alias() {
+        let mut tt = TranspositionTable::with_size(1);
+        // Same slot and same top 16 bits: only the full key tells them apart
+        let hash = 0xbeef_0000_0000_0001;
+        let colliding = hash + tt.size() as u64;
+        assert_eq!(hash >> 48, colliding >> 48);
+        assert_eq!(tt.hash_index(hash), tt.hash_index(colliding));
+
+        tt.store(
+            hash,
+            TTEntry {
+                score: 10,
+                best_move: Move::new(Square::G1, Square::F3),
+                depth: 2,
+                node_type: NodeType::Lower,
+                static_eval: None,
+            },
+        );
+        assert!(tt.probe(colliding).is_none());
+        assert!(tt.probe(hash).is_some());
+    }
+
+    #[test]
+    fn test_tt_entry_pack_round_trip() {
+        let entries = [
+            (-29_990, Move::new(Square::E2, Square::E4), 0, NodeType::Exact, None),
+            (-1, Move::promotion(Square::B7, Square::A8, crate::bitboard::Piece::Knight), 12, NodeType::Upper, Some(-310)),
+            (29_999, Move(u16::MAX), 255, NodeType::Lower, Some(i16::MIN)),
+            (0, Move(0), 1, NodeType::Lower, Some(i16::MAX)),
+        ];
+        for (score, best_move, depth, node_type, static_eval) in entries {
+            let entry = TTEntry { score, best_move, depth, node_type, static_eval };
+            let packed = entry.pack();
+            assert_ne!(packed, 0);
+            let unpacked = TTEntry::unpack(packed).unwrap();
+            assert_eq!(unpacked.score, score);
+            assert_eq!(unpacked.best_move, best_move);
+            assert_eq!(unpacked.depth, depth);
+            assert!(unpacked.node_type == node_type);
+            assert_eq!(unpacked.static_eval, static_eval);
+        }
+        assert!(TTEntry::unpack(0).is_none());
+
+        // Out of range scores and depths are clamped
+        let wide = TTEntry { score: -100_000, best_move: Move(0), depth: 300, node_type: NodeType::Exact, static_eval: None };
+        let unpacked = TTEntry::unpack(wide.pack()).unwrap();
+        assert_eq!((unpacked.score, unpacked.depth), (i16::MIN as i32, 255));
+
+        assert_eq!(std::mem::size_of::<Slot>(), 16);
+    }
+
+    #[test]
+    fn test_tt_save_load_round_trip() {
+        let path = std::env::temp_dir().join(format!("m4k_tt_round_trip_{}.bin", std::process::id()));
+        let mut tt = TranspositionTable::with_size(1);
+        let entries = [
+            (0x1234_5678_9abc_def0, 35, Move::new(Square::E2, Square::E4), 7, NodeType::Exact, Some(20)),
+            (42, -120, Move::new(Square::G8, Square::F6), 3, NodeType::Upper, None),
+            (u64::MAX, 29_990, Move::promotion(Square::B7, Square::B8, crate::bitboard::Piece::Knight), 12, NodeType::Lower, Some(-310)),
+        ];
+        for (hash, score, best_move, depth, node_type, static_eval) in entries {
+            tt.store(hash, TTEntry { score, best_move, depth, node_type, static_eval });
+        }
+        tt.save(&path).unwrap();
+
+        tt.clear();
+        assert!(tt.probe(42).is_none());
+        assert!(tt.load(&path).unwrap());
+
+        // A table of another size places the entries by their keys
+        let mut larger = TranspositionTable::with_size(2);
+        assert!(larger.load(&path).unwrap());
+
+        for table in [&tt, &larger] {
+            for (hash, score, best_move, depth, node_type, static_eval) in entries {
+                let entry = table.probe(hash).unwrap();
+                assert_eq!((entry.score, entry.best_move, entry.depth), (score, best_move, depth));
+                assert_eq!(entry.static_eval, static_eval);
+                assert!(entry.node_type == node_type);
+            }
+        }
+
+        // Anything else is ignored and leaves the table as it was
+        fs::write(&path, b"not a transposition table").unwrap();
+        assert!(!tt.load(&path).unwrap());
+        assert_eq!(tt.probe(42).map(|entry| entry.score), Some(-120));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tt_static_eval() {
+        let mut tt = TranspositionTable::with_size(1);
+        let entry = TTEntry {
+            score: 15,
+            best_move: Move::new(Square::E2, Square::E4),
+            depth: 2,
+            node_type: NodeType::Exact,
+            static_eval: Some(-37),
+        };
+        tt.store(7, entry);
+        assert_eq!(tt.probe(7).unwrap().static_eval, Some(-37));
+
+        // A deeper result without an evaluation keeps the stored one
+        tt.store(7, TTEntry { depth: 6, static_eval: None, ..entry });
+        assert_eq!(tt.probe(7).map(|entry| (entry.depth, entry.static_eval)), Some((6, Some(-37))));
+
+        // An evaluation stored on its own keeps the entry's bound and move,
+        // or makes an entry that bounds nothing
+        tt.store_static_eval(7, 12);
+        let kept = tt.probe(7).unwrap();
+        assert_eq!((kept.score, kept.best_move, kept.depth, kept.static_eval), (15, entry.best_move, 6, Some(12)));
+        tt.store_static_eval(8, 40);
+        let fresh = tt.probe(8).unwrap();
+        assert!(fresh.node_type == NodeType::Lower && fresh.score == i16::MIN as i32);
+        assert_eq!((fresh.depth, fresh.static_eval), (0, Some(40)));
+
+        // Another position in the same slot doesn't inherit it, and its
+        // evaluation alone doesn't replace the stored search result
+        let other = 7 + tt.size() as u64;
+        tt.store_static_eval(other, 90);
+        assert!(tt.probe(other).is_none());
+        assert_eq!(tt.probe(7).unwrap().depth, 6);
+        tt.store(other, TTEntry { static_eval: None, ..entry });
+        assert_eq!(tt.probe(other).unwrap().static_eval, None);
+    }
 }
@@ -0,0 +1,119 @@
+//! Bench - Fixed-depth search over a built-in position suite
+//!
+//! `bench` searches every position of an embedded suite to a fixed depth
+//! with a fresh transposition table and reports the total node count. The
+//! search has no time limit, so the node total only depends on the engine's
+//! search and evaluation: it serves as a fingerprint of the engine version
+//! and as a quick speed check (nodes per second).
+
+use crate::bitboard::position::Position;
+use crate::eval::Evaluator;
+use crate::movegen::ordering::OrderingTables;
+use crate::search::alphabeta::iterative_deepening;
+use crate::search::syzygy::Tablebases;
+use crate::search::transposition::TranspositionTable;
+use crate::uci::commands::TimeControl;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Search depth used when `bench` is given no depth
+pub const BENCH_DEPTH: u32 = 4;
+
+/// Transposition table size for each bench position, in megabytes
+const BENCH_HASH_MB: usize = 1;
+
+/// Positions searched by `bench`: openings, middlegames and endgames
+pub const BENCH_FENS: [&str; 20] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 10",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+    "4rrk1/pp1n3p/3q2pQ/2p1pb2/2PP4/2P3N1/P2B2PP/4RRK1 b - - 7 19",
+    "rq3rk1/ppp2ppp/1bnpb3/3N2B1/3NP3/7P/PPPQ1PP1/2KR3R w - - 7 14",
+    "r1bq1r1k/1pp1n1pp/1p1p4/4p2Q/4Pp2/1BNP4/PPP2PPP/3R1RK1 w - - 2 14",
+    "r3r1k1/2p2ppp/p1p1bn2/8/1q2P3/2NPQN2/PPP3PP/R4RK1 b - - 2 15",
+    "r1bbk1nr/pp3p1p/2n5/1N4p1/2Np1B2/8/PPP2PPP/2KR1B1R w kq - 0 13",
+    "r1bq1rk1/ppp1nppp/4n3/3p3Q/3P4/1BP1B3/PP1N2PP/R4RK1 w - - 1 16",
+    "4r1k1/r1q2ppp/ppp2n2/4P3/5Rb1/1N1BQ3/PPP3PP/R5K1 w - - 1 17",
+    "2rqkb1r/ppp2p2/2npb1p1/1N1Nn2p/2P1PP2/8/PP2B1PP/R1BQK2R b KQ - 0 11",
+    "r1bq1r1k/b1p1npp1/p2p3p/1p6/3PP3/1B2NN2/PP3PPP/R2Q1RK1 w - - 1 16",
+    "3r1rk1/p5pp/bpp1pp2/8/q1PP1P2/b3P3/P2NQRPP/1R2B1K1 b - - 6 22",
+    "r1q2rk1/2p1bppp/2Pp4/p6b/Q1PNp3/4B3/PP1R1PPP/2K4R w - - 2 18",
+    "4k2r/1pb2ppp/1p2p3/1R1p4/3P4/2r1PN2/P4PPP/1R4K1 b - - 3 22",
+    "3q2k1/pb3p1p/4pbp1/2r5/PpN2N2/1P2P2P/5PP1/Q2R2K1 b - - 4 26",
+    "6k1/6p1/6Pp/ppp5/3pn2P/1P3K2/1PP2P2/3N4 b - - 0 1",
+    "3b4/5kp1/1p1p1p1p/pP1PpP1P/P1P1P3/3KN3/8/8 w - - 0 1",
+    "8/8/8/8/5kp1/P7/8/1K1N4 w - - 0 1",
+    "8/3k4/8/8/8/4B3/4KB2/2B5 w - - 0 1",
+];
+
+/// Totals of a bench run
+#[derive(Clone, Copy, Debug)]
+pub struct BenchResult {
+    /// Nodes searched over all positions
+    pub nodes: u64,
+    /// Wall-clock time of the whole run
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    /// Nodes searched per second
+    pub fn nps(&self) -> u64 {
+        let millis = self.elapsed.as_millis() as u64;
+        (self.nodes * 1000).checked_div(millis).unwrap_or(0)
+    }
+}
+
+/// Search every bench position to `depth` and total the nodes
+pub fn run_bench(depth: u32) -> BenchResult {
+    crate::bitboard::magic::init_magics();
+    let evaluator = Evaluator::new();
+    let tablebases = Tablebases::new();
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let time_control = TimeControl {
+        depth: Some(depth),
+        infinite: true,
+        ..TimeControl::default()
+    };
+
+    let start_time = Instant::now();
+    let mut nodes = 0;
+    for fen in BENCH_FENS {
+        let mut position = Position::empty();
+        position.set_fen(fen).expect("bench FEN is valid");
+        let mut tt = TranspositionTable::with_size(BENCH_HASH_MB);
+
+        let result = iterative_deepening(
+            &time_control,
+            position.side_to_move,
+            &mut tt,
+            &mut OrderingTables::new(),
+            &evaluator,
+            &tablebases,
+            &position,
+            1,
+            &stop_flag,
+            &mut |_| {},
+        );
+        nodes += result.nodes_searched;
+    }
+
+    BenchResult {
+        nodes,
+        elapsed: start_time.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_node_count_is_stable() {
+        let first = run_bench(2);
+        let second = run_bench(2);
+
+        assert!(first.nodes > BENCH_FENS.len() as u64);
+        assert_eq!(first.nodes, second.nodes);
+    }
+}
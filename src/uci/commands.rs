@@ -10,11 +10,22 @@ pub fn parse_command(command: &str) -> Option<UciCommand> {
 
     match parts.get(0) {
         Some(&"uci") => Some(UciCommand::Uci),
+        Some(&"debug") => match parts.get(1) {
+            Some(&"on") => Some(UciCommand::Debug(true)),
+            Some(&"off") => Some(UciCommand::Debug(false)),
+            _ => None,
+        },
         Some(&"isready") => Some(UciCommand::IsReady),
         Some(&"ucinewgame") => Some(UciCommand::NewGame),
         Some(&"position") => parse_position_command(&parts[1..]),
         Some(&"go") => parse_go_command(&parts[1..]),
+        Some(&"setoption") => parse_setoption_command(&parts[1..]),
         Some(&"stop") => Some(UciCommand::Stop),
+        Some(&"bench") => match parts.get(1) {
+            Some(depth) => depth.parse().ok().map(|depth| UciCommand::Bench { depth: Some(depth) }),
+            None => Some(UciCommand::Bench { depth: None }),
+        },
+        Some(&"register") => Some(UciCommand::Register),
         Some(&"quit") => Some(UciCommand::Quit),
         _ => None,
     }
@@ -23,12 +34,29 @@ pub fn parse_command(command: &str) -> Option<UciCommand> {
 /// UCI command types
 pub enum UciCommand {
     Uci,
+    /// `debug on` / `debug off`
+    Debug(bool),
     IsReady,
     NewGame,
     Position { fen: String, moves: Vec<Move> },
     Go { time_control: TimeControl },
+    /// `setoption name <name> [value <value>]`
+    SetOption {
+        /// Option name as sent by the GUI
+        name: String,
+        /// Option value, empty when omitted (e.g. for buttons)
+        value: String,
+    },
     Stop,
     Quit,
+    /// `register later` / `register name <x> code <y>`: the engine needs no
+    /// registration, so the arguments are ignored
+    Register,
+    /// `bench [depth]`: search the built-in bench positions
+    Bench {
+        /// Search depth, `BENCH_DEPTH` when omitted
+        depth: Option<u32>,
+    },
 }
 
 /// Time control for search
@@ -43,6 +71,8 @@ pub struct TimeControl {
     pub nodes: Option<u64>,
     pub movetime: Option<u64>,
     pub infinite: bool,
+    /// `go mate <moves>`: look for a forced mate in at most this many moves
+    pub mate: Option<u32>,
 }
 
 impl Default for TimeControl {
@@ -57,6 +87,7 @@ impl Default for TimeControl {
             nodes: None,
             movetime: None,
             infinite: false,
+            mate: None,
         }
     }
 }
@@ -126,12 +157,8 @@ fn parse_uci_move(mv_str: &str) -> Option<Move> {
 
     // Check for promotion
     if mv_str.len() == 5 {
-        let promo_char = bytes[4] as char;
-        let promo_piece = match promo_char.to_ascii_lowercase() {
-            'q' => Piece::Queen,
-            'r' => Piece::Rook,
-            'b' => Piece::Bishop,
-            'n' => Piece::Knight,
+        let promo_piece = match Piece::from_char(bytes[4] as char) {
+            Some((piece @ (Piece::Queen | Piece::Rook | Piece::Bishop | Piece::Knight), _)) => piece,
             _ => return None,
         };
         Some(Move::promotion(from, to, promo_piece))
@@ -140,6 +167,23 @@ fn parse_uci_move(mv_str: &str) -> Option<Move> {
     }
 }
 
+/// Parse setoption command (`setoption name <name> [value <value>]`)
+///
+/// Any option name is accepted here; the engine decides which ones it knows.
+fn parse_setoption_command(args: &[&str]) -> Option<UciCommand> {
+    if args.first() != Some(&"name") {
+        return None;
+    }
+    let value_idx = args.iter().position(|&x| x == "value").unwrap_or(args.len());
+    let name = args[1..value_idx].join(" ");
+    if name.is_empty() {
+        return None;
+    }
+    let value = args.get(value_idx + 1..).map_or(String::new(), |rest| rest.join(" "));
+
+    Some(UciCommand::SetOption { name, value })
+}
+
 /// Parse go command
 fn parse_go_command(args: &[&str]) -> Option<UciCommand> {
     let mut time_control = TimeControl {
@@ -152,6 +196,7 @@ fn parse_go_command(args: &[&str]) -> Option<UciCommand> {
         nodes: None,
         movetime: None,
         infinite: false,
+        mate: None,
     };
 
     let mut i = 0;
@@ -225,6 +270,10 @@ fn parse_go_command(args: &[&str]) -> Option<UciCommand> {
                     i += 1;
                 }
             }
+            "mate" => {
+                time_control.mate = args.get(i + 1).and_then(|moves| moves.parse().ok()).filter(|&moves| moves > 0);
+                i += 2;
+            }
             _ => {
                 i += 1;
             }
@@ -247,4 +296,68 @@ mod tests {
         ));
         assert!(matches!(parse_command("quit"), Some(UciCommand::Quit)));
     }
+
+    #[test]
+    fn test_parse_go_mate() {
+        match parse_command("go mate 3") {
+            Some(UciCommand::Go { time_control }) => assert_eq!(time_control.mate, Some(3)),
+            _ => panic!("expected go"),
+        }
+        match parse_command("go mate 0") {
+            Some(UciCommand::Go { time_control }) => assert_eq!(time_control.mate, None),
+            _ => panic!("expected go"),
+        }
+    }
+
+    #[test]
+    fn test_parse_bench() {
+        assert!(matches!(parse_command("bench"), Some(UciCommand::Bench { depth: None })));
+        assert!(matches!(parse_command("bench 3"), Some(UciCommand::Bench { depth: Some(3) })));
+        assert!(parse_command("bench deep").is_none());
+    }
+
+    #[test]
+    fn test_parse_register() {
+        assert!(matches!(parse_command("register later"), Some(UciCommand::Register)));
+        assert!(matches!(
+            parse_command("register name Jane Doe code 1234"),
+            Some(UciCommand::Register)
+        ));
+    }
+
+    #[test]
+    fn test_parse_debug() {
+        assert!(matches!(parse_command("debug on"), Some(UciCommand::Debug(true))));
+        assert!(matches!(parse_command("debug off"), Some(UciCommand::Debug(false))));
+        assert!(parse_command("debug").is_none());
+    }
+
+    #[test]
+    fn test_parse_syzygy_path() {
+        match parse_command("setoption name SyzygyPath value /home/pi/syzygy") {
+            Some(UciCommand::SetOption { name, value }) => {
+                assert_eq!(name, "SyzygyPath");
+                assert_eq!(value, "/home/pi/syzygy");
+            }
+            _ => panic!("expected SyzygyPath option"),
+        }
+    }
+
+    #[test]
+    fn test_parse_known_and_unknown_options() {
+        match parse_command("setoption name Hash value 64") {
+            Some(UciCommand::SetOption { name, value }) => {
+                assert_eq!(name, "Hash");
+                assert_eq!(value, "64");
+            }
+            _ => panic!("expected Hash option"),
+        }
+        match parse_command("setoption name Foo Bar") {
+            Some(UciCommand::SetOption { name, value }) => {
+                assert_eq!(name, "Foo Bar");
+                assert!(value.is_empty());
+            }
+            _ => panic!("expected Foo option"),
+        }
+    }
 }
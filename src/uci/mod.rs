@@ -3,12 +3,14 @@
 //! This module implements the UCI protocol for communication with chess GUIs
 //! and other engines, handling commands and responses.
 
+pub mod bench;
 pub mod commands;
 pub mod protocol;
 
 pub use self::prelude::*;
 
 pub mod prelude {
+    pub use super::bench::*;
     pub use super::commands::*;
     pub use super::protocol::*;
 }
@@ -4,29 +4,156 @@
 //! parsing commands from GUIs and sending responses.
 
 use crate::bitboard::position::Position;
-use crate::bitboard::{Bitboard, Color, Piece};
+use crate::bitboard::Piece;
+use crate::eval::material::piece_value;
 use crate::eval::Evaluator;
+use crate::movegen::ordering::OrderingTables;
 use crate::movegen::Move;
-use crate::search::alphabeta::{iterative_deepening, SearchResult};
-use crate::search::transposition::TranspositionTable;
+use crate::search::alphabeta::{is_mate_within, iterative_deepening, mate_in_moves, RootStatus, SearchEvent, SearchInfo, SearchResult};
+use crate::search::skill::{pick_skill_move, MAX_SKILL_LEVEL, SKILL_LINES, SKILL_SEED};
+use crate::search::syzygy::Tablebases;
+use crate::search::transposition::{NodeType, TranspositionTable, DEFAULT_HASH_MB};
+use crate::uci::bench::{run_bench, BENCH_DEPTH};
 use crate::uci::commands::{parse_command, TimeControl, UciCommand};
+use crate::utils::zobrist::ZOBRIST_KEYS;
+use once_cell::sync::Lazy;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::io::{self, BufRead, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
-use std::sync::Arc;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::thread;
-use std::time::{Duration, Instant};
+
+/// Largest transposition table size accepted via `setoption name Hash`
+pub const MAX_HASH_MB: usize = 1024;
+/// Largest number of lines accepted via `setoption name MultiPV`
+pub const MAX_MULTIPV: usize = 64;
+/// Options setting the value of a piece in centipawns, for material-odds
+/// and style experiments
+pub const PIECE_VALUE_OPTIONS: [(&str, Piece); 5] = [
+    ("PawnValue", Piece::Pawn),
+    ("KnightValue", Piece::Knight),
+    ("BishopValue", Piece::Bishop),
+    ("RookValue", Piece::Rook),
+    ("QueenValue", Piece::Queen),
+];
+/// Largest piece value accepted via the piece value options
+pub const MAX_PIECE_VALUE: i32 = 5000;
+
+/// Engine name and version for `id name`, from the crate manifest
+pub fn engine_id_name() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME").to_uppercase(), env!("CARGO_PKG_VERSION"))
+}
+
+/// Authors for `id author`, from the crate manifest without their emails
+pub fn engine_id_author() -> String {
+    env!("CARGO_PKG_AUTHORS")
+        .split(':')
+        .map(|author| author.split('<').next().unwrap_or(author).trim())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Write the reply to `uci`: engine identification, supported options, `uciok`
+pub fn write_uci_handshake<W: Write>(out: &mut W) -> io::Result<()> {
+    writeln!(out, "id name {}", engine_id_name())?;
+    writeln!(out, "id author {}", engine_id_author())?;
+    writeln!(
+        out,
+        "option name Hash type spin default {} min 1 max {}",
+        DEFAULT_HASH_MB, MAX_HASH_MB
+    )?;
+    writeln!(out, "option name MultiPV type spin default 1 min 1 max {}", MAX_MULTIPV)?;
+    // SyzygyPath is not advertised until a tablebase file decoder exists
+    writeln!(
+        out,
+        "option name Skill Level type spin default {} min 0 max {}",
+        MAX_SKILL_LEVEL, MAX_SKILL_LEVEL
+    )?;
+    for (name, piece) in PIECE_VALUE_OPTIONS {
+        writeln!(
+            out,
+            "option name {} type spin default {} min 0 max {}",
+            name,
+            piece_value(piece),
+            MAX_PIECE_VALUE
+        )?;
+    }
+    writeln!(out, "uciok")?;
+    out.flush()
+}
+
+/// Format a search progress report as a UCI `info` line
+pub fn format_info_line(info: &SearchInfo) -> String {
+    let millis = info.elapsed.as_millis() as u64;
+    let nps = (info.nodes * 1000).checked_div(millis).unwrap_or(0);
+    let pv: Vec<String> = info.pv.iter().map(|mv| mv.to_string()).collect();
+    let bound = match info.bound {
+        NodeType::Exact => "",
+        NodeType::Lower => " lowerbound",
+        NodeType::Upper => " upperbound",
+    };
+    let score = match mate_in_moves(info.score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", info.score),
+    };
+    format!(
+        "info depth {} seldepth {} multipv {} score {}{} nodes {} nps {} time {} pv {}",
+        info.depth,
+        info.seldepth,
+        info.multipv,
+        score,
+        bound,
+        info.nodes,
+        nps,
+        millis,
+        pv.join(" ")
+    )
+}
+
+/// Block until the lazily built tables, the magic attacks and the Zobrist
+/// keys, are ready; no tablebase files are loaded yet
+fn wait_until_ready() {
+    crate::bitboard::magic::init_magics();
+    Lazy::force(&ZOBRIST_KEYS);
+}
 
 /// UCI Engine state
 pub struct UciEngine {
     position: Position,
+    /// Whether the GUI has sent a `position` since startup or `ucinewgame`
+    position_received: bool,
     evaluator: Evaluator,
-    tt: TranspositionTable,
+    /// Kept across searches of a game; the search thread holds the lock
+    tt: Arc<Mutex<TranspositionTable>>,
+    /// Killers, history and countermoves, kept across searches of a game
+    tables: Arc<Mutex<OrderingTables>>,
+    tablebases: Arc<Tablebases>,
+    hash_size_mb: usize,
+    multipv: usize,
+    skill_level: u8,
+    /// Seeds the move pick of each search below full skill
+    skill_rng: StdRng,
+    debug: bool,
     time_control: TimeControl,
     stop_flag: Arc<AtomicBool>,
     search_handle: Option<thread::JoinHandle<()>>,
-    result_sender: mpsc::Sender<SearchResult>,
-    result_receiver: mpsc::Receiver<SearchResult>,
+    event_sender: mpsc::Sender<EngineEvent>,
+    event_receiver: mpsc::Receiver<EngineEvent>,
+}
+
+/// Events the main loop waits on: GUI input and finished searches
+enum EngineEvent {
+    /// A line read from the GUI
+    Input(String),
+    /// The GUI closed its end of the input
+    InputClosed,
+    /// A line from the search thread (`info` output) to write to the GUI
+    Output(String),
+    /// The search thread finished; `None` if it failed without a result
+    SearchFinished(Option<SearchResult>),
 }
 
 impl UciEngine {
@@ -42,120 +169,258 @@ impl UciEngine {
 
         UciEngine {
             position,
+            position_received: false,
             evaluator: Evaluator::new(),
-            tt: TranspositionTable::new(),
+            tt: Arc::new(Mutex::new(TranspositionTable::with_size(DEFAULT_HASH_MB))),
+            tables: Arc::new(Mutex::new(OrderingTables::new())),
+            tablebases: Arc::new(Tablebases::new()),
+            hash_size_mb: DEFAULT_HASH_MB,
+            multipv: 1,
+            skill_level: MAX_SKILL_LEVEL,
+            skill_rng: StdRng::seed_from_u64(SKILL_SEED),
+            debug: false,
             time_control: TimeControl::default(),
             stop_flag: Arc::new(AtomicBool::new(false)),
             search_handle: None,
-            result_sender: tx,
-            result_receiver: rx,
+            event_sender: tx,
+            event_receiver: rx,
         }
     }
 
-    /// Run the main UCI loop
+    /// Run the main UCI loop on stdin/stdout
     pub fn run(&mut self) {
-        let stdin = io::stdin();
-        let mut stdout = io::stdout();
-
-        println!("id name M4K Chess Engine");
-        println!("id author Your Name");
-        println!("uciok");
-        stdout.flush().unwrap();
-
-        for line in stdin.lock().lines() {
-            let line = match line {
-                Ok(l) => l,
-                Err(_) => continue,
-            };
-            let command = line.trim();
+        self.run_with(io::BufReader::new(io::stdin()), &mut io::stdout());
+    }
 
-            if command.is_empty() {
-                continue;
+    /// Run the UCI loop on the given input and output
+    ///
+    /// Input is read on a separate thread so that a finished search's
+    /// `bestmove` is written as soon as it is available, not when the next
+    /// command arrives, and so that `stop`, `isready` and `quit` are handled
+    /// while a search is running.
+    pub fn run_with<R, W>(&mut self, input: R, out: &mut W)
+    where
+        R: BufRead + Send + 'static,
+        W: Write,
+    {
+        let input_sender = self.event_sender.clone();
+        thread::spawn(move || {
+            for line in input.lines() {
+                let Ok(line) = line else { break };
+                if input_sender.send(EngineEvent::Input(line)).is_err() {
+                    return;
+                }
             }
+            let _ = input_sender.send(EngineEvent::InputClosed);
+        });
 
-            if let Some(response) = self.handle_command(command) {
-                println!("{}", response);
-                stdout.flush().unwrap();
-            }
+        while let Ok(event) = self.event_receiver.recv() {
+            match event {
+                EngineEvent::Input(line) => {
+                    let command = line.trim();
+                    if command.is_empty() {
+                        continue;
+                    }
 
-            // Check for search result immediately after handling command
-            if let Ok(result) = self.result_receiver.try_recv() {
-                if let Some(mv) = result.best_move {
-                    println!("bestmove {}", mv);
-                    stdout.flush().unwrap();
-                } else if let Some(fallback_mv) = self.generate_emergency_move() {
-                    println!("bestmove {}", fallback_mv);
-                    stdout.flush().unwrap();
-                } else {
-                    println!("bestmove 0000"); // Absolute fallback
-                    stdout.flush().unwrap();
+                    if let Some(response) = self.handle_command(command) {
+                        let _ = writeln!(out, "{}", response);
+                        let _ = out.flush();
+                    }
+
+                    if command == "quit" {
+                        self.finish_search(out);
+                        break;
+                    }
+                }
+                EngineEvent::SearchFinished(result) => {
+                    self.search_handle = None;
+                    self.write_bestmove(out, result.as_ref());
+                }
+                EngineEvent::Output(line) => {
+                    let _ = writeln!(out, "{}", line);
+                    let _ = out.flush();
+                }
+                EngineEvent::InputClosed => {
+                    self.finish_search(out);
+                    break;
                 }
             }
+        }
+    }
 
-            if command == "quit" {
-                if let Some(handle) = self.search_handle.take() {
-                    self.stop_flag.store(true, Ordering::Relaxed);
-                    let _ = handle.join();
+    /// Stop a running search and report its move before shutting down
+    fn finish_search<W: Write>(&mut self, out: &mut W) {
+        self.stop_search();
+        while let Ok(event) = self.event_receiver.try_recv() {
+            match event {
+                EngineEvent::SearchFinished(result) => self.write_bestmove(out, result.as_ref()),
+                EngineEvent::Output(line) => {
+                    let _ = writeln!(out, "{}", line);
                 }
-                break;
+                EngineEvent::Input(_) | EngineEvent::InputClosed => {}
             }
         }
     }
 
+    /// Write the `bestmove` line for a finished search
+    ///
+    /// A position without legal moves gets its final score reported and
+    /// `bestmove 0000`, as there is no move to make. A search that failed to
+    /// produce a move falls back to an emergency move instead, and only
+    /// reports `0000` if none can be found; an `info string` says which
+    /// case occurred.
+    fn write_bestmove<W: Write>(&self, out: &mut W, result: Option<&SearchResult>) {
+        let final_score = match result.map(|result| result.status) {
+            Some(RootStatus::Checkmate) => Some("mate 0"),
+            Some(RootStatus::Stalemate) => Some("cp 0"),
+            Some(RootStatus::Normal) | None => None,
+        };
+        if let Some(score) = final_score {
+            let _ = writeln!(out, "info depth 0 score {}", score);
+            let _ = writeln!(out, "info string no legal moves, the game is over");
+            let _ = writeln!(out, "bestmove 0000");
+            let _ = out.flush();
+            return;
+        }
+
+        match result.and_then(|result| result.best_move) {
+            Some(mv) => {
+                let _ = writeln!(out, "bestmove {}", mv);
+            }
+            None => match self.generate_emergency_move() {
+                Some(mv) => {
+                    let _ = writeln!(out, "info string search ended without a move, playing an emergency move");
+                    let _ = writeln!(out, "bestmove {}", mv);
+                }
+                None => {
+                    let _ = writeln!(out, "info string search ended without a move and no emergency move was found");
+                    let _ = writeln!(out, "bestmove 0000");
+                }
+            },
+        }
+        let _ = out.flush();
+    }
+
+    /// Rule under which the side to move, if not ahead, could claim a draw
+    fn claimable_draw(&self) -> Option<&'static str> {
+        if self.evaluator.evaluate(&self.position) > 0 {
+            return None;
+        }
+        if self.position.halfmove_clock >= 100 {
+            Some("fifty-move rule")
+        } else if self.position.is_repetition() {
+            Some("repetition")
+        } else {
+            None
+        }
+    }
+
     /// Handle a UCI command
     fn handle_command(&mut self, command: &str) -> Option<String> {
         match parse_command(command) {
-            Some(UciCommand::Uci) => Some("uciok".to_string()),
-            Some(UciCommand::IsReady) => Some("readyok".to_string()),
-            Some(UciCommand::NewGame) => {
-                self.position.set_startpos();
+            Some(UciCommand::Uci) => {
+                let mut handshake = Vec::new();
+                write_uci_handshake(&mut handshake).ok()?;
+                Some(String::from_utf8_lossy(&handshake).trim_end().to_string())
+            }
+            Some(UciCommand::Debug(on)) => {
+                self.debug = on;
+                None
+            }
+            Some(UciCommand::IsReady) => {
+                wait_until_ready();
                 Some("readyok".to_string())
             }
+            // The GUI follows up with isready, which is what gets readyok
+            Some(UciCommand::NewGame) => {
+                self.new_game();
+                wait_until_ready();
+                None
+            }
             Some(UciCommand::Position { fen, moves }) => {
                 self.handle_position(fen, moves);
                 None
             }
             Some(UciCommand::Go { time_control }) => {
+                // Without a `position` command, search the start position
+                let mut notes = Vec::new();
+                if !self.position_received {
+                    self.position.set_startpos();
+                    notes.push("info string no position set, searching the start position".to_string());
+                }
+                if let Some(rule) = self.claimable_draw() {
+                    notes.push(format!("info string draw claimable by {}", rule));
+                }
                 self.time_control = time_control;
                 self.start_search();
-                None
+                (!notes.is_empty()).then(|| notes.join("\n"))
             }
+            Some(UciCommand::SetOption { name, value }) => self.handle_setoption(&name, value),
             Some(UciCommand::Stop) => {
+                // The search thread reports its result through the event channel
                 self.stop_flag.store(true, Ordering::Relaxed);
-                // Wait a short time for search to complete and send result
-                thread::sleep(Duration::from_millis(10));
-                if let Ok(result) = self.result_receiver.try_recv() {
-                    if let Some(mv) = result.best_move {
-                        return Some(format!("bestmove {}", mv));
-                    } else if let Some(fallback_mv) = self.generate_emergency_move() {
-                        return Some(format!("bestmove {}", fallback_mv));
-                    }
-                }
                 None
             }
-            Some(UciCommand::Quit) => {
-                // Wait for any pending search results before quitting
-                if let Some(handle) = self.search_handle.take() {
-                    let _ = handle.join();
-                }
-                // Try to get the final result
-                if let Ok(result) = self.result_receiver.try_recv() {
-                    if let Some(mv) = result.best_move {
-                        println!("bestmove {}", mv);
-                        io::stdout().flush().unwrap();
-                    } else if let Some(fallback_mv) = self.generate_emergency_move() {
-                        println!("bestmove {}", fallback_mv);
-                        io::stdout().flush().unwrap();
-                    }
-                }
-                None
+            // The main loop stops the search and exits after this
+            Some(UciCommand::Quit) => None,
+            Some(UciCommand::Register) => None,
+            Some(UciCommand::Bench { depth }) => {
+                let result = run_bench(depth.unwrap_or(BENCH_DEPTH));
+                Some(format!(
+                    "info string bench nodes {} time {} nps {}",
+                    result.nodes,
+                    result.elapsed.as_millis(),
+                    result.nps()
+                ))
             }
             None => Some(format!("info string Unknown command: {}", command.split_whitespace().next().unwrap_or(""))),
         }
     }
 
+    /// Handle setoption for the options the engine knows; others are ignored
+    fn handle_setoption(&mut self, name: &str, value: String) -> Option<String> {
+        if name.eq_ignore_ascii_case("Hash") {
+            if let Ok(size_mb) = value.parse::<usize>() {
+                self.hash_size_mb = size_mb.clamp(1, MAX_HASH_MB);
+                self.stop_search();
+                self.tt.lock().unwrap_or_else(PoisonError::into_inner).resize(self.hash_size_mb);
+            }
+            None
+        } else if name.eq_ignore_ascii_case("MultiPV") {
+            if let Ok(lines) = value.parse::<usize>() {
+                self.multipv = lines.clamp(1, MAX_MULTIPV);
+            }
+            None
+        } else if name.eq_ignore_ascii_case("Skill Level") {
+            if let Ok(level) = value.parse::<i64>() {
+                self.skill_level = level.clamp(0, MAX_SKILL_LEVEL as i64) as u8;
+            }
+            None
+        } else if name.eq_ignore_ascii_case("SyzygyPath") {
+            // No file decoder is built in yet, so a path loads nothing
+            if value.is_empty() || value == "<empty>" {
+                None
+            } else {
+                Some("info string Syzygy tablebases are not supported by this build".to_string())
+            }
+        } else if let Some((_, piece)) = PIECE_VALUE_OPTIONS
+            .iter()
+            .find(|(option, _)| name.eq_ignore_ascii_case(option))
+        {
+            if let Ok(centipawns) = value.parse::<i32>() {
+                let mut params = *self.evaluator.params();
+                params.piece_values[*piece as usize] = centipawns.clamp(0, MAX_PIECE_VALUE);
+                self.evaluator = Evaluator::with_params(params);
+            }
+            None
+        } else {
+            None
+        }
+    }
+
     /// Handle position command
     fn handle_position(&mut self, fen: String, moves: Vec<Move>) {
+        self.position_received = true;
         if fen == "startpos" {
             self.position.set_startpos();
         } else {
@@ -166,41 +431,119 @@ impl UciEngine {
             }
         }
 
-        // Apply moves
+        // Play the moves up to the first illegal one; the rest no longer
+        // fit the board
         for mv in moves {
-            self.position.make_move(mv);
+            if let Err(e) = self.position.make_move_checked(mv) {
+                eprintln!("info string Ignoring the rest of the moves: {}", e);
+                break;
+            }
+        }
+    }
+
+    /// Forget everything learned in the previous game: the transposition
+    /// table, killers, history, countermoves and repetition history; the
+    /// table starts the game at the configured Hash size and the skill
+    /// picks start over from their seed
+    fn new_game(&mut self) {
+        self.stop_search();
+        self.skill_rng = StdRng::seed_from_u64(SKILL_SEED);
+        self.tt.lock().unwrap_or_else(PoisonError::into_inner).resize(self.hash_size_mb);
+        *self.tables.lock().unwrap_or_else(PoisonError::into_inner) = OrderingTables::new();
+        self.position.set_startpos();
+        self.position_received = false;
+    }
+
+    /// Stop a running search and wait for it; its `bestmove` is still
+    /// written by the main loop
+    fn stop_search(&mut self) {
+        if let Some(handle) = self.search_handle.take() {
+            self.stop_flag.store(true, Ordering::Relaxed);
+            let _ = handle.join();
         }
     }
 
     /// Start search in a separate thread
     fn start_search(&mut self) {
+        // The new search needs the tables the old one holds
+        self.stop_search();
         self.stop_flag.store(false, Ordering::Relaxed);
         let stop_flag_clone = Arc::clone(&self.stop_flag);
         let position = self.position.clone();
-        let evaluator = Evaluator::new();
-        let mut tt = TranspositionTable::new();
+        let evaluator = Evaluator::with_params(*self.evaluator.params());
+        let tt = Arc::clone(&self.tt);
+        let tables = Arc::clone(&self.tables);
         let time_control = self.time_control.clone();
-        let sender = self.result_sender.clone();
+        let tablebases = Arc::clone(&self.tablebases);
+        let sender = self.event_sender.clone();
+        let debug = self.debug;
+        let multipv = self.multipv;
+        // Below full skill a few more lines are searched to pick from; only
+        // the requested ones are reported
+        let skill_level = self.skill_level;
+        let search_lines = if skill_level < MAX_SKILL_LEVEL { multipv.max(SKILL_LINES) } else { multipv };
+        let skill_seed: u64 = self.skill_rng.gen();
 
         self.search_handle = Some(thread::spawn(move || {
-            // Set a hard timeout to prevent infinite searches (5 minutes max)
-            let search_timeout = if time_control.infinite {
-                Duration::from_secs(300) // 5 minutes for infinite searches
-            } else {
-                Duration::from_secs(60) // 1 minute max for normal searches
+            // Output goes through the main loop, to the same writer as `bestmove`
+            let print = |line: String| {
+                let _ = sender.send(EngineEvent::Output(line));
             };
+            // Exact root lines of the last completed depth, best first
+            let mut skill_lines: Vec<(Move, i32)> = Vec::new();
+            let mut report = |event: SearchEvent| match event {
+                SearchEvent::Iteration(info) => {
+                    if info.bound == NodeType::Exact && !info.pv.is_empty() {
+                        if info.multipv == 1 {
+                            skill_lines.clear();
+                        }
+                        skill_lines.push((info.pv[0], info.score));
+                    }
+                    if info.multipv > multipv {
+                        return;
+                    }
+                    print(format_info_line(info));
+                    if debug && info.multipv == 1 {
+                        print(format!("info string depth {} nodes {}", info.depth, info.nodes));
+                    }
+                }
+                SearchEvent::CurrMove { depth, mv, number } => {
+                    print(format!("info depth {} currmove {} currmovenumber {}", depth, mv, number));
+                }
+            };
+            // A panicking search still has to answer the GUI, so it is
+            // caught and reported as a search without a result. The tables
+            // it held stay usable: they are only caches.
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut tt = tt.lock().unwrap_or_else(PoisonError::into_inner);
+                let mut tables = tables.lock().unwrap_or_else(PoisonError::into_inner);
+                tables.new_search();
+                let result = iterative_deepening(&time_control, position.side_to_move, &mut tt, &mut tables, &evaluator, &tablebases, &position, search_lines, &stop_flag_clone, &mut report);
+                if let Some(moves) = time_control.mate {
+                    if !is_mate_within(result.score, moves) {
+                        print(format!("info string no mate in {} found", moves));
+                    }
+                }
+                if debug {
+                    let stats = tt.stats();
+                    print(format!(
+                        "info string tt hits {} of {} probes ({:.1}%)",
+                        stats.hits,
+                        stats.probes,
+                        stats.hit_rate_percent()
+                    ));
+                }
+                result
+            }))
+            .ok()
+            .map(|mut result| {
+                if let Some(mv) = pick_skill_move(&skill_lines, skill_level, skill_seed) {
+                    result.best_move = Some(mv);
+                }
+                result
+            });
 
-            let start_time = Instant::now();
-
-            // Run search with timeout
-            let result = iterative_deepening(&time_control, position.side_to_move, &mut tt, &evaluator, &position, &stop_flag_clone);
-
-            // If search took too long, force stop flag
-            if start_time.elapsed() > search_timeout {
-                // This shouldn't happen with proper time management, but just in case
-            }
-
-            let _ = sender.send(result);
+            let _ = sender.send(EngineEvent::SearchFinished(result));
         }));
     }
 
@@ -212,13 +555,8 @@ impl UciEngine {
 
         let mut moves = MoveList::new();
         let color = self.position.side_to_move;
-        let occupied = (0..6).fold(Bitboard::EMPTY, |acc, p| {
-            acc | self.position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::White)
-                | self.position.piece_bb(Piece::from_u8(p).unwrap(), crate::bitboard::Color::Black)
-        });
-        let enemies = (0..6).fold(crate::bitboard::Bitboard::EMPTY, |acc, p| {
-            acc | self.position.piece_bb(Piece::from_u8(p).unwrap(), color.opposite())
-        });
+        let occupied = self.position.all_occupancy();
+        let enemies = self.position.occupancy(color.opposite());
 
         generate_pawn_moves(
             &mut moves,
@@ -264,6 +602,8 @@ impl UciEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bitboard::Color;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_uci_engine_creation() {
@@ -271,4 +611,424 @@ mod tests {
         // Test that engine can be created
         assert!(true);
     }
+
+    /// Reader that blocks until the test sends the next line
+    struct ChannelReader {
+        lines: mpsc::Receiver<String>,
+        pending: Vec<u8>,
+    }
+
+    impl io::Read for ChannelReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.pending.is_empty() {
+                match self.lines.recv() {
+                    Ok(line) => self.pending = format!("{}\n", line).into_bytes(),
+                    Err(_) => return Ok(0),
+                }
+            }
+            let n = buf.len().min(self.pending.len());
+            buf[..n].copy_from_slice(&self.pending[..n]);
+            self.pending.drain(..n);
+            Ok(n)
+        }
+    }
+
+    /// Output buffer shared between the engine thread and the test
+    #[derive(Clone)]
+    struct SharedOutput(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Run an engine on its own thread, fed line by line through the sender
+    fn spawn_engine() -> (mpsc::Sender<String>, SharedOutput, thread::JoinHandle<()>) {
+        let (line_tx, line_rx) = mpsc::channel();
+        let output = SharedOutput(Arc::new(std::sync::Mutex::new(Vec::new())));
+        let reader = io::BufReader::new(ChannelReader {
+            lines: line_rx,
+            pending: Vec::new(),
+        });
+
+        let mut engine_output = output.clone();
+        let engine_thread = thread::spawn(move || {
+            UciEngine::new().run_with(reader, &mut engine_output);
+        });
+        (line_tx, output, engine_thread)
+    }
+
+    /// Wait (up to 30 seconds) for a `bestmove` line and return the output so far
+    fn wait_for_bestmove(output: &SharedOutput) -> String {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        loop {
+            let text = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+            if text.lines().any(|line| line.starts_with("bestmove")) || Instant::now() > deadline {
+                return text;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_bestmove_sent_without_further_input() {
+        let (line_tx, output, engine_thread) = spawn_engine();
+
+        line_tx.send("position startpos".to_string()).unwrap();
+        line_tx.send("go depth 1".to_string()).unwrap();
+
+        // No more input is sent until the bestmove shows up
+        let text = wait_for_bestmove(&output);
+        assert_eq!(text.lines().filter(|line| line.starts_with("bestmove")).count(), 1);
+
+        line_tx.send("quit".to_string()).unwrap();
+        engine_thread.join().unwrap();
+        let text = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(text.lines().filter(|line| line.starts_with("bestmove")).count(), 1);
+    }
+
+    #[test]
+    fn test_search_info_goes_to_the_engine_output() {
+        let (line_tx, output, engine_thread) = spawn_engine();
+
+        line_tx.send("position startpos".to_string()).unwrap();
+        line_tx.send("go depth 2".to_string()).unwrap();
+        let text = wait_for_bestmove(&output);
+        let mut info_depths: Vec<&str> = text
+            .lines()
+            .take_while(|line| !line.starts_with("bestmove"))
+            .filter_map(|line| line.strip_prefix("info depth "))
+            .map(|rest| rest.split(' ').next().unwrap())
+            .collect();
+        // A failed aspiration window repeats a depth
+        info_depths.dedup();
+        assert_eq!(info_depths, ["1", "2"]);
+
+        output.0.lock().unwrap().clear();
+        line_tx.send("go mate 1 depth 2".to_string()).unwrap();
+        let text = wait_for_bestmove(&output);
+        assert!(text.lines().any(|line| line == "info string no mate in 1 found"), "{}", text);
+
+        line_tx.send("quit".to_string()).unwrap();
+        engine_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_stop_and_isready_handled_mid_search() {
+        let (line_tx, output, engine_thread) = spawn_engine();
+        let text = || String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        let wait_for_readyok = |count: usize| {
+            let deadline = Instant::now() + Duration::from_secs(30);
+            while text().lines().filter(|line| *line == "readyok").count() < count {
+                assert!(Instant::now() < deadline, "no readyok: {}", text());
+                thread::sleep(Duration::from_millis(10));
+            }
+        };
+
+        // Let the engine finish starting up before timing anything
+        line_tx.send("isready".to_string()).unwrap();
+        wait_for_readyok(1);
+
+        line_tx.send("position startpos".to_string()).unwrap();
+        line_tx.send("go infinite".to_string()).unwrap();
+        thread::sleep(Duration::from_millis(200));
+
+        // The search is still running, yet isready gets its answer
+        let asked = Instant::now();
+        line_tx.send("isready".to_string()).unwrap();
+        wait_for_readyok(2);
+        assert!(asked.elapsed() < Duration::from_secs(2), "isready took {:?}", asked.elapsed());
+        assert!(!text().lines().any(|line| line.starts_with("bestmove")));
+
+        let stopped = Instant::now();
+        line_tx.send("stop".to_string()).unwrap();
+        let text = wait_for_bestmove(&output);
+        assert!(stopped.elapsed() < Duration::from_secs(2), "stop took {:?}", stopped.elapsed());
+        assert_eq!(text.lines().filter(|line| line.starts_with("bestmove")).count(), 1);
+
+        line_tx.send("quit".to_string()).unwrap();
+        engine_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_go_without_position_searches_start_position() {
+        use crate::movegen::generator::is_pseudo_legal;
+        use crate::movegen::legal::is_legal_move;
+
+        let (line_tx, output, engine_thread) = spawn_engine();
+        line_tx.send("go depth 1".to_string()).unwrap();
+        let text = wait_for_bestmove(&output);
+        line_tx.send("quit".to_string()).unwrap();
+        engine_thread.join().unwrap();
+
+        assert!(text.lines().any(|line| line.starts_with("info string no position set")));
+        let bestmove = text
+            .lines()
+            .find_map(|line| line.strip_prefix("bestmove "))
+            .expect("a bestmove line");
+        let mut start = Position::empty();
+        start.set_startpos();
+        let parsed = parse_command(&format!("position startpos moves {}", bestmove));
+        let Some(UciCommand::Position { moves, .. }) = parsed else {
+            panic!("unparsable bestmove {}", bestmove);
+        };
+        assert_eq!(moves.len(), 1);
+        assert!(is_pseudo_legal(&start, Color::White, moves[0]));
+        assert!(is_legal_move(moves[0], &start, Color::White));
+    }
+
+    #[test]
+    fn test_isready_waits_for_initialization() {
+        let mut engine = UciEngine::new();
+        assert_eq!(engine.handle_command("isready"), Some("readyok".to_string()));
+        assert!(crate::bitboard::magic::magics_initialized());
+        assert!(Lazy::get(&ZOBRIST_KEYS).is_some());
+    }
+
+    #[test]
+    fn test_skill_level_option() {
+        use crate::movegen::legal::is_legal_move;
+
+        let mut engine = UciEngine::new();
+        engine.handle_command("setoption name Skill Level value 50");
+        assert_eq!(engine.skill_level, MAX_SKILL_LEVEL);
+        engine.handle_command("setoption name skill level value -3");
+        assert_eq!(engine.skill_level, 0);
+
+        let (line_tx, output, engine_thread) = spawn_engine();
+        line_tx.send("setoption name Skill Level value 0".to_string()).unwrap();
+        line_tx.send("position startpos".to_string()).unwrap();
+        line_tx.send("go depth 2".to_string()).unwrap();
+        let text = wait_for_bestmove(&output);
+        line_tx.send("quit".to_string()).unwrap();
+        engine_thread.join().unwrap();
+
+        // The extra lines searched to pick from are not reported
+        assert!(text.lines().all(|line| !line.contains(" multipv 2 ")));
+        let bestmove = text
+            .lines()
+            .find_map(|line| line.strip_prefix("bestmove "))
+            .expect("a bestmove line");
+        let Some(UciCommand::Position { moves, .. }) = parse_command(&format!("position startpos moves {}", bestmove)) else {
+            panic!("unparsable bestmove {}", bestmove);
+        };
+        let mut start = Position::empty();
+        start.set_startpos();
+        assert!(is_legal_move(moves[0], &start, Color::White));
+    }
+
+    #[test]
+    fn test_go_reports_claimable_fifty_move_draw() {
+        let (line_tx, output, engine_thread) = spawn_engine();
+        line_tx.send("position fen 4k3/8/8/8/8/8/8/4K3 w - - 100 80".to_string()).unwrap();
+        line_tx.send("go depth 1".to_string()).unwrap();
+        let text = wait_for_bestmove(&output);
+        line_tx.send("quit".to_string()).unwrap();
+        engine_thread.join().unwrap();
+
+        assert!(text.lines().any(|line| line == "info string draw claimable by fifty-move rule"));
+        assert!(text.lines().any(|line| line.starts_with("bestmove ") && line != "bestmove 0000"));
+    }
+
+    #[test]
+    fn test_ucinewgame_applies_configured_hash_size() {
+        let mut engine = UciEngine::new();
+        engine.handle_command("position startpos moves e2e4");
+        engine.handle_command("setoption name Hash value 64");
+        // A table of another size, as left behind by an earlier game
+        *engine.tt.lock().unwrap() = TranspositionTable::with_size(1);
+
+        engine.handle_command("ucinewgame");
+        assert_eq!(engine.tt.lock().unwrap().size(), TranspositionTable::with_size(64).size());
+    }
+
+    #[test]
+    fn test_ucinewgame_resets_search_state() {
+        use crate::bitboard::Square;
+        use crate::search::transposition::TTEntry;
+
+        let mut engine = UciEngine::new();
+        engine.handle_command("position startpos moves e2e4 e7e5");
+        let hash = engine.position.zobrist_hash().value();
+        let mv = Move::new(Square::G1, Square::F3);
+        engine.tt.lock().unwrap().store(
+            hash,
+            TTEntry {
+                score: 30,
+                best_move: mv,
+                depth: 4,
+                node_type: NodeType::Exact,
+                static_eval: None,
+            },
+        );
+        engine.tables.lock().unwrap().record_cutoff(mv, None, 4, 4);
+        assert!(engine.tt.lock().unwrap().probe(hash).is_some());
+
+        assert_eq!(engine.handle_command("ucinewgame"), None);
+
+        assert!(engine.tt.lock().unwrap().probe(hash).is_none());
+        let tables = engine.tables.lock().unwrap();
+        assert!(tables.history.iter().flatten().all(|&score| score == 0));
+        assert!(tables.killers.iter().flatten().all(|&killer| killer == Move(0)));
+        assert!(engine.position.hash_history.is_empty());
+    }
+
+    #[test]
+    fn test_go_in_checkmate_reports_mate_zero() {
+        let (line_tx, output, engine_thread) = spawn_engine();
+        line_tx
+            .send("position fen rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3".to_string())
+            .unwrap();
+        line_tx.send("go depth 3".to_string()).unwrap();
+        let text = wait_for_bestmove(&output);
+        line_tx.send("quit".to_string()).unwrap();
+        engine_thread.join().unwrap();
+
+        assert!(text.lines().any(|line| line == "info depth 0 score mate 0"), "{}", text);
+        assert!(text.lines().any(|line| line == "info string no legal moves, the game is over"), "{}", text);
+        assert!(text.lines().any(|line| line == "bestmove 0000"), "{}", text);
+    }
+
+    #[test]
+    fn test_search_without_result_plays_emergency_move() {
+        let engine = UciEngine::new();
+        let mut out = Vec::new();
+        engine.write_bestmove(&mut out, None);
+        let text = String::from_utf8(out).unwrap();
+
+        assert!(text.lines().any(|line| line.starts_with("info string search ended without a move")), "{}", text);
+        let bestmove = text.lines().find_map(|line| line.strip_prefix("bestmove ")).unwrap();
+        assert_ne!(bestmove, "0000");
+        assert!(parse_command(&format!("position startpos moves {}", bestmove)).is_some());
+    }
+
+    #[test]
+    fn test_uci_handshake_lists_options() {
+        let mut out = Vec::new();
+        write_uci_handshake(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert_eq!(text.lines().next(), Some(format!("id name M4K {}", env!("CARGO_PKG_VERSION")).as_str()));
+        assert_eq!(text.lines().nth(1), Some("id author Blake Park"));
+        assert!(text
+            .lines()
+            .any(|line| line == "option name Hash type spin default 16 min 1 max 1024"));
+        assert!(text
+            .lines()
+            .any(|line| line == "option name KnightValue type spin default 320 min 0 max 5000"));
+        assert!(!text.contains("SyzygyPath"));
+        assert_eq!(text.lines().last(), Some("uciok"));
+    }
+
+    #[test]
+    fn test_syzygy_path_reports_unsupported() {
+        let mut engine = UciEngine::new();
+        assert_eq!(
+            engine.handle_command("setoption name SyzygyPath value /home/pi/syzygy"),
+            Some("info string Syzygy tablebases are not supported by this build".to_string())
+        );
+        assert_eq!(engine.handle_command("setoption name SyzygyPath value <empty>"), None);
+    }
+
+    #[test]
+    fn test_position_rejects_king_capture() {
+        let mut engine = UciEngine::new();
+        // Black is left in check, so the first move takes the king
+        let fen = "4k3/8/8/8/8/8/8/4RK2 w - - 0 1";
+        engine.handle_command(&format!("position fen {} moves e1e8 e8d7", fen));
+
+        assert_eq!(engine.position.to_fen(), fen);
+        assert!(engine.position.piece_bb(Piece::King, Color::Black).is_occupied(crate::bitboard::Square::E8));
+    }
+
+    #[test]
+    fn test_position_keeps_the_legal_prefix() {
+        let mut engine = UciEngine::new();
+        engine.handle_command("position startpos moves e2e4 e7e5 e1e3 g1f3");
+
+        let mut expected = UciEngine::new();
+        expected.handle_command("position startpos moves e2e4 e7e5");
+        assert_eq!(engine.position.to_fen(), expected.position.to_fen());
+    }
+
+    #[test]
+    fn test_setoption_piece_value() {
+        let mut engine = UciEngine::new();
+        engine.handle_command("setoption name KnightValue value 400");
+        engine.handle_command("setoption name queenvalue value 99999");
+        engine.handle_command("setoption name PawnValue value lots");
+
+        let params = engine.evaluator.params();
+        assert_eq!(params.piece_value(Piece::Knight), 400);
+        assert_eq!(params.piece_value(Piece::Queen), MAX_PIECE_VALUE);
+        assert_eq!(params.piece_value(Piece::Pawn), piece_value(Piece::Pawn));
+    }
+
+    #[test]
+    fn test_info_line_reports_mate_scores() {
+        use crate::search::alphabeta::MATE_SCORE;
+
+        let line = |score| {
+            format_info_line(&SearchInfo {
+                depth: 3,
+                seldepth: 3,
+                multipv: 1,
+                score,
+                bound: NodeType::Exact,
+                window: (i32::MIN / 2, i32::MAX / 2),
+                nodes: 100,
+                pv: vec![Move::new(crate::bitboard::Square::D5, crate::bitboard::Square::F6)],
+                elapsed: Duration::from_millis(10),
+            })
+        };
+        assert!(line(MATE_SCORE - 3).contains(" score mate 2 "));
+        assert!(line(-MATE_SCORE + 2).contains(" score mate -1 "));
+        assert!(line(35).contains(" score cp 35 "));
+    }
+
+    #[test]
+    fn test_fail_high_reports_lowerbound() {
+        crate::bitboard::magic::init_magics();
+        // Depth 1 sees only the material; depth 2 finds that the pawn fork
+        // wins a knight, far above the aspiration window around the depth 1 score
+        let mut pos = Position::empty();
+        pos.set_fen("7k/8/3n1n2/8/4P3/8/8/K7 w - - 0 1").unwrap();
+        let time_control = TimeControl {
+            depth: Some(2),
+            infinite: true,
+            ..TimeControl::default()
+        };
+
+        let mut lines = Vec::new();
+        iterative_deepening(
+            &time_control,
+            Color::White,
+            &mut TranspositionTable::with_size(1),
+            &mut OrderingTables::new(),
+            &Evaluator::new(),
+            &Tablebases::new(),
+            &pos,
+            1,
+            &Arc::new(AtomicBool::new(false)),
+            &mut |event| {
+                if let SearchEvent::Iteration(info) = event {
+                    lines.push(format_info_line(info));
+                }
+            },
+        );
+
+        let fail_high = lines
+            .iter()
+            .find(|line| line.starts_with("info depth 2 "))
+            .unwrap();
+        assert!(fail_high.contains(" lowerbound "), "{}", fail_high);
+        // The re-search with a wider window settles on an exact score
+        assert!(!lines.last().unwrap().contains("bound"));
+    }
 }
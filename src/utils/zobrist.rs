@@ -2,53 +2,92 @@
 //!
 //! Zobrist hashing is a method for generating unique keys for chess positions
 //! that is used in transposition tables and other caching mechanisms.
+//!
+//! The keys are generated from a fixed seed, so they are stable for a given
+//! build: the same position hashes the same way in every run.
 
 use crate::bitboard::{Bitboard, CastleRights, Color, Piece, Square};
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
-/// Random 64-bit numbers for Zobrist hashing
+/// Seed the key tables are generated from unless `set_zobrist_seed` says otherwise
 ///
-/// We use a large array of random numbers to ensure minimal collisions.
-/// The structure is: [piece][color][square]
-pub static ZOBRIST_PIECE_SQUARE: Lazy<[[[u64; 64]; 2]; 6]> = Lazy::new(|| {
-    use rand::Rng;
-    let mut rng = rand::thread_rng();
-    let mut table = [[[0u64; 64]; 2]; 6];
-
-    for piece in 0..6 {
-        for color in 0..2 {
-            for square in 0..64 {
-                table[piece][color][square] = rng.gen();
+/// Keys are therefore identical in every run of the same build, so hashes can
+/// be compared across processes (opening books, saved transposition tables).
+pub const ZOBRIST_SEED: u64 = 0x4D34_4B5F_5A4F_4252;
+
+/// Seed chosen for this process, fixed once the keys are first used
+static SEED: OnceCell<u64> = OnceCell::new();
+
+/// Use `seed` instead of `ZOBRIST_SEED` for this process
+///
+/// Must be called before any position is hashed; once the keys have been
+/// generated the seed can no longer change and the rejected seed is returned.
+pub fn set_zobrist_seed(seed: u64) -> Result<(), u64> {
+    SEED.set(seed)
+}
+
+/// A complete set of Zobrist keys
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ZobristKeys {
+    /// Keys by [piece][color][square]
+    pub piece_square: [[[u64; 64]; 2]; 6],
+    /// Key toggled when Black is to move
+    pub black_to_move: u64,
+    /// Keys by castling rights bits
+    pub castle: [u64; 16],
+    /// Keys by en passant file
+    pub en_passant: [u64; 8],
+}
+
+impl ZobristKeys {
+    /// Generate the keys deterministically from `seed`
+    pub fn from_seed(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut keys = ZobristKeys {
+            piece_square: [[[0u64; 64]; 2]; 6],
+            black_to_move: 0,
+            castle: [0u64; 16],
+            en_passant: [0u64; 8],
+        };
+
+        for piece in 0..6 {
+            for color in 0..2 {
+                for square in 0..64 {
+                    keys.piece_square[piece][color][square] = rng.gen();
+                }
             }
         }
+        keys.black_to_move = rng.gen();
+        for key in keys.castle.iter_mut() {
+            *key = rng.gen();
+        }
+        for key in keys.en_passant.iter_mut() {
+            *key = rng.gen();
+        }
+        keys
     }
-    table
-});
+}
+
+/// The keys used by this process
+pub static ZOBRIST_KEYS: Lazy<ZobristKeys> =
+    Lazy::new(|| ZobristKeys::from_seed(*SEED.get_or_init(|| ZOBRIST_SEED)));
+
+/// Random 64-bit numbers for Zobrist hashing
+///
+/// We use a large array of random numbers to ensure minimal collisions.
+/// The structure is: [piece][color][square]
+pub static ZOBRIST_PIECE_SQUARE: Lazy<[[[u64; 64]; 2]; 6]> = Lazy::new(|| ZOBRIST_KEYS.piece_square);
 
 /// Random number for black to move
-pub static ZOBRIST_BLACK_TO_MOVE: Lazy<u64> = Lazy::new(|| rand::random());
+pub static ZOBRIST_BLACK_TO_MOVE: Lazy<u64> = Lazy::new(|| ZOBRIST_KEYS.black_to_move);
 
 /// Random numbers for castling rights
-pub static ZOBRIST_CASTLE: Lazy<[u64; 16]> = Lazy::new(|| {
-    use rand::Rng;
-    let mut rng = rand::thread_rng();
-    let mut table = [0u64; 16];
-    for i in 0..16 {
-        table[i] = rng.gen();
-    }
-    table
-});
+pub static ZOBRIST_CASTLE: Lazy<[u64; 16]> = Lazy::new(|| ZOBRIST_KEYS.castle);
 
 /// Random numbers for en passant files
-pub static ZOBRIST_EN_PASSANT: Lazy<[u64; 8]> = Lazy::new(|| {
-    use rand::Rng;
-    let mut rng = rand::thread_rng();
-    let mut table = [0u64; 8];
-    for i in 0..8 {
-        table[i] = rng.gen();
-    }
-    table
-});
+pub static ZOBRIST_EN_PASSANT: Lazy<[u64; 8]> = Lazy::new(|| ZOBRIST_KEYS.en_passant);
 
 /// Zobrist hash for a chess position
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Default)]
@@ -202,4 +241,16 @@ mod tests {
         hash.update_en_passant(Some(Square::E3), None);
         assert_eq!(hash.value(), original);
     }
+
+    #[test]
+    fn test_seeded_keys_are_reproducible() {
+        assert_eq!(ZobristKeys::from_seed(ZOBRIST_SEED), ZobristKeys::from_seed(ZOBRIST_SEED));
+        assert_ne!(ZobristKeys::from_seed(ZOBRIST_SEED), ZobristKeys::from_seed(ZOBRIST_SEED + 1));
+
+        // The process-wide keys come from the default seed, and it can no
+        // longer be changed once they are in use
+        assert_eq!(*ZOBRIST_KEYS, ZobristKeys::from_seed(ZOBRIST_SEED));
+        assert_eq!(*ZOBRIST_BLACK_TO_MOVE, ZOBRIST_KEYS.black_to_move);
+        assert_eq!(set_zobrist_seed(7), Err(7));
+    }
 }